@@ -0,0 +1,83 @@
+//! Benchmark corpus for the tokenizer. It is not meant to compile; it mixes
+//! the constructs that dominate real code: declarations, long comments,
+//! string literals, numbers and operators.
+
+const std = @import("std");
+const mem = std.mem;
+const Allocator = std.mem.Allocator;
+const assert = std.debug.assert;
+
+/// A growable list of `T` that stores its length alongside a slice of
+/// capacity. Documentation comments like this one are common in library
+/// code and tend to be long, so they matter for throughput.
+pub fn List(comptime T: type) type {
+    return struct {
+        items: []T = &[_]T{},
+        capacity: usize = 0,
+
+        const Self = @This();
+
+        pub fn append(self: *Self, gpa: Allocator, item: T) Allocator.Error!void {
+            if (self.items.len == self.capacity) {
+                // Grow by half again, with a floor so tiny lists do not
+                // reallocate on every push.
+                const new_capacity = @max(8, self.capacity +| self.capacity / 2);
+                const new_memory = try gpa.realloc(self.allocatedSlice(), new_capacity);
+                self.items.ptr = new_memory.ptr;
+                self.capacity = new_memory.len;
+            }
+            self.items.len += 1;
+            self.items[self.items.len - 1] = item;
+        }
+
+        fn allocatedSlice(self: Self) []T {
+            return self.items.ptr[0..self.capacity];
+        }
+    };
+}
+
+const Color = enum(u8) { red = 0xff, green = 0b0101_0101, blue = 0o17, _ };
+
+fn describe(color: Color) []const u8 {
+    return switch (color) {
+        .red => "a warm colour, \"red\", often used for errors\n",
+        .green => "green: \x1b[32mcolour\x1b[0m with an escape sequence",
+        .blue => "blue \u{1F499} with a unicode escape",
+        _ => "unknown",
+    };
+}
+
+test "float and integer literals" {
+    const values = [_]f64{ 1.0, 2.5e10, 0x1.8p3, 6.022_140_76e23, 1e-9 };
+    var sum: f64 = 0;
+    for (values) |value| sum += value;
+    try std.testing.expect(sum > 0);
+    const mask: u32 = (1 << 31) | 0x0000_ffff;
+    try std.testing.expectEqual(@as(u32, 0x8000_ffff), mask & ~@as(u32, 0));
+}
+
+const help_text =
+    \\Usage: tool [options] <file>...
+    \\
+    \\Options:
+    \\  -h, --help       Print this help and exit
+    \\  -o <path>        Write output to <path>
+    \\
+;
+
+pub fn main() !void {
+    var gpa_state: std.heap.GeneralPurposeAllocator(.{}) = .{};
+    defer _ = gpa_state.deinit();
+    const gpa = gpa_state.allocator();
+
+    var list: List(u32) = .{};
+    var i: u32 = 0;
+    while (i < 1000) : (i += 1) {
+        try list.append(gpa, i *% 31 +% 7);
+    }
+    const c = 'x';
+    const newline = '\n';
+    if (c != newline and list.items.len >= 1000 or false) {
+        std.debug.print("{s} {d}\n", .{ help_text, list.items.len });
+    }
+}
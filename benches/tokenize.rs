@@ -0,0 +1,50 @@
+//! Tokenizer throughput in MB/s. Run with `cargo bench --bench tokenize`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use zig_in_rust::zig::tokenizer::{tokenize, TokenReader, TokenStream};
+
+const CORPUS: &[u8] = include_bytes!("corpus.zig");
+const TARGET_SIZE: usize = 16 << 20;
+const RUNS: usize = 10;
+
+fn measure(name: &str, source: &[u8], mut run: impl FnMut(&[u8]) -> usize) {
+    let mut best = f64::INFINITY;
+    let mut tokens = 0;
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        tokens = black_box(run(black_box(source)));
+        best = best.min(start.elapsed().as_secs_f64());
+    }
+    let megabytes = source.len() as f64 / 1e6;
+    println!(
+        "{name:<16} {:>8.1} MB/s  ({tokens} tokens, {megabytes:.1} MB, best of {RUNS})",
+        megabytes / best
+    );
+}
+
+fn main() {
+    let mut source = Vec::with_capacity(TARGET_SIZE + CORPUS.len());
+    while source.len() < TARGET_SIZE {
+        source.extend_from_slice(CORPUS);
+    }
+
+    measure("tokenize", &source, |source| {
+        tokenize(source).unwrap().len()
+    });
+    measure("token_stream", &source, |source| {
+        TokenStream::new(source).count()
+    });
+    measure("with_trivia", &source, |source| {
+        TokenStream::with_trivia(source).count()
+    });
+    let mut sentinel_source = source.clone();
+    sentinel_source.push(0);
+    measure("with_sentinel", &sentinel_source, |source| {
+        TokenStream::with_sentinel(source).count()
+    });
+    measure("token_reader", &source, |source| {
+        TokenReader::new(source).count()
+    });
+}
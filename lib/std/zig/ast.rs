@@ -0,0 +1,1762 @@
+//! Abstract syntax tree for Zig source, ported from upstream `std.zig.Ast`.
+//!
+//! Nodes, `extra_data` and errors use upstream's layout exactly: every node
+//! has a tag, a main token and two `u32`s of data whose meaning depends on
+//! the tag, and anything that does not fit is stored in `extra_data`. Index
+//! 0 doubles as "none" for both nodes and tokens, since node 0 is always the
+//! root and token 0 can never be an optional operand.
+
+use std::fmt;
+
+use crate::zig::parse::Parser;
+use crate::zig::tokenizer::{tokenize, Loc, SourceTooLarge, Tag, TokenList};
+
+pub type TokenIndex = u32;
+pub type NodeIndex = u32;
+pub type ByteOffset = u32;
+
+/// The "no node" value of optional node operands.
+pub const NULL_NODE: NodeIndex = 0;
+
+/// What the root node holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// A container: the root's data is the range of its members in
+    /// `extra_data`.
+    #[default]
+    Zig,
+    /// A single expression, whose node is the root's `lhs`.
+    Zon,
+}
+
+pub struct Ast<'a> {
+    pub source: &'a [u8],
+    pub tokens: TokenList,
+    pub nodes: NodeList,
+    pub extra_data: Vec<u32>,
+    pub mode: Mode,
+    pub errors: Vec<Error>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Node {
+    pub tag: NodeTag,
+    pub main_token: TokenIndex,
+    pub data: Data,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Data {
+    pub lhs: u32,
+    pub rhs: u32,
+}
+
+/// Nodes stored as parallel arrays, like upstream's `MultiArrayList(Node)`.
+#[derive(Debug, Clone, Default)]
+pub struct NodeList {
+    tags: Vec<NodeTag>,
+    main_tokens: Vec<TokenIndex>,
+    datas: Vec<Data>,
+}
+
+impl NodeList {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            tags: Vec::with_capacity(capacity),
+            main_tokens: Vec::with_capacity(capacity),
+            datas: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, node: Node) -> NodeIndex {
+        let index = self.len() as NodeIndex;
+        self.tags.push(node.tag);
+        self.main_tokens.push(node.main_token);
+        self.datas.push(node.data);
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    pub fn tags(&self) -> &[NodeTag] {
+        &self.tags
+    }
+
+    pub fn main_tokens(&self) -> &[TokenIndex] {
+        &self.main_tokens
+    }
+
+    pub fn datas(&self) -> &[Data] {
+        &self.datas
+    }
+
+    pub fn get(&self, index: NodeIndex) -> Node {
+        let index = index as usize;
+        Node {
+            tag: self.tags[index],
+            main_token: self.main_tokens[index],
+            data: self.datas[index],
+        }
+    }
+
+    pub fn set(&mut self, index: NodeIndex, node: Node) {
+        let index = index as usize;
+        self.tags[index] = node.tag;
+        self.main_tokens[index] = node.main_token;
+        self.datas[index] = node.data;
+    }
+
+    pub(crate) fn data_mut(&mut self, index: NodeIndex) -> &mut Data {
+        &mut self.datas[index as usize]
+    }
+
+    pub(crate) fn pop(&mut self) {
+        self.tags.pop();
+        self.main_tokens.pop();
+        self.datas.pop();
+    }
+}
+
+/// Declares [`NodeTag`] along with its upstream names.
+macro_rules! define_node_tags {
+    ($($(#[$attr:meta])* $variant:ident $name:literal,)*) => {
+        /// Node kinds, in the same order as upstream's `Ast.Node.Tag`. The
+        /// comments give the meaning of `lhs` and `rhs`; "extra" means an
+        /// index into `extra_data` where the named struct is stored, and
+        /// `extra_data[lhs..rhs]` means the data is a range of node indices
+        /// stored there directly.
+        #[repr(u8)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum NodeTag {
+            $($(#[$attr])* $variant,)*
+        }
+
+        impl NodeTag {
+            /// Every tag, in declaration order.
+            pub const ALL: &'static [NodeTag] = &[$(NodeTag::$variant,)*];
+
+            /// The tag's name in upstream `std.zig.Ast.Node.Tag`, such as
+            /// `fn_decl` or `@"if"` without the quoting: `if`.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(NodeTag::$variant => $name,)*
+                }
+            }
+        }
+    };
+}
+
+define_node_tags! {
+    /// In `Mode::Zig`, `extra_data[lhs..rhs]` are the members. In
+    /// `Mode::Zon`, `lhs` is the expression.
+    Root "root",
+    /// `usingnamespace lhs;`. `main_token` is `usingnamespace`.
+    Usingnamespace "usingnamespace",
+    /// `test "name" rhs`. `lhs` is the name token, string literal or
+    /// identifier, or 0.
+    TestDecl "test_decl",
+    /// `lhs` is extra [`GlobalVarDecl`], `rhs` the initializer or 0.
+    /// `main_token` is `var` or `const`.
+    GlobalVarDecl "global_var_decl",
+    /// `var a: x align(y) = rhs`. `lhs` is extra [`LocalVarDecl`].
+    LocalVarDecl "local_var_decl",
+    /// `var a: lhs = rhs`. Either may be 0.
+    SimpleVarDecl "simple_var_decl",
+    /// `var a align(lhs) = rhs`. `rhs` may be 0.
+    AlignedVarDecl "aligned_var_decl",
+    /// `errdefer rhs` or `errdefer |lhs| rhs`; `lhs` is the payload token
+    /// or 0.
+    Errdefer "errdefer",
+    /// `defer rhs`.
+    Defer "defer",
+    /// `lhs catch rhs` or `lhs catch |err| rhs`; the payload is at
+    /// `main_token + 2` when present.
+    Catch "catch",
+    /// `lhs.a`. `main_token` is the dot, `rhs` the identifier token.
+    FieldAccess "field_access",
+    /// `lhs.?`. `main_token` is the dot, `rhs` the `?` token.
+    UnwrapOptional "unwrap_optional",
+    /// `lhs == rhs`; this and the following binary operators have the
+    /// operator as `main_token`.
+    EqualEqual "equal_equal",
+    BangEqual "bang_equal",
+    LessThan "less_than",
+    GreaterThan "greater_than",
+    LessOrEqual "less_or_equal",
+    GreaterOrEqual "greater_or_equal",
+    AssignMul "assign_mul",
+    AssignDiv "assign_div",
+    AssignMod "assign_mod",
+    AssignAdd "assign_add",
+    AssignSub "assign_sub",
+    AssignShl "assign_shl",
+    AssignShlSat "assign_shl_sat",
+    AssignShr "assign_shr",
+    AssignBitAnd "assign_bit_and",
+    AssignBitXor "assign_bit_xor",
+    AssignBitOr "assign_bit_or",
+    AssignMulWrap "assign_mul_wrap",
+    AssignAddWrap "assign_add_wrap",
+    AssignSubWrap "assign_sub_wrap",
+    AssignMulSat "assign_mul_sat",
+    AssignAddSat "assign_add_sat",
+    AssignSubSat "assign_sub_sat",
+    Assign "assign",
+    /// `a, b, c = rhs`. `lhs` is an index into `extra_data` holding the
+    /// number of targets followed by the target nodes. `main_token` is `=`.
+    AssignDestructure "assign_destructure",
+    MergeErrorSets "merge_error_sets",
+    Mul "mul",
+    Div "div",
+    Mod "mod",
+    ArrayMult "array_mult",
+    MulWrap "mul_wrap",
+    MulSat "mul_sat",
+    Add "add",
+    Sub "sub",
+    ArrayCat "array_cat",
+    AddWrap "add_wrap",
+    SubWrap "sub_wrap",
+    AddSat "add_sat",
+    SubSat "sub_sat",
+    Shl "shl",
+    ShlSat "shl_sat",
+    Shr "shr",
+    BitAnd "bit_and",
+    BitXor "bit_xor",
+    BitOr "bit_or",
+    Orelse "orelse",
+    BoolAnd "bool_and",
+    BoolOr "bool_or",
+    /// `!lhs`; this and the following prefix operators have the operator
+    /// as `main_token` and an unused `rhs`.
+    BoolNot "bool_not",
+    Negation "negation",
+    BitNot "bit_not",
+    NegationWrap "negation_wrap",
+    AddressOf "address_of",
+    Try "try",
+    Await "await",
+    OptionalType "optional_type",
+    /// `[lhs]rhs`.
+    ArrayType "array_type",
+    /// `[lhs:a]b`. `rhs` is extra [`ArrayTypeSentinel`].
+    ArrayTypeSentinel "array_type_sentinel",
+    /// `[*]align(lhs) rhs`, `*align(lhs) rhs` or `[]rhs`. `lhs` may be 0.
+    /// `main_token` is the asterisk for single and many-item pointers and
+    /// the `[` for slices.
+    PtrTypeAligned "ptr_type_aligned",
+    /// `[*:lhs]rhs` or `[:lhs]rhs`.
+    PtrTypeSentinel "ptr_type_sentinel",
+    /// `lhs` is extra [`PtrType`], `rhs` the child type.
+    PtrType "ptr_type",
+    /// `lhs` is extra [`PtrTypeBitRange`], `rhs` the child type.
+    PtrTypeBitRange "ptr_type_bit_range",
+    /// `lhs[rhs..]`. `main_token` is the `[`.
+    SliceOpen "slice_open",
+    /// `lhs[a..b]`. `rhs` is extra [`Slice`].
+    Slice "slice",
+    /// `lhs[a..b :c]`. `rhs` is extra [`SliceSentinel`]; its `end` may be 0.
+    SliceSentinel "slice_sentinel",
+    /// `lhs.*`.
+    Deref "deref",
+    /// `lhs[rhs]`.
+    ArrayAccess "array_access",
+    /// `lhs{rhs}`. `main_token` is the `{`.
+    ArrayInitOne "array_init_one",
+    /// `lhs{rhs,}`.
+    ArrayInitOneComma "array_init_one_comma",
+    /// `.{lhs, rhs}`; either may be 0.
+    ArrayInitDotTwo "array_init_dot_two",
+    ArrayInitDotTwoComma "array_init_dot_two_comma",
+    /// `.{a, b, c}`, elements in `extra_data[lhs..rhs]`.
+    ArrayInitDot "array_init_dot",
+    ArrayInitDotComma "array_init_dot_comma",
+    /// `lhs{a, b, c}`. `rhs` is extra [`SubRange`].
+    ArrayInit "array_init",
+    ArrayInitComma "array_init_comma",
+    /// `lhs{.a = rhs}`, or `lhs{}` when `rhs` is 0.
+    StructInitOne "struct_init_one",
+    StructInitOneComma "struct_init_one_comma",
+    /// `.{.a = lhs, .b = rhs}`; either may be 0. The field name is two
+    /// tokens before the first token of each initializer.
+    StructInitDotTwo "struct_init_dot_two",
+    StructInitDotTwoComma "struct_init_dot_two_comma",
+    /// `.{.a = b, .c = d}`, initializers in `extra_data[lhs..rhs]`.
+    StructInitDot "struct_init_dot",
+    StructInitDotComma "struct_init_dot_comma",
+    /// `lhs{.a = b, .c = d}`. `rhs` is extra [`SubRange`].
+    StructInit "struct_init",
+    StructInitComma "struct_init_comma",
+    /// `lhs(rhs)`, or `lhs()` when `rhs` is 0. `main_token` is the `(`.
+    CallOne "call_one",
+    CallOneComma "call_one_comma",
+    AsyncCallOne "async_call_one",
+    AsyncCallOneComma "async_call_one_comma",
+    /// `lhs(a, b, c)`. `rhs` is extra [`SubRange`].
+    Call "call",
+    CallComma "call_comma",
+    AsyncCall "async_call",
+    AsyncCallComma "async_call_comma",
+    /// `switch(lhs) {}`. `rhs` is extra [`SubRange`] of the prongs.
+    /// `main_token` is `switch`, or the label for a labeled switch.
+    Switch "switch",
+    SwitchComma "switch_comma",
+    /// `lhs => rhs`, or `else => rhs` when `lhs` is 0. `main_token` is the
+    /// `=>`; any payload follows it.
+    SwitchCaseOne "switch_case_one",
+    /// `inline lhs => rhs`.
+    SwitchCaseInlineOne "switch_case_inline_one",
+    /// `a, b, c => rhs`. `lhs` is extra [`SubRange`].
+    SwitchCase "switch_case",
+    SwitchCaseInline "switch_case_inline",
+    /// `lhs...rhs`.
+    SwitchRange "switch_range",
+    /// `while (lhs) rhs` or `while (lhs) |x| rhs`.
+    WhileSimple "while_simple",
+    /// `while (lhs) : (a) b`. `rhs` is extra [`WhileCont`].
+    WhileCont "while_cont",
+    /// `while (lhs) : (a) b else c`. `rhs` is extra [`While`].
+    While "while",
+    /// `for (lhs) rhs`.
+    ForSimple "for_simple",
+    /// `rhs` is a packed [`For`]. `extra_data[lhs..]` holds the inputs,
+    /// then the body, then the else branch if there is one.
+    For "for",
+    /// `lhs..rhs`, `rhs` may be 0. Only appears as a for input.
+    ForRange "for_range",
+    /// `if (lhs) rhs` or `if (lhs) |a| rhs`.
+    IfSimple "if_simple",
+    /// `if (lhs) a else b`. `rhs` is extra [`If`].
+    If "if",
+    /// `suspend lhs`; `lhs` may be 0.
+    Suspend "suspend",
+    /// `resume lhs`.
+    Resume "resume",
+    /// `continue :lhs rhs`. Both may be 0; `lhs` is the label token, and
+    /// `rhs` is the operand of a continue targeting a labeled switch.
+    Continue "continue",
+    /// `break :lhs rhs`. Both may be 0; `lhs` is the label token.
+    Break "break",
+    /// `return lhs`; `lhs` may be 0.
+    Return "return",
+    /// `fn (a: lhs) rhs`. `lhs` may be 0. Parameters are only stored when
+    /// they have a type expression: `anytype` and `...` are found by
+    /// scanning tokens.
+    FnProtoSimple "fn_proto_simple",
+    /// `fn (a: b, c: d) rhs`. `lhs` is extra [`SubRange`] of the
+    /// parameter types.
+    FnProtoMulti "fn_proto_multi",
+    /// `fn (a: b) addrspace(e) linksection(f) callconv(g) rhs`. `lhs` is
+    /// extra [`FnProtoOne`].
+    FnProtoOne "fn_proto_one",
+    /// Like `fn_proto_one` with any number of parameters. `lhs` is extra
+    /// [`FnProto`].
+    FnProto "fn_proto",
+    /// `lhs` is a prototype, `rhs` the body block.
+    FnDecl "fn_decl",
+    /// `anyframe->rhs`. `lhs` is the arrow token.
+    AnyframeType "anyframe_type",
+    /// The following literals are all `main_token`.
+    AnyframeLiteral "anyframe_literal",
+    CharLiteral "char_literal",
+    NumberLiteral "number_literal",
+    UnreachableLiteral "unreachable_literal",
+    Identifier "identifier",
+    /// `.a`. `main_token` is the identifier; a decl literal such as
+    /// `.init(x)` is a call on this node.
+    EnumLiteral "enum_literal",
+    StringLiteral "string_literal",
+    /// Consecutive `\\` lines, from token `lhs` through token `rhs`.
+    MultilineStringLiteral "multiline_string_literal",
+    /// `(lhs)`. `rhs` is the `)` token.
+    GroupedExpression "grouped_expression",
+    /// `@a(lhs, rhs)`; either may be 0.
+    BuiltinCallTwo "builtin_call_two",
+    BuiltinCallTwoComma "builtin_call_two_comma",
+    /// `@a(b, c, d)`, arguments in `extra_data[lhs..rhs]`.
+    BuiltinCall "builtin_call",
+    BuiltinCallComma "builtin_call_comma",
+    /// `error{a, b}`. `rhs` is the `}` token.
+    ErrorSetDecl "error_set_decl",
+    /// `struct {}`, `union {}`, `opaque {}` or `enum {}`, members in
+    /// `extra_data[lhs..rhs]`. `main_token` is the keyword; a preceding
+    /// `extern` or `packed` is part of the node.
+    ContainerDecl "container_decl",
+    ContainerDeclTrailing "container_decl_trailing",
+    /// Like `container_decl` with members `lhs` and `rhs`, either 0.
+    ContainerDeclTwo "container_decl_two",
+    ContainerDeclTwoTrailing "container_decl_two_trailing",
+    /// `struct(lhs)` or `union(lhs)`. `rhs` is extra [`SubRange`].
+    ContainerDeclArg "container_decl_arg",
+    ContainerDeclArgTrailing "container_decl_arg_trailing",
+    /// `union(enum) {}`, members in `extra_data[lhs..rhs]`.
+    TaggedUnion "tagged_union",
+    TaggedUnionTrailing "tagged_union_trailing",
+    /// `union(enum) {}` with members `lhs` and `rhs`, either 0.
+    TaggedUnionTwo "tagged_union_two",
+    TaggedUnionTwoTrailing "tagged_union_two_trailing",
+    /// `union(enum(lhs)) {}`. `rhs` is extra [`SubRange`].
+    TaggedUnionEnumTag "tagged_union_enum_tag",
+    TaggedUnionEnumTagTrailing "tagged_union_enum_tag_trailing",
+    /// `a: lhs = rhs`; `rhs` may be 0. `main_token` is the field name, or
+    /// the first token of the type for tuple-like fields.
+    ContainerFieldInit "container_field_init",
+    /// `a: lhs align(rhs)`.
+    ContainerFieldAlign "container_field_align",
+    /// `a: lhs align(c) = d`. `rhs` is extra [`ContainerField`].
+    ContainerField "container_field",
+    /// `comptime lhs`.
+    Comptime "comptime",
+    /// `nosuspend lhs`.
+    Nosuspend "nosuspend",
+    /// `{lhs rhs}`; either may be 0. `main_token` is the `{`, and a label
+    /// precedes it.
+    BlockTwo "block_two",
+    BlockTwoSemicolon "block_two_semicolon",
+    /// `{}`, statements in `extra_data[lhs..rhs]`.
+    Block "block",
+    BlockSemicolon "block_semicolon",
+    /// `asm(lhs)`. `rhs` is the `)` token.
+    AsmSimple "asm_simple",
+    /// `asm(lhs, a)`. `rhs` is extra [`Asm`].
+    Asm "asm",
+    /// `[a] "b" (c)` or `[a] "b" (-> lhs)`. `lhs` may be 0, `rhs` is the
+    /// `)` token and `main_token` the name.
+    AsmOutput "asm_output",
+    /// `[a] "b" (lhs)`. `rhs` is the `)` token and `main_token` the name.
+    AsmInput "asm_input",
+    /// `error.a`. `lhs` is the `.` token and `rhs` the identifier token;
+    /// either is 0 if it is missing.
+    ErrorValue "error_value",
+    /// `lhs!rhs`. `main_token` is the `!`.
+    ErrorUnion "error_union",
+}
+
+/// A struct stored in `extra_data` as consecutive `u32`s.
+pub trait Extra: Sized {
+    const LEN: usize;
+
+    fn read(data: &[u32]) -> Self;
+
+    fn write(&self, out: &mut Vec<u32>);
+}
+
+macro_rules! define_extra {
+    ($($(#[$attr:meta])* $name:ident { $($field:ident),* $(,)? })*) => {
+        $(
+            $(#[$attr])*
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+            pub struct $name {
+                $(pub $field: u32,)*
+            }
+
+            impl Extra for $name {
+                const LEN: usize = [$(stringify!($field)),*].len();
+
+                fn read(data: &[u32]) -> Self {
+                    let mut data = data.iter().copied();
+                    Self {
+                        $($field: data.next().unwrap(),)*
+                    }
+                }
+
+                fn write(&self, out: &mut Vec<u32>) {
+                    out.extend([$(self.$field),*]);
+                }
+            }
+        )*
+    };
+}
+
+define_extra! {
+    LocalVarDecl { type_node, align_node }
+    ArrayTypeSentinel { sentinel, elem_type }
+    PtrType { sentinel, align_node, addrspace_node }
+    PtrTypeBitRange { sentinel, align_node, addrspace_node, bit_range_start, bit_range_end }
+    /// A range of node indices stored in `extra_data[start..end]`.
+    SubRange { start, end }
+    If { then_expr, else_expr }
+    ContainerField { align_expr, value_expr }
+    GlobalVarDecl { type_node, align_node, addrspace_node, section_node }
+    Slice { start, end }
+    SliceSentinel { start, end, sentinel }
+    While { cont_expr, then_expr, else_expr }
+    WhileCont { cont_expr, then_expr }
+    FnProtoOne { param, align_expr, addrspace_expr, section_expr, callconv_expr }
+    FnProto { params_start, params_end, align_expr, addrspace_expr, section_expr, callconv_expr }
+    Asm { items_start, items_end, rparen }
+}
+
+/// The `rhs` of a `for` node, packed like upstream's
+/// `packed struct(u32) { inputs: u31, has_else: bool }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct For {
+    pub inputs: u32,
+    pub has_else: bool,
+}
+
+impl For {
+    pub fn from_u32(bits: u32) -> Self {
+        Self {
+            inputs: bits & 0x7fff_ffff,
+            has_else: bits >> 31 != 0,
+        }
+    }
+
+    pub fn to_u32(self) -> u32 {
+        debug_assert!(self.inputs <= 0x7fff_ffff);
+        self.inputs | (self.has_else as u32) << 31
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Error {
+    pub tag: ErrorTag,
+    pub is_note: bool,
+    /// The error points just past the end of `token` rather than at it,
+    /// because the token it is about is on a later line.
+    pub token_is_prev: bool,
+    pub token: TokenIndex,
+    /// The token that was expected, for `ExpectedToken`, or the container
+    /// keyword, for `CStyleContainer` and `ZigStyleContainer`.
+    pub expected_tag: Option<Tag>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorTag {
+    AsteriskAfterPtrDeref,
+    ChainedComparisonOperators,
+    DeclBetweenFields,
+    ExpectedBlock,
+    ExpectedBlockOrAssignment,
+    ExpectedBlockOrExpr,
+    ExpectedBlockOrField,
+    ExpectedContainerMembers,
+    ExpectedExpr,
+    ExpectedExprOrAssignment,
+    ExpectedExprOrVarDecl,
+    ExpectedFn,
+    ExpectedInlinable,
+    ExpectedLabelable,
+    ExpectedParamList,
+    ExpectedPrefixExpr,
+    ExpectedPrimaryTypeExpr,
+    ExpectedPubItem,
+    ExpectedReturnType,
+    ExpectedSemiOrElse,
+    ExpectedSemiOrLbrace,
+    ExpectedStatement,
+    ExpectedSuffixOp,
+    ExpectedTypeExpr,
+    ExpectedVarDecl,
+    ExpectedVarDeclOrFn,
+    ExpectedLoopPayload,
+    ExpectedContainer,
+    ExternFnBody,
+    ExtraAddrspaceQualifier,
+    ExtraAlignQualifier,
+    ExtraAllowzeroQualifier,
+    ExtraConstQualifier,
+    ExtraVolatileQualifier,
+    PtrModOnArrayChildType,
+    InvalidBitRange,
+    SameLineDocComment,
+    UnattachedDocComment,
+    TestDocComment,
+    ComptimeDocComment,
+    VarargsNonfinal,
+    ExpectedContinueExpr,
+    ExpectedSemiAfterDecl,
+    ExpectedSemiAfterStmt,
+    ExpectedCommaAfterField,
+    ExpectedCommaAfterArg,
+    ExpectedCommaAfterParam,
+    ExpectedCommaAfterInitializer,
+    ExpectedCommaAfterSwitchProng,
+    ExpectedCommaAfterForOperand,
+    ExpectedCommaAfterCapture,
+    ExpectedInitializer,
+    MismatchedBinaryOpWhitespace,
+    InvalidAmpersandAmpersand,
+    CStyleContainer,
+    ExpectedVarConst,
+    WrongEqualVarDecl,
+    VarConstDecl,
+    ExtraForCapture,
+    ForInputNotCaptured,
+    ZigStyleContainer,
+    PreviousField,
+    NextField,
+    /// `expected_tag` is set.
+    ExpectedToken,
+}
+
+impl<'a> Ast<'a> {
+    /// Tokenizes and parses `source`. Syntax errors do not stop parsing;
+    /// they are collected in `errors` and the tree covers whatever could be
+    /// recovered.
+    pub fn parse(source: &'a [u8], mode: Mode) -> Result<Ast<'a>, SourceTooLarge> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser::new(source, &tokens);
+        match mode {
+            Mode::Zig => parser.parse_root(),
+            Mode::Zon => parser.parse_zon(),
+        }
+        let (nodes, extra_data, errors) = parser.finish();
+        Ok(Ast {
+            source,
+            tokens,
+            nodes,
+            extra_data,
+            mode,
+            errors,
+        })
+    }
+
+    pub fn node(&self, index: NodeIndex) -> Node {
+        self.nodes.get(index)
+    }
+
+    pub fn token_tag(&self, index: TokenIndex) -> Tag {
+        *self.tokens.tag(index as usize)
+    }
+
+    pub fn token_start(&self, index: TokenIndex) -> ByteOffset {
+        self.tokens.starts()[index as usize]
+    }
+
+    pub fn token_slice(&self, index: TokenIndex) -> &'a [u8] {
+        self.tokens.token_slice(self.source, index as usize)
+    }
+
+    /// Reads the struct stored at `index` in `extra_data`.
+    pub fn extra_data<T: Extra>(&self, index: u32) -> T {
+        T::read(&self.extra_data[index as usize..][..T::LEN])
+    }
+
+    /// The top-level declarations and fields of a `Mode::Zig` tree.
+    pub fn root_decls(&self) -> &[NodeIndex] {
+        let Data { lhs, rhs } = self.nodes.datas()[0];
+        &self.extra_data[lhs as usize..rhs as usize]
+    }
+
+    /// Byte offset an error points at.
+    pub fn error_offset(&self, error: &Error) -> ByteOffset {
+        let start = self.token_start(error.token);
+        if error.token_is_prev {
+            start + self.token_slice(error.token).len() as ByteOffset
+        } else {
+            start
+        }
+    }
+
+    /// The source span of a node, from the start of its first token to the
+    /// end of its last.
+    pub fn node_loc(&self, node: NodeIndex) -> Loc {
+        let first = self.token_start(self.first_token(node)) as usize;
+        let last = self
+            .tokens
+            .token_loc(self.source, self.last_token(node) as usize);
+        Loc {
+            start: first,
+            end: last.end,
+        }
+    }
+
+    pub fn node_source(&self, node: NodeIndex) -> &'a [u8] {
+        let loc = self.node_loc(node);
+        &self.source[loc.start..loc.end]
+    }
+
+    pub fn render_error(&self, error: &Error, out: &mut impl fmt::Write) -> fmt::Result {
+        let found = || {
+            self.token_tag(error.token + error.token_is_prev as TokenIndex)
+                .symbol()
+        };
+        let expected = || {
+            error
+                .expected_tag
+                .expect("error has an expected tag")
+                .symbol()
+        };
+        let slice = || String::from_utf8_lossy(self.token_slice(error.token));
+        match error.tag {
+            ErrorTag::AsteriskAfterPtrDeref => {
+                out.write_str("'.*' cannot be followed by '*'; are you missing a space?")
+            }
+            ErrorTag::ChainedComparisonOperators => {
+                out.write_str("comparison operators cannot be chained")
+            }
+            ErrorTag::DeclBetweenFields => {
+                out.write_str("declarations are not allowed between container fields")
+            }
+            ErrorTag::ExpectedBlock => write!(out, "expected block, found '{}'", found()),
+            ErrorTag::ExpectedBlockOrAssignment => {
+                write!(out, "expected block or assignment, found '{}'", found())
+            }
+            ErrorTag::ExpectedBlockOrExpr => {
+                write!(out, "expected block or expression, found '{}'", found())
+            }
+            ErrorTag::ExpectedBlockOrField => {
+                write!(out, "expected block or field, found '{}'", found())
+            }
+            ErrorTag::ExpectedContainerMembers => write!(
+                out,
+                "expected test, comptime, var decl, or container field, found '{}'",
+                found()
+            ),
+            ErrorTag::ExpectedExpr => write!(out, "expected expression, found '{}'", found()),
+            ErrorTag::ExpectedExprOrAssignment => {
+                write!(
+                    out,
+                    "expected expression or assignment, found '{}'",
+                    found()
+                )
+            }
+            ErrorTag::ExpectedExprOrVarDecl => {
+                write!(out, "expected expression or var decl, found '{}'", found())
+            }
+            ErrorTag::ExpectedFn => write!(out, "expected function, found '{}'", found()),
+            ErrorTag::ExpectedInlinable => {
+                write!(out, "expected 'while' or 'for', found '{}'", found())
+            }
+            ErrorTag::ExpectedLabelable => write!(
+                out,
+                "expected 'while', 'for', 'inline', or '{{', found '{}'",
+                found()
+            ),
+            ErrorTag::ExpectedParamList => {
+                write!(out, "expected parameter list, found '{}'", found())
+            }
+            ErrorTag::ExpectedPrefixExpr => {
+                write!(out, "expected prefix expression, found '{}'", found())
+            }
+            ErrorTag::ExpectedPrimaryTypeExpr => {
+                write!(out, "expected primary type expression, found '{}'", found())
+            }
+            ErrorTag::ExpectedPubItem => {
+                out.write_str("expected function or variable declaration after pub")
+            }
+            ErrorTag::ExpectedReturnType => {
+                write!(out, "expected return type expression, found '{}'", found())
+            }
+            ErrorTag::ExpectedSemiOrElse => out.write_str("expected ';' or 'else' after statement"),
+            ErrorTag::ExpectedSemiOrLbrace => {
+                out.write_str("expected ';' or block after function prototype")
+            }
+            ErrorTag::ExpectedStatement => {
+                write!(out, "expected statement, found '{}'", found())
+            }
+            ErrorTag::ExpectedSuffixOp => write!(
+                out,
+                "expected pointer dereference, optional unwrap, or field access, found '{}'",
+                found()
+            ),
+            ErrorTag::ExpectedTypeExpr => {
+                write!(out, "expected type expression, found '{}'", found())
+            }
+            ErrorTag::ExpectedVarDecl => {
+                write!(out, "expected variable declaration, found '{}'", found())
+            }
+            ErrorTag::ExpectedVarDeclOrFn => write!(
+                out,
+                "expected variable declaration or function, found '{}'",
+                found()
+            ),
+            ErrorTag::ExpectedLoopPayload => {
+                write!(out, "expected loop payload, found '{}'", found())
+            }
+            ErrorTag::ExpectedContainer => {
+                write!(out, "expected a struct, enum or union, found '{}'", found())
+            }
+            ErrorTag::ExternFnBody => out.write_str("extern functions have no body"),
+            ErrorTag::ExtraAddrspaceQualifier => out.write_str("extra addrspace qualifier"),
+            ErrorTag::ExtraAlignQualifier => out.write_str("extra align qualifier"),
+            ErrorTag::ExtraAllowzeroQualifier => out.write_str("extra allowzero qualifier"),
+            ErrorTag::ExtraConstQualifier => out.write_str("extra const qualifier"),
+            ErrorTag::ExtraVolatileQualifier => out.write_str("extra volatile qualifier"),
+            ErrorTag::PtrModOnArrayChildType => write!(
+                out,
+                "pointer modifier '{}' not allowed on array child type",
+                self.token_tag(error.token).symbol()
+            ),
+            ErrorTag::InvalidBitRange => {
+                out.write_str("bit range not allowed on slices and arrays")
+            }
+            ErrorTag::SameLineDocComment => out.write_str("same line documentation comment"),
+            ErrorTag::UnattachedDocComment => out.write_str("unattached documentation comment"),
+            ErrorTag::TestDocComment => {
+                out.write_str("documentation comments cannot be attached to tests")
+            }
+            ErrorTag::ComptimeDocComment => {
+                out.write_str("documentation comments cannot be attached to comptime blocks")
+            }
+            ErrorTag::VarargsNonfinal => {
+                out.write_str("function prototype has parameter after varargs")
+            }
+            ErrorTag::ExpectedContinueExpr => {
+                out.write_str("expected ':' before while continue expression")
+            }
+            ErrorTag::ExpectedSemiAfterDecl => out.write_str("expected ';' after declaration"),
+            ErrorTag::ExpectedSemiAfterStmt => out.write_str("expected ';' after statement"),
+            ErrorTag::ExpectedCommaAfterField => out.write_str("expected ',' after field"),
+            ErrorTag::ExpectedCommaAfterArg => out.write_str("expected ',' after argument"),
+            ErrorTag::ExpectedCommaAfterParam => out.write_str("expected ',' after parameter"),
+            ErrorTag::ExpectedCommaAfterInitializer => {
+                out.write_str("expected ',' after initializer")
+            }
+            ErrorTag::ExpectedCommaAfterSwitchProng => {
+                out.write_str("expected ',' after switch prong")
+            }
+            ErrorTag::ExpectedCommaAfterForOperand => {
+                out.write_str("expected ',' after for operand")
+            }
+            ErrorTag::ExpectedCommaAfterCapture => out.write_str("expected ',' after for capture"),
+            ErrorTag::ExpectedInitializer => out.write_str("expected field initializer"),
+            ErrorTag::MismatchedBinaryOpWhitespace => write!(
+                out,
+                "binary operator `{}` has whitespace on one side, but not the other.",
+                self.token_tag(error.token).symbol()
+            ),
+            ErrorTag::InvalidAmpersandAmpersand => out.write_str(
+                "ambiguous use of '&&'; use 'and' for logical AND, \
+                 or change whitespace to ' & &' for bitwise AND",
+            ),
+            ErrorTag::CStyleContainer => write!(out, "'{} {}' is invalid", expected(), slice()),
+            ErrorTag::ZigStyleContainer => write!(
+                out,
+                "to declare a container do 'const {} = {}'",
+                slice(),
+                expected()
+            ),
+            ErrorTag::PreviousField => out.write_str("field before declarations here"),
+            ErrorTag::NextField => out.write_str("field after declarations here"),
+            ErrorTag::ExpectedVarConst => {
+                out.write_str("expected 'var' or 'const' before variable declaration")
+            }
+            ErrorTag::WrongEqualVarDecl => {
+                out.write_str("variable initialized with '==' instead of '='")
+            }
+            ErrorTag::VarConstDecl => out.write_str("use 'var' or 'const' to declare variable"),
+            ErrorTag::ExtraForCapture => out.write_str("extra capture in for loop"),
+            ErrorTag::ForInputNotCaptured => out.write_str("for input is not captured"),
+            ErrorTag::ExpectedToken => {
+                let found = self.token_tag(error.token + error.token_is_prev as TokenIndex);
+                match found {
+                    Tag::Invalid => write!(out, "expected '{}', found invalid bytes", expected()),
+                    _ => write!(out, "expected '{}', found '{}'", expected(), found.symbol()),
+                }
+            }
+        }
+    }
+
+    /// Convenience wrapper around [`Ast::render_error`].
+    pub fn error_message(&self, error: &Error) -> String {
+        let mut message = String::new();
+        self.render_error(error, &mut message).unwrap();
+        message
+    }
+}
+
+impl Ast<'_> {
+    pub fn first_token(&self, node: NodeIndex) -> TokenIndex {
+        let tags = self.nodes.tags();
+        let datas = self.nodes.datas();
+        let main_tokens = self.nodes.main_tokens();
+        let token_tags = self.tokens.tags();
+        let token_tag = |index: TokenIndex| token_tags[index as usize];
+        let mut end_offset = 0;
+        let mut n = node as usize;
+        loop {
+            match tags[n] {
+                NodeTag::Root => return 0,
+
+                NodeTag::TestDecl
+                | NodeTag::Errdefer
+                | NodeTag::Defer
+                | NodeTag::BoolNot
+                | NodeTag::Negation
+                | NodeTag::BitNot
+                | NodeTag::NegationWrap
+                | NodeTag::AddressOf
+                | NodeTag::Try
+                | NodeTag::Await
+                | NodeTag::OptionalType
+                | NodeTag::Switch
+                | NodeTag::SwitchComma
+                | NodeTag::IfSimple
+                | NodeTag::If
+                | NodeTag::Suspend
+                | NodeTag::Resume
+                | NodeTag::Continue
+                | NodeTag::Break
+                | NodeTag::Return
+                | NodeTag::AnyframeType
+                | NodeTag::Identifier
+                | NodeTag::AnyframeLiteral
+                | NodeTag::CharLiteral
+                | NodeTag::NumberLiteral
+                | NodeTag::UnreachableLiteral
+                | NodeTag::StringLiteral
+                | NodeTag::MultilineStringLiteral
+                | NodeTag::GroupedExpression
+                | NodeTag::BuiltinCallTwo
+                | NodeTag::BuiltinCallTwoComma
+                | NodeTag::BuiltinCall
+                | NodeTag::BuiltinCallComma
+                | NodeTag::ErrorSetDecl
+                | NodeTag::Comptime
+                | NodeTag::Nosuspend
+                | NodeTag::AsmSimple
+                | NodeTag::Asm
+                | NodeTag::ArrayType
+                | NodeTag::ArrayTypeSentinel
+                | NodeTag::ErrorValue => return main_tokens[n] - end_offset,
+
+                NodeTag::ArrayInitDot
+                | NodeTag::ArrayInitDotComma
+                | NodeTag::ArrayInitDotTwo
+                | NodeTag::ArrayInitDotTwoComma
+                | NodeTag::StructInitDot
+                | NodeTag::StructInitDotComma
+                | NodeTag::StructInitDotTwo
+                | NodeTag::StructInitDotTwoComma
+                | NodeTag::EnumLiteral => return main_tokens[n] - 1 - end_offset,
+
+                NodeTag::Catch
+                | NodeTag::FieldAccess
+                | NodeTag::UnwrapOptional
+                | NodeTag::EqualEqual
+                | NodeTag::BangEqual
+                | NodeTag::LessThan
+                | NodeTag::GreaterThan
+                | NodeTag::LessOrEqual
+                | NodeTag::GreaterOrEqual
+                | NodeTag::AssignMul
+                | NodeTag::AssignDiv
+                | NodeTag::AssignMod
+                | NodeTag::AssignAdd
+                | NodeTag::AssignSub
+                | NodeTag::AssignShl
+                | NodeTag::AssignShlSat
+                | NodeTag::AssignShr
+                | NodeTag::AssignBitAnd
+                | NodeTag::AssignBitXor
+                | NodeTag::AssignBitOr
+                | NodeTag::AssignMulWrap
+                | NodeTag::AssignAddWrap
+                | NodeTag::AssignSubWrap
+                | NodeTag::AssignMulSat
+                | NodeTag::AssignAddSat
+                | NodeTag::AssignSubSat
+                | NodeTag::Assign
+                | NodeTag::MergeErrorSets
+                | NodeTag::Mul
+                | NodeTag::Div
+                | NodeTag::Mod
+                | NodeTag::ArrayMult
+                | NodeTag::MulWrap
+                | NodeTag::MulSat
+                | NodeTag::Add
+                | NodeTag::Sub
+                | NodeTag::ArrayCat
+                | NodeTag::AddWrap
+                | NodeTag::SubWrap
+                | NodeTag::AddSat
+                | NodeTag::SubSat
+                | NodeTag::Shl
+                | NodeTag::ShlSat
+                | NodeTag::Shr
+                | NodeTag::BitAnd
+                | NodeTag::BitXor
+                | NodeTag::BitOr
+                | NodeTag::Orelse
+                | NodeTag::BoolAnd
+                | NodeTag::BoolOr
+                | NodeTag::SliceOpen
+                | NodeTag::Slice
+                | NodeTag::SliceSentinel
+                | NodeTag::Deref
+                | NodeTag::ArrayAccess
+                | NodeTag::ArrayInitOne
+                | NodeTag::ArrayInitOneComma
+                | NodeTag::ArrayInit
+                | NodeTag::ArrayInitComma
+                | NodeTag::StructInitOne
+                | NodeTag::StructInitOneComma
+                | NodeTag::StructInit
+                | NodeTag::StructInitComma
+                | NodeTag::CallOne
+                | NodeTag::CallOneComma
+                | NodeTag::Call
+                | NodeTag::CallComma
+                | NodeTag::SwitchRange
+                | NodeTag::ForRange
+                | NodeTag::ErrorUnion => n = datas[n].lhs as usize,
+
+                NodeTag::AssignDestructure => {
+                    let extra = datas[n].lhs as usize;
+                    debug_assert!(self.extra_data[extra] > 0);
+                    n = self.extra_data[extra + 1] as usize;
+                }
+
+                NodeTag::FnDecl
+                | NodeTag::FnProtoSimple
+                | NodeTag::FnProtoMulti
+                | NodeTag::FnProtoOne
+                | NodeTag::FnProto => {
+                    let mut i = main_tokens[n];
+                    while i > 0 {
+                        match token_tag(i - 1) {
+                            Tag::KWExtern
+                            | Tag::KWExport
+                            | Tag::KWPub
+                            | Tag::KWInline
+                            | Tag::KWNoinline
+                            | Tag::StringLiteral => i -= 1,
+                            _ => break,
+                        }
+                    }
+                    return i - end_offset;
+                }
+
+                NodeTag::Usingnamespace => {
+                    let main_token = main_tokens[n];
+                    if main_token > 0 && token_tag(main_token - 1) == Tag::KWPub {
+                        end_offset += 1;
+                    }
+                    return main_token - end_offset;
+                }
+
+                NodeTag::AsyncCallOne
+                | NodeTag::AsyncCallOneComma
+                | NodeTag::AsyncCall
+                | NodeTag::AsyncCallComma => {
+                    end_offset += 1; // async token
+                    n = datas[n].lhs as usize;
+                }
+
+                NodeTag::ContainerFieldInit
+                | NodeTag::ContainerFieldAlign
+                | NodeTag::ContainerField => {
+                    let name_token = main_tokens[n];
+                    if name_token > 0 && token_tag(name_token - 1) == Tag::KWComptime {
+                        end_offset += 1;
+                    }
+                    return name_token - end_offset;
+                }
+
+                NodeTag::GlobalVarDecl
+                | NodeTag::LocalVarDecl
+                | NodeTag::SimpleVarDecl
+                | NodeTag::AlignedVarDecl => {
+                    let mut i = main_tokens[n];
+                    while i > 0 {
+                        match token_tag(i - 1) {
+                            Tag::KWExtern
+                            | Tag::KWExport
+                            | Tag::KWComptime
+                            | Tag::KWPub
+                            | Tag::KWThreadlocal
+                            | Tag::StringLiteral => i -= 1,
+                            _ => break,
+                        }
+                    }
+                    return i - end_offset;
+                }
+
+                NodeTag::Block
+                | NodeTag::BlockSemicolon
+                | NodeTag::BlockTwo
+                | NodeTag::BlockTwoSemicolon => {
+                    // Look for a label.
+                    let lbrace = main_tokens[n];
+                    if lbrace >= 2
+                        && token_tag(lbrace - 1) == Tag::Colon
+                        && token_tag(lbrace - 2) == Tag::Identifier
+                    {
+                        end_offset += 2;
+                    }
+                    return lbrace - end_offset;
+                }
+
+                NodeTag::ContainerDecl
+                | NodeTag::ContainerDeclTrailing
+                | NodeTag::ContainerDeclTwo
+                | NodeTag::ContainerDeclTwoTrailing
+                | NodeTag::ContainerDeclArg
+                | NodeTag::ContainerDeclArgTrailing
+                | NodeTag::TaggedUnion
+                | NodeTag::TaggedUnionTrailing
+                | NodeTag::TaggedUnionTwo
+                | NodeTag::TaggedUnionTwoTrailing
+                | NodeTag::TaggedUnionEnumTag
+                | NodeTag::TaggedUnionEnumTagTrailing => {
+                    let main_token = main_tokens[n];
+                    if matches!(
+                        token_tag(main_token.saturating_sub(1)),
+                        Tag::KWPacked | Tag::KWExtern
+                    ) {
+                        end_offset += 1;
+                    }
+                    return main_token - end_offset;
+                }
+
+                NodeTag::PtrTypeAligned
+                | NodeTag::PtrTypeSentinel
+                | NodeTag::PtrType
+                | NodeTag::PtrTypeBitRange => {
+                    let main_token = main_tokens[n];
+                    let first = match token_tag(main_token) {
+                        Tag::Asterisk | Tag::AsteriskAsterisk
+                            if token_tag(main_token.saturating_sub(1)) == Tag::LBrack =>
+                        {
+                            main_token - 1
+                        }
+                        _ => main_token,
+                    };
+                    return first - end_offset;
+                }
+
+                NodeTag::SwitchCaseOne => {
+                    if datas[n].lhs == 0 {
+                        return main_tokens[n] - 1 - end_offset; // else token
+                    }
+                    n = datas[n].lhs as usize;
+                }
+                NodeTag::SwitchCaseInlineOne => {
+                    if datas[n].lhs == 0 {
+                        return main_tokens[n] - 2 - end_offset; // inline else
+                    }
+                    return self.first_token(datas[n].lhs) - 1 - end_offset;
+                }
+                NodeTag::SwitchCase => {
+                    let items: SubRange = self.extra_data(datas[n].lhs);
+                    debug_assert!(items.end > items.start);
+                    n = self.extra_data[items.start as usize] as usize;
+                }
+                NodeTag::SwitchCaseInline => {
+                    let items: SubRange = self.extra_data(datas[n].lhs);
+                    debug_assert!(items.end > items.start);
+                    return self.first_token(self.extra_data[items.start as usize])
+                        - 1
+                        - end_offset;
+                }
+
+                NodeTag::AsmOutput | NodeTag::AsmInput => {
+                    debug_assert_eq!(token_tag(main_tokens[n] - 1), Tag::LBrack);
+                    return main_tokens[n] - 1 - end_offset;
+                }
+
+                NodeTag::WhileSimple
+                | NodeTag::WhileCont
+                | NodeTag::While
+                | NodeTag::ForSimple
+                | NodeTag::For => {
+                    // Look for a label and inline.
+                    let mut result = main_tokens[n];
+                    if token_tag(result.saturating_sub(1)) == Tag::KWInline {
+                        result -= 1;
+                    }
+                    if token_tag(result.saturating_sub(1)) == Tag::Colon {
+                        result = result.saturating_sub(2);
+                    }
+                    return result - end_offset;
+                }
+            }
+        }
+    }
+
+    pub fn last_token(&self, node: NodeIndex) -> TokenIndex {
+        let tags = self.nodes.tags();
+        let datas = self.nodes.datas();
+        let main_tokens = self.nodes.main_tokens();
+        let token_tags = self.tokens.tags();
+        let mut end_offset = 0;
+        let mut n = node as usize;
+        loop {
+            let Data { lhs, rhs } = datas[n];
+            match tags[n] {
+                NodeTag::Root => return self.tokens.len() as TokenIndex - 1,
+
+                NodeTag::Usingnamespace
+                | NodeTag::BoolNot
+                | NodeTag::Negation
+                | NodeTag::BitNot
+                | NodeTag::NegationWrap
+                | NodeTag::AddressOf
+                | NodeTag::Try
+                | NodeTag::Await
+                | NodeTag::OptionalType
+                | NodeTag::Resume
+                | NodeTag::Nosuspend
+                | NodeTag::Comptime => n = lhs as usize,
+
+                NodeTag::TestDecl
+                | NodeTag::Errdefer
+                | NodeTag::Defer
+                | NodeTag::Catch
+                | NodeTag::EqualEqual
+                | NodeTag::BangEqual
+                | NodeTag::LessThan
+                | NodeTag::GreaterThan
+                | NodeTag::LessOrEqual
+                | NodeTag::GreaterOrEqual
+                | NodeTag::AssignMul
+                | NodeTag::AssignDiv
+                | NodeTag::AssignMod
+                | NodeTag::AssignAdd
+                | NodeTag::AssignSub
+                | NodeTag::AssignShl
+                | NodeTag::AssignShlSat
+                | NodeTag::AssignShr
+                | NodeTag::AssignBitAnd
+                | NodeTag::AssignBitXor
+                | NodeTag::AssignBitOr
+                | NodeTag::AssignMulWrap
+                | NodeTag::AssignAddWrap
+                | NodeTag::AssignSubWrap
+                | NodeTag::AssignMulSat
+                | NodeTag::AssignAddSat
+                | NodeTag::AssignSubSat
+                | NodeTag::Assign
+                | NodeTag::AssignDestructure
+                | NodeTag::MergeErrorSets
+                | NodeTag::Mul
+                | NodeTag::Div
+                | NodeTag::Mod
+                | NodeTag::ArrayMult
+                | NodeTag::MulWrap
+                | NodeTag::MulSat
+                | NodeTag::Add
+                | NodeTag::Sub
+                | NodeTag::ArrayCat
+                | NodeTag::AddWrap
+                | NodeTag::SubWrap
+                | NodeTag::AddSat
+                | NodeTag::SubSat
+                | NodeTag::Shl
+                | NodeTag::ShlSat
+                | NodeTag::Shr
+                | NodeTag::BitAnd
+                | NodeTag::BitXor
+                | NodeTag::BitOr
+                | NodeTag::Orelse
+                | NodeTag::BoolAnd
+                | NodeTag::BoolOr
+                | NodeTag::AnyframeType
+                | NodeTag::ErrorUnion
+                | NodeTag::IfSimple
+                | NodeTag::WhileSimple
+                | NodeTag::ForSimple
+                | NodeTag::FnDecl
+                | NodeTag::PtrTypeAligned
+                | NodeTag::PtrTypeSentinel
+                | NodeTag::PtrType
+                | NodeTag::PtrTypeBitRange
+                | NodeTag::ArrayType
+                | NodeTag::SwitchCaseOne
+                | NodeTag::SwitchCaseInlineOne
+                | NodeTag::SwitchCase
+                | NodeTag::SwitchCaseInline
+                | NodeTag::SwitchRange => n = rhs as usize,
+
+                NodeTag::FnProtoSimple
+                | NodeTag::FnProtoMulti
+                | NodeTag::FnProtoOne
+                | NodeTag::FnProto => {
+                    if rhs == 0 {
+                        // Only after a missing return type was reported.
+                        return main_tokens[n] + end_offset;
+                    }
+                    n = rhs as usize;
+                }
+
+                NodeTag::ForRange => {
+                    if rhs == 0 {
+                        return main_tokens[n] + end_offset;
+                    }
+                    n = rhs as usize;
+                }
+
+                NodeTag::FieldAccess
+                | NodeTag::UnwrapOptional
+                | NodeTag::GroupedExpression
+                | NodeTag::MultilineStringLiteral
+                | NodeTag::ErrorSetDecl
+                | NodeTag::AsmSimple
+                | NodeTag::AsmOutput
+                | NodeTag::AsmInput
+                | NodeTag::ErrorValue => return rhs + end_offset,
+
+                NodeTag::AnyframeLiteral
+                | NodeTag::CharLiteral
+                | NodeTag::NumberLiteral
+                | NodeTag::UnreachableLiteral
+                | NodeTag::Identifier
+                | NodeTag::Deref
+                | NodeTag::EnumLiteral
+                | NodeTag::StringLiteral => return main_tokens[n] + end_offset,
+
+                NodeTag::Return | NodeTag::Suspend => {
+                    if lhs == 0 {
+                        return main_tokens[n] + end_offset;
+                    }
+                    n = lhs as usize;
+                }
+
+                NodeTag::Call | NodeTag::AsyncCall => {
+                    end_offset += 1; // for the rparen
+                    let params: SubRange = self.extra_data(rhs);
+                    debug_assert!(params.end > params.start);
+                    n = self.extra_data[params.end as usize - 1] as usize;
+                }
+                NodeTag::TaggedUnionEnumTag => {
+                    let members: SubRange = self.extra_data(rhs);
+                    if members.end == members.start {
+                        end_offset += 4; // for the rparen + rparen + lbrace + rbrace
+                        n = lhs as usize;
+                    } else {
+                        end_offset += 1; // for the rbrace
+                        n = self.extra_data[members.end as usize - 1] as usize;
+                    }
+                }
+                NodeTag::CallComma
+                | NodeTag::AsyncCallComma
+                | NodeTag::TaggedUnionEnumTagTrailing => {
+                    end_offset += 2; // for the comma/semicolon + rparen/rbrace
+                    let params: SubRange = self.extra_data(rhs);
+                    debug_assert!(params.end > params.start);
+                    n = self.extra_data[params.end as usize - 1] as usize;
+                }
+                NodeTag::Switch => {
+                    let cases: SubRange = self.extra_data(rhs);
+                    if cases.end == cases.start {
+                        end_offset += 3; // rparen, lbrace, rbrace
+                        n = lhs as usize;
+                    } else {
+                        end_offset += 1; // for the rbrace
+                        n = self.extra_data[cases.end as usize - 1] as usize;
+                    }
+                }
+                NodeTag::ContainerDeclArg => {
+                    let members: SubRange = self.extra_data(rhs);
+                    if members.end == members.start {
+                        end_offset += 3; // for the rparen + lbrace + rbrace
+                        n = lhs as usize;
+                    } else {
+                        end_offset += 1; // for the rbrace
+                        n = self.extra_data[members.end as usize - 1] as usize;
+                    }
+                }
+                NodeTag::Asm => {
+                    let extra: Asm = self.extra_data(rhs);
+                    return extra.rparen + end_offset;
+                }
+                NodeTag::ArrayInit | NodeTag::StructInit => {
+                    let elements: SubRange = self.extra_data(rhs);
+                    debug_assert!(elements.end > elements.start);
+                    end_offset += 1; // for the rbrace
+                    n = self.extra_data[elements.end as usize - 1] as usize;
+                }
+                NodeTag::ArrayInitComma
+                | NodeTag::StructInitComma
+                | NodeTag::ContainerDeclArgTrailing
+                | NodeTag::SwitchComma => {
+                    let members: SubRange = self.extra_data(rhs);
+                    debug_assert!(members.end > members.start);
+                    end_offset += 2; // for the comma + rbrace
+                    n = self.extra_data[members.end as usize - 1] as usize;
+                }
+                NodeTag::ArrayInitDot
+                | NodeTag::StructInitDot
+                | NodeTag::Block
+                | NodeTag::ContainerDecl
+                | NodeTag::TaggedUnion
+                | NodeTag::BuiltinCall => {
+                    debug_assert!(rhs > lhs);
+                    end_offset += 1; // for the rbrace
+                    n = self.extra_data[rhs as usize - 1] as usize;
+                }
+                NodeTag::ArrayInitDotComma
+                | NodeTag::StructInitDotComma
+                | NodeTag::BlockSemicolon
+                | NodeTag::ContainerDeclTrailing
+                | NodeTag::TaggedUnionTrailing
+                | NodeTag::BuiltinCallComma => {
+                    debug_assert!(rhs > lhs);
+                    end_offset += 2; // for the comma/semicolon + rbrace/rparen
+                    n = self.extra_data[rhs as usize - 1] as usize;
+                }
+                NodeTag::CallOne | NodeTag::AsyncCallOne | NodeTag::ArrayAccess => {
+                    end_offset += 1; // for the rparen/rbracket
+                    if rhs == 0 {
+                        return main_tokens[n] + end_offset;
+                    }
+                    n = rhs as usize;
+                }
+                NodeTag::ArrayInitDotTwo
+                | NodeTag::BlockTwo
+                | NodeTag::BuiltinCallTwo
+                | NodeTag::StructInitDotTwo
+                | NodeTag::ContainerDeclTwo
+                | NodeTag::TaggedUnionTwo => {
+                    if rhs != 0 {
+                        end_offset += 1; // for the rparen/rbrace
+                        n = rhs as usize;
+                    } else if lhs != 0 {
+                        end_offset += 1; // for the rparen/rbrace
+                        n = lhs as usize;
+                    } else {
+                        let skip_doc_comments = |mut i: u32| {
+                            while token_tags[(main_tokens[n] + i) as usize]
+                                == Tag::ContainerDocComment
+                            {
+                                i += 1;
+                            }
+                            i
+                        };
+                        end_offset += match tags[n] {
+                            NodeTag::ArrayInitDotTwo
+                            | NodeTag::BlockTwo
+                            | NodeTag::StructInitDotTwo => 1, // rbrace
+                            NodeTag::BuiltinCallTwo => 2, // lparen + rparen
+                            NodeTag::ContainerDeclTwo => skip_doc_comments(2), // lbrace + rbrace
+                            _ => skip_doc_comments(5),    // (enum) {}
+                        };
+                        return main_tokens[n] + end_offset;
+                    }
+                }
+                NodeTag::ArrayInitDotTwoComma
+                | NodeTag::BuiltinCallTwoComma
+                | NodeTag::BlockTwoSemicolon
+                | NodeTag::StructInitDotTwoComma
+                | NodeTag::ContainerDeclTwoTrailing
+                | NodeTag::TaggedUnionTwoTrailing => {
+                    end_offset += 2; // for the comma/semicolon + rbrace/rparen
+                    n = if rhs != 0 { rhs } else { lhs } as usize;
+                }
+                NodeTag::SimpleVarDecl => {
+                    if rhs != 0 {
+                        n = rhs as usize;
+                    } else if lhs != 0 {
+                        n = lhs as usize;
+                    } else {
+                        end_offset += 1; // from mut token to name
+                        return main_tokens[n] + end_offset;
+                    }
+                }
+                NodeTag::AlignedVarDecl => {
+                    if rhs != 0 {
+                        n = rhs as usize;
+                    } else {
+                        end_offset += 1; // for the rparen
+                        n = lhs as usize;
+                    }
+                }
+                NodeTag::GlobalVarDecl => {
+                    if rhs != 0 {
+                        n = rhs as usize;
+                        continue;
+                    }
+                    let extra: GlobalVarDecl = self.extra_data(lhs);
+                    if extra.section_node != 0 {
+                        end_offset += 1; // for the rparen
+                        n = extra.section_node as usize;
+                    } else if extra.addrspace_node != 0 {
+                        end_offset += 1; // for the rparen
+                        n = extra.addrspace_node as usize;
+                    } else if extra.align_node != 0 {
+                        end_offset += 1; // for the rparen
+                        n = extra.align_node as usize;
+                    } else if extra.type_node != 0 {
+                        n = extra.type_node as usize;
+                    } else {
+                        end_offset += 1; // from mut token to name
+                        return main_tokens[n] + end_offset;
+                    }
+                }
+                NodeTag::LocalVarDecl => {
+                    if rhs != 0 {
+                        n = rhs as usize;
+                        continue;
+                    }
+                    let extra: LocalVarDecl = self.extra_data(lhs);
+                    if extra.align_node != 0 {
+                        end_offset += 1; // for the rparen
+                        n = extra.align_node as usize;
+                    } else {
+                        n = extra.type_node as usize;
+                    }
+                }
+                NodeTag::ContainerFieldInit => {
+                    n = if rhs != 0 { rhs } else { lhs } as usize;
+                }
+                NodeTag::ContainerFieldAlign => {
+                    end_offset += 1; // for the rparen
+                    n = rhs as usize;
+                }
+                NodeTag::ContainerField => {
+                    let extra: ContainerField = self.extra_data(rhs);
+                    if extra.value_expr != 0 {
+                        n = extra.value_expr as usize;
+                    } else {
+                        end_offset += 1; // for the rparen
+                        n = extra.align_expr as usize;
+                    }
+                }
+
+                NodeTag::ArrayInitOne | NodeTag::StructInitOne => {
+                    end_offset += 1; // rbrace
+                    if rhs == 0 {
+                        return main_tokens[n] + end_offset;
+                    }
+                    n = rhs as usize;
+                }
+                NodeTag::SliceOpen
+                | NodeTag::CallOneComma
+                | NodeTag::AsyncCallOneComma
+                | NodeTag::ArrayInitOneComma
+                | NodeTag::StructInitOneComma => {
+                    end_offset += 2; // ellipsis2 + rbracket, or comma + rparen
+                    debug_assert_ne!(rhs, 0);
+                    n = rhs as usize;
+                }
+                NodeTag::Slice => {
+                    let extra: Slice = self.extra_data(rhs);
+                    end_offset += 1; // rbracket
+                    n = extra.end as usize;
+                }
+                NodeTag::SliceSentinel => {
+                    let extra: SliceSentinel = self.extra_data(rhs);
+                    end_offset += 1; // rbracket
+                    n = extra.sentinel as usize;
+                }
+
+                NodeTag::Continue | NodeTag::Break => {
+                    if rhs != 0 {
+                        n = rhs as usize;
+                    } else if lhs != 0 {
+                        return lhs + end_offset;
+                    } else {
+                        return main_tokens[n] + end_offset;
+                    }
+                }
+                NodeTag::WhileCont => {
+                    let extra: WhileCont = self.extra_data(rhs);
+                    n = extra.then_expr as usize;
+                }
+                NodeTag::While => {
+                    let extra: While = self.extra_data(rhs);
+                    n = extra.else_expr as usize;
+                }
+                NodeTag::If => {
+                    let extra: If = self.extra_data(rhs);
+                    n = extra.else_expr as usize;
+                }
+                NodeTag::For => {
+                    let extra = For::from_u32(rhs);
+                    n = self.extra_data[(lhs + extra.inputs + extra.has_else as u32) as usize]
+                        as usize;
+                }
+                NodeTag::ArrayTypeSentinel => {
+                    let extra: ArrayTypeSentinel = self.extra_data(rhs);
+                    n = extra.elem_type as usize;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Ast<'_> {
+        let ast = Ast::parse(source.as_bytes(), Mode::Zig).unwrap();
+        let messages: Vec<_> = ast.errors.iter().map(|e| ast.error_message(e)).collect();
+        assert!(messages.is_empty(), "{messages:?}");
+        ast
+    }
+
+    /// The first node with `tag`, in node order.
+    fn find(ast: &Ast, tag: NodeTag) -> NodeIndex {
+        let index = ast.nodes.tags().iter().position(|&t| t == tag);
+        index.unwrap_or_else(|| panic!("no {} node", tag.name())) as NodeIndex
+    }
+
+    fn source_of<'a>(ast: &Ast<'a>, node: NodeIndex) -> &'a str {
+        std::str::from_utf8(ast.node_source(node)).unwrap()
+    }
+
+    #[test]
+    fn test_root_decls() {
+        let ast = parse("const std = @import(\"std\");\npub fn main() void {}\ntest { x += 1; }\n");
+        let decls: Vec<_> = ast
+            .root_decls()
+            .iter()
+            .map(|&d| (ast.node(d).tag, source_of(&ast, d)))
+            .collect();
+        assert_eq!(
+            decls,
+            [
+                (NodeTag::SimpleVarDecl, "const std = @import(\"std\")"),
+                (NodeTag::FnDecl, "pub fn main() void {}"),
+                (NodeTag::TestDecl, "test { x += 1; }"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_labeled_switch_continue() {
+        let ast = parse("test { sw: switch (s) { .a => continue :sw .b, else => {} } }");
+        let switch = ast.node(find(&ast, NodeTag::Switch));
+        assert_eq!(ast.token_slice(switch.main_token), b"sw");
+        assert_eq!(ast.token_tag(switch.main_token + 2), Tag::KWSwitch);
+        let cases: SubRange = ast.extra_data(switch.data.rhs);
+        assert_eq!(cases.end - cases.start, 2);
+
+        let cont = find(&ast, NodeTag::Continue);
+        let Data { lhs, rhs } = ast.node(cont).data;
+        assert_eq!(ast.token_slice(lhs), b"sw");
+        assert_eq!(ast.node(rhs).tag, NodeTag::EnumLiteral);
+        assert_eq!(source_of(&ast, cont), "continue :sw .b");
+        assert_eq!(
+            source_of(&ast, find(&ast, NodeTag::Switch)),
+            "sw: switch (s) { .a => continue :sw .b, else => {} }"
+        );
+    }
+
+    #[test]
+    fn test_decl_literal() {
+        let ast = parse("const s: S = .init(1);\nconst t: T = .{ .x = .empty };");
+        let call = ast.node(find(&ast, NodeTag::CallOne));
+        let callee = ast.node(call.data.lhs);
+        assert_eq!(callee.tag, NodeTag::EnumLiteral);
+        assert_eq!(ast.token_slice(callee.main_token), b"init");
+        assert_eq!(source_of(&ast, call.data.lhs), ".init");
+
+        let init = ast.node(find(&ast, NodeTag::StructInitDotTwo));
+        assert_eq!(ast.node(init.data.lhs).tag, NodeTag::EnumLiteral);
+        assert_eq!(init.data.rhs, NULL_NODE);
+    }
+
+    #[test]
+    fn test_addrspace() {
+        let ast = parse(
+            "var g: u32 addrspace(.global) = 0;\nconst p: *addrspace(.shared) const u8 = undefined;",
+        );
+        let var = ast.node(find(&ast, NodeTag::GlobalVarDecl));
+        let extra: GlobalVarDecl = ast.extra_data(var.data.lhs);
+        assert_eq!(source_of(&ast, extra.addrspace_node), ".global");
+        assert_eq!(extra.align_node, NULL_NODE);
+
+        let ptr = ast.node(find(&ast, NodeTag::PtrType));
+        let extra: PtrType = ast.extra_data(ptr.data.lhs);
+        assert_eq!(source_of(&ast, extra.addrspace_node), ".shared");
+        assert_eq!(extra.sentinel, NULL_NODE);
+        assert_eq!(source_of(&ast, ptr.data.rhs), "u8");
+    }
+
+    #[test]
+    fn test_node_spans() {
+        let source = concat!(
+            "pub const Foo = extern struct { a: u8 align(4) = 1, b: [*c]u8 };\n",
+            "fn f(comptime T: type, x: anytype) !T {\n",
+            "    outer: for (xs, 0..) |*x, i| {\n",
+            "        if (x.*) continue :outer else break;\n",
+            "    } else unreachable;\n",
+            "    const y = asm volatile (\"\" : [r] \"=r\" (-> u8) : [n] \"r\" (n) : \"memory\");\n",
+            "    while (i < 10) : (i += 1) {}\n",
+            "    return @as(T, s[1..][0..n :0].*) orelse error.Oops;\n",
+            "}\n",
+        );
+        let ast = parse(source);
+        for node in 1..ast.nodes.len() as NodeIndex {
+            let loc = ast.node_loc(node);
+            let main = ast.token_start(ast.node(node).main_token) as usize;
+            assert!(
+                loc.start <= main && main < loc.end,
+                "{} {:?}",
+                ast.node(node).tag.name(),
+                source_of(&ast, node)
+            );
+        }
+        assert_eq!(
+            source_of(&ast, find(&ast, NodeTag::For)),
+            "outer: for (xs, 0..) |*x, i| {\n        if (x.*) continue :outer else break;\n    } else unreachable"
+        );
+        assert_eq!(
+            source_of(&ast, find(&ast, NodeTag::SliceSentinel)),
+            "s[1..][0..n :0]"
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let cases = [
+            ("const x = 1", 11, "expected ';' after declaration"),
+            (
+                "test { _ = a == b == c; }",
+                18,
+                "comparison operators cannot be chained",
+            ),
+            (
+                "const x = a +b;",
+                12,
+                "binary operator `+` has whitespace on one side, but not the other.",
+            ),
+            (
+                "fn f(a: u8 b: u8) void {}",
+                11,
+                "expected ',' after parameter",
+            ),
+            (
+                "test { for (a, b) |x| {} }",
+                15,
+                "for input is not captured",
+            ),
+            (
+                "test { inline x; }",
+                14,
+                "expected 'while' or 'for', found 'an identifier'",
+            ),
+            ("struct Foo {};", 7, "'struct Foo' is invalid"),
+        ];
+        for (source, offset, message) in cases {
+            let ast = Ast::parse(source.as_bytes(), Mode::Zig).unwrap();
+            let error = &ast.errors[0];
+            assert_eq!(ast.error_offset(error), offset, "{source}");
+            assert_eq!(ast.error_message(error), message, "{source}");
+        }
+    }
+
+    #[test]
+    fn test_recovery() {
+        let ast = Ast::parse(
+            b"const a = 1 +;\nconst b = 2;\nfn f() void { x = ; y(); }",
+            Mode::Zig,
+        )
+        .unwrap();
+        assert_eq!(ast.errors.len(), 2);
+        assert_eq!(ast.root_decls().len(), 3);
+        let call = find(&ast, NodeTag::CallOne);
+        assert_eq!(source_of(&ast, call), "y()");
+    }
+
+    #[test]
+    fn test_zon() {
+        let ast = Ast::parse(b".{ .name = .foo, .paths = .{\"a\"} }", Mode::Zon).unwrap();
+        assert!(ast.errors.is_empty());
+        let root = ast.node(0).data.lhs;
+        assert_eq!(ast.node(root).tag, NodeTag::StructInitDotTwo);
+        assert_eq!(ast.node_loc(root).end, ast.source.len());
+    }
+}
@@ -0,0 +1,327 @@
+use crate::zig::language_version::LanguageVersion;
+use crate::zig::tokenizer::{Tag, Token};
+
+/// Whether a call to the builtin can evaluate to an error, which decides if
+/// `try` on its result is meaningful.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvalToError {
+    Never,
+    Always,
+    Maybe,
+}
+
+/// How the builtin uses the result location of the call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemLocRequirement {
+    /// The result location is never needed.
+    Never,
+    /// The builtin always writes its result through the result location.
+    Always,
+    /// The result location is forwarded to the second parameter, as in
+    /// `@as(T, value)`.
+    Forward1,
+}
+
+/// Static data about a builtin, ported from upstream `BuiltinFn.zig`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Info {
+    pub tag: BuiltinFn,
+    /// Name without the leading `@`.
+    pub name: &'static str,
+    /// Number of parameters, or `None` for variadic builtins such as
+    /// `@compileLog`.
+    pub param_count: Option<u8>,
+    pub eval_to_error: EvalToError,
+    pub needs_mem_loc: MemLocRequirement,
+    /// Whether the call may appear on the left-hand side of an assignment.
+    pub allows_lvalue: bool,
+    /// Whether the builtin may only be called inside a function body, and
+    /// not at container level or in a comptime block outside a function.
+    pub illegal_outside_function: bool,
+    /// Whether the call is always evaluated at compile time: its operands
+    /// must be comptime-known and it never produces runtime code. Upstream
+    /// keeps this knowledge in Sema rather than in `BuiltinFn.zig`.
+    pub comptime_only: bool,
+    /// First version that has the builtin.
+    pub since: LanguageVersion,
+    /// First version that no longer has it.
+    pub until: Option<LanguageVersion>,
+}
+
+const DEFAULT: Info = Info {
+    tag: BuiltinFn::As,
+    name: "",
+    param_count: None,
+    eval_to_error: EvalToError::Never,
+    needs_mem_loc: MemLocRequirement::Never,
+    allows_lvalue: false,
+    illegal_outside_function: false,
+    comptime_only: false,
+    since: LanguageVersion::V0_11,
+    until: None,
+};
+
+macro_rules! builtin_fns {
+    ($($variant:ident $name:literal $params:expr $(, $field:ident: $value:expr)*;)*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum BuiltinFn {
+            $($variant,)*
+        }
+
+        /// Indexed by `BuiltinFn as usize`, and sorted by name.
+        const INFOS: &[Info] = &[
+            $(Info {
+                tag: BuiltinFn::$variant,
+                name: $name,
+                param_count: $params,
+                $($field: $value,)*
+                ..DEFAULT
+            },)*
+        ];
+    };
+}
+
+builtin_fns! {
+    FieldType "FieldType" Some(2), comptime_only: true, since: LanguageVersion::V0_14;
+    FrameType "Frame" Some(1), comptime_only: true, until: Some(LanguageVersion::Master);
+    This "This" Some(0), comptime_only: true;
+    Type "Type" Some(1), comptime_only: true;
+    TypeOf "TypeOf" None, comptime_only: true;
+    Vector "Vector" Some(2), comptime_only: true;
+    Abs "abs" Some(1), since: LanguageVersion::V0_12;
+    AddWithOverflow "addWithOverflow" Some(2);
+    AddrSpaceCast "addrSpaceCast" Some(1);
+    AlignCast "alignCast" Some(1);
+    AlignOf "alignOf" Some(1), comptime_only: true;
+    As "as" Some(2), eval_to_error: EvalToError::Maybe, needs_mem_loc: MemLocRequirement::Forward1;
+    AsyncCall "asyncCall" Some(4), until: Some(LanguageVersion::Master);
+    AtomicLoad "atomicLoad" Some(3);
+    AtomicRmw "atomicRmw" Some(5);
+    AtomicStore "atomicStore" Some(4);
+    BitCast "bitCast" Some(1);
+    BitOffsetOf "bitOffsetOf" Some(2), comptime_only: true;
+    BitReverse "bitReverse" Some(1);
+    BitSizeOf "bitSizeOf" Some(1), comptime_only: true;
+    BranchHint "branchHint" Some(1), illegal_outside_function: true, since: LanguageVersion::V0_14;
+    Breakpoint "breakpoint" Some(0), illegal_outside_function: true;
+    ByteSwap "byteSwap" Some(1);
+    CDefine "cDefine" Some(2), comptime_only: true;
+    CImport "cImport" Some(1), comptime_only: true;
+    CInclude "cInclude" Some(1), comptime_only: true;
+    CUndef "cUndef" Some(1), comptime_only: true;
+    CVaArg "cVaArg" Some(2), illegal_outside_function: true;
+    CVaCopy "cVaCopy" Some(1), illegal_outside_function: true;
+    CVaEnd "cVaEnd" Some(1), illegal_outside_function: true;
+    CVaStart "cVaStart" Some(0), illegal_outside_function: true;
+    Call "call" Some(3), eval_to_error: EvalToError::Maybe, needs_mem_loc: MemLocRequirement::Always;
+    Ceil "ceil" Some(1);
+    Clz "clz" Some(1);
+    CmpxchgStrong "cmpxchgStrong" Some(6);
+    CmpxchgWeak "cmpxchgWeak" Some(6);
+    CompileError "compileError" Some(1), comptime_only: true;
+    CompileLog "compileLog" None, comptime_only: true;
+    ConstCast "constCast" Some(1);
+    Cos "cos" Some(1);
+    Ctz "ctz" Some(1);
+    DisableInstrumentation "disableInstrumentation" Some(0), illegal_outside_function: true, since: LanguageVersion::V0_14;
+    DisableIntrinsics "disableIntrinsics" Some(0), illegal_outside_function: true, since: LanguageVersion::V0_14;
+    DivExact "divExact" Some(2);
+    DivFloor "divFloor" Some(2);
+    DivTrunc "divTrunc" Some(2);
+    EmbedFile "embedFile" Some(1), comptime_only: true;
+    EnumFromInt "enumFromInt" Some(1);
+    ErrorCast "errorCast" Some(1), eval_to_error: EvalToError::Always;
+    ErrorFromInt "errorFromInt" Some(1), eval_to_error: EvalToError::Always;
+    ErrorName "errorName" Some(1);
+    ErrorReturnTrace "errorReturnTrace" Some(0);
+    Exp "exp" Some(1);
+    Exp2 "exp2" Some(1);
+    Export "export" Some(2);
+    Extern "extern" Some(2);
+    Fabs "fabs" Some(1), until: Some(LanguageVersion::V0_12);
+    Fence "fence" Some(1), until: Some(LanguageVersion::V0_14);
+    Field "field" Some(2), eval_to_error: EvalToError::Maybe, needs_mem_loc: MemLocRequirement::Always, allows_lvalue: true;
+    FieldParentPtr "fieldParentPtr" Some(2);
+    FloatCast "floatCast" Some(1);
+    FloatFromInt "floatFromInt" Some(1);
+    Floor "floor" Some(1);
+    Frame "frame" Some(0), illegal_outside_function: true, until: Some(LanguageVersion::Master);
+    FrameAddress "frameAddress" Some(0), illegal_outside_function: true;
+    FrameSize "frameSize" Some(1), until: Some(LanguageVersion::Master);
+    HasDecl "hasDecl" Some(2), comptime_only: true;
+    HasField "hasField" Some(2), comptime_only: true;
+    Import "import" Some(1), comptime_only: true;
+    InComptime "inComptime" Some(0), comptime_only: true;
+    IntCast "intCast" Some(1);
+    IntFromBool "intFromBool" Some(1);
+    IntFromEnum "intFromEnum" Some(1);
+    IntFromError "intFromError" Some(1);
+    IntFromFloat "intFromFloat" Some(1);
+    IntFromPtr "intFromPtr" Some(1);
+    Log "log" Some(1);
+    Log10 "log10" Some(1);
+    Log2 "log2" Some(1);
+    Max "max" None;
+    Memcpy "memcpy" Some(2);
+    Memmove "memmove" Some(2), since: LanguageVersion::Master;
+    Memset "memset" Some(2);
+    Min "min" None;
+    Mod "mod" Some(2);
+    MulAdd "mulAdd" Some(4);
+    MulWithOverflow "mulWithOverflow" Some(2);
+    OffsetOf "offsetOf" Some(2), comptime_only: true;
+    Panic "panic" Some(1);
+    PopCount "popCount" Some(1);
+    Prefetch "prefetch" Some(2);
+    PtrCast "ptrCast" Some(1);
+    PtrFromInt "ptrFromInt" Some(1);
+    Reduce "reduce" Some(2);
+    Rem "rem" Some(2);
+    ReturnAddress "returnAddress" Some(0), illegal_outside_function: true;
+    Round "round" Some(1);
+    Select "select" Some(4);
+    SetAlignStack "setAlignStack" Some(1), illegal_outside_function: true, until: Some(LanguageVersion::V0_14);
+    SetCold "setCold" Some(1), illegal_outside_function: true, until: Some(LanguageVersion::V0_14);
+    SetEvalBranchQuota "setEvalBranchQuota" Some(1);
+    SetFloatMode "setFloatMode" Some(1);
+    SetRuntimeSafety "setRuntimeSafety" Some(1);
+    ShlExact "shlExact" Some(2);
+    ShlWithOverflow "shlWithOverflow" Some(2);
+    ShrExact "shrExact" Some(2);
+    Shuffle "shuffle" Some(4);
+    Sin "sin" Some(1);
+    SizeOf "sizeOf" Some(1), comptime_only: true;
+    Splat "splat" Some(1);
+    Sqrt "sqrt" Some(1);
+    Src "src" Some(0), comptime_only: true, illegal_outside_function: true;
+    SubWithOverflow "subWithOverflow" Some(2);
+    TagName "tagName" Some(1);
+    Tan "tan" Some(1);
+    Trap "trap" Some(0);
+    Trunc "trunc" Some(1);
+    Truncate "truncate" Some(1);
+    TypeInfo "typeInfo" Some(1), comptime_only: true;
+    TypeName "typeName" Some(1), comptime_only: true;
+    UnionInit "unionInit" Some(3), needs_mem_loc: MemLocRequirement::Always;
+    VolatileCast "volatileCast" Some(1);
+    WasmMemoryGrow "wasmMemoryGrow" Some(2);
+    WasmMemorySize "wasmMemorySize" Some(1);
+    WorkGroupId "workGroupId" Some(1), illegal_outside_function: true;
+    WorkGroupSize "workGroupSize" Some(1), illegal_outside_function: true;
+    WorkItemId "workItemId" Some(1), illegal_outside_function: true;
+}
+
+impl BuiltinFn {
+    pub fn all() -> impl Iterator<Item = BuiltinFn> {
+        INFOS.iter().map(|info| info.tag)
+    }
+
+    /// Looks up a builtin by name, without the leading `@`, in any version.
+    pub fn from_name(name: &[u8]) -> Option<BuiltinFn> {
+        INFOS
+            .binary_search_by(|info| info.name.as_bytes().cmp(name))
+            .ok()
+            .map(|index| INFOS[index].tag)
+    }
+
+    /// Classifies a `Tag::Builtin` token. Returns `None` for other tokens and
+    /// for names that are not builtins in `version`.
+    pub fn from_token(source: &[u8], token: &Token, version: LanguageVersion) -> Option<BuiltinFn> {
+        if token.tag != Tag::Builtin {
+            return None;
+        }
+        let name = source[token.loc.start..token.loc.end].strip_prefix(b"@")?;
+        BuiltinFn::from_name(name).filter(|builtin| builtin.available_in(version))
+    }
+
+    pub fn info(self) -> &'static Info {
+        &INFOS[self as usize]
+    }
+
+    pub fn name(self) -> &'static str {
+        self.info().name
+    }
+
+    pub fn available_in(self, version: LanguageVersion) -> bool {
+        let info = self.info();
+        info.since <= version && info.until.is_none_or(|until| version < until)
+    }
+
+    /// Parameter count in `version`, which differs from [`Info::param_count`]
+    /// only where a builtin's signature changed.
+    pub fn param_count(self, version: LanguageVersion) -> Option<u8> {
+        match self {
+            // `@fieldParentPtr(T, "field", ptr)` lost its type parameter in 0.12.
+            BuiltinFn::FieldParentPtr if version == LanguageVersion::V0_11 => Some(3),
+            _ => self.info().param_count,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zig::tokenizer::TokenStream;
+
+    #[test]
+    fn test_infos_sorted_and_indexed() {
+        for (index, info) in INFOS.iter().enumerate() {
+            assert_eq!(info.tag as usize, index);
+            assert_eq!(BuiltinFn::from_name(info.name.as_bytes()), Some(info.tag));
+        }
+        for pair in INFOS.windows(2) {
+            assert!(pair[0].name < pair[1].name);
+        }
+    }
+
+    #[test]
+    fn test_info() {
+        let info = BuiltinFn::As.info();
+        assert_eq!(info.name, "as");
+        assert_eq!(info.param_count, Some(2));
+        assert_eq!(info.needs_mem_loc, MemLocRequirement::Forward1);
+        assert_eq!(BuiltinFn::CompileLog.info().param_count, None);
+        assert!(BuiltinFn::Field.info().allows_lvalue);
+        assert!(BuiltinFn::ReturnAddress.info().illegal_outside_function);
+        for builtin in [
+            BuiltinFn::CompileError,
+            BuiltinFn::CompileLog,
+            BuiltinFn::TypeInfo,
+            BuiltinFn::Type,
+            BuiltinFn::HasDecl,
+            BuiltinFn::HasField,
+            BuiltinFn::TypeOf,
+        ] {
+            assert!(builtin.info().comptime_only, "{}", builtin.name());
+        }
+        assert!(!BuiltinFn::IntCast.info().comptime_only);
+        assert!(!BuiltinFn::ReturnAddress.info().comptime_only);
+        assert_eq!(
+            BuiltinFn::FieldParentPtr.param_count(LanguageVersion::V0_11),
+            Some(3)
+        );
+        assert_eq!(
+            BuiltinFn::FieldParentPtr.param_count(LanguageVersion::V0_12),
+            Some(2)
+        );
+    }
+
+    #[test]
+    fn test_from_token() {
+        let source = b"@intCast(x) + @foo() + @setCold(true) + @\"a\"";
+        let classify = |version| -> Vec<Option<BuiltinFn>> {
+            TokenStream::new(source)
+                .filter(|token| token.tag == Tag::Builtin)
+                .map(|token| BuiltinFn::from_token(source, &token, version))
+                .collect()
+        };
+        assert_eq!(
+            classify(LanguageVersion::V0_13),
+            [Some(BuiltinFn::IntCast), None, Some(BuiltinFn::SetCold)]
+        );
+        assert_eq!(
+            classify(LanguageVersion::V0_14),
+            [Some(BuiltinFn::IntCast), None, None]
+        );
+    }
+}
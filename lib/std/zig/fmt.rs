@@ -0,0 +1,168 @@
+//! Formatting of names and strings as Zig source, ported from upstream
+//! `std.zig.fmt`.
+
+use std::fmt;
+
+use crate::zig::primitives::{is_primitive, is_valid_id};
+
+/// Formats `name` as an identifier, using the `@"..."` form when the bare
+/// name would not refer to it: when it is not valid identifier syntax, is a
+/// keyword in any language version, is a primitive such as `u8` or `null`,
+/// or is `_`. The last two can be allowed where they are unambiguous, such
+/// as after a `.`.
+pub fn fmt_id(name: &(impl AsRef<[u8]> + ?Sized)) -> FmtId<'_> {
+    FmtId {
+        name: name.as_ref(),
+        allow_primitive: false,
+        allow_underscore: false,
+    }
+}
+
+/// Escapes `bytes` for use between the quotes of a string literal. Printable
+/// ASCII is kept, `\n`, `\r` and `\t` get their short escapes, and every
+/// other byte is written as `\xNN`.
+pub fn fmt_escapes(bytes: &(impl AsRef<[u8]> + ?Sized)) -> FmtEscapes<'_> {
+    FmtEscapes {
+        bytes: bytes.as_ref(),
+        quote: b'"',
+    }
+}
+
+/// See [`fmt_id`].
+#[derive(Debug, Clone, Copy)]
+pub struct FmtId<'a> {
+    name: &'a [u8],
+    allow_primitive: bool,
+    allow_underscore: bool,
+}
+
+impl FmtId<'_> {
+    /// Leaves primitive names bare, like upstream's `{p}`.
+    pub fn allow_primitive(self) -> Self {
+        Self {
+            allow_primitive: true,
+            ..self
+        }
+    }
+
+    /// Leaves `_` bare, like upstream's `{_}`.
+    pub fn allow_underscore(self) -> Self {
+        Self {
+            allow_underscore: true,
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for FmtId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name;
+        if is_valid_id(name)
+            && (self.allow_primitive || !is_primitive(name))
+            && (self.allow_underscore || name != b"_")
+        {
+            // Valid identifiers are ASCII.
+            return f.write_str(std::str::from_utf8(name).unwrap());
+        }
+        write!(f, "@\"{}\"", fmt_escapes(name))
+    }
+}
+
+/// See [`fmt_escapes`].
+#[derive(Debug, Clone, Copy)]
+pub struct FmtEscapes<'a> {
+    bytes: &'a [u8],
+    quote: u8,
+}
+
+impl FmtEscapes<'_> {
+    /// Escapes for a character literal instead: `'` is escaped and `"` is
+    /// not, like upstream's `{'}`.
+    pub fn single_quoted(self) -> Self {
+        Self {
+            quote: b'\'',
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for FmtEscapes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for &byte in self.bytes {
+            match byte {
+                b'\n' => f.write_str("\\n")?,
+                b'\r' => f.write_str("\\r")?,
+                b'\t' => f.write_str("\\t")?,
+                b'\\' => f.write_str("\\\\")?,
+                b'"' | b'\'' if byte == self.quote => write!(f, "\\{}", byte as char)?,
+                b' '..=b'~' => write!(f, "{}", byte as char)?,
+                _ => write!(f, "\\x{byte:02x}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zig::string_literal::{identifier_name, parse};
+    use crate::zig::tokenizer::{Tag, TokenStream};
+
+    #[test]
+    fn test_fmt_id() {
+        let cases = [
+            ("foo", "foo"),
+            ("_foo1", "_foo1"),
+            ("type", "@\"type\""),
+            ("error", "@\"error\""),
+            ("async", "@\"async\""),
+            ("u8", "@\"u8\""),
+            ("i99999", "@\"i99999\""),
+            ("null", "@\"null\""),
+            ("_", "@\"_\""),
+            ("", "@\"\""),
+            ("1x", "@\"1x\""),
+            ("a b", "@\"a b\""),
+            ("a\"\n\\", "@\"a\\\"\\n\\\\\""),
+        ];
+        for (name, expected) in cases {
+            assert_eq!(fmt_id(name).to_string(), expected);
+        }
+        assert_eq!(fmt_id("u8").allow_primitive().to_string(), "u8");
+        assert_eq!(fmt_id("_").allow_underscore().to_string(), "_");
+        assert_eq!(fmt_id("fn").allow_primitive().to_string(), "@\"fn\"");
+        assert_eq!(fmt_id(&b"\xff"[..]).to_string(), "@\"\\xff\"");
+    }
+
+    #[test]
+    fn test_fmt_escapes() {
+        let bytes = b"a'\"\t\x00\x7f\xe2\x82\xac~";
+        assert_eq!(
+            fmt_escapes(bytes).to_string(),
+            "a'\\\"\\t\\x00\\x7f\\xe2\\x82\\xac~"
+        );
+        assert_eq!(
+            fmt_escapes(bytes).single_quoted().to_string(),
+            "a\\'\"\\t\\x00\\x7f\\xe2\\x82\\xac~"
+        );
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let names = ["x", "type", "u8", "_", "a-b", "\u{e9}", "\"\\", "\0\n"];
+        for name in names {
+            let source = fmt_id(name).to_string();
+            let tokens: Vec<_> = TokenStream::new(source.as_bytes()).collect();
+            assert_eq!(tokens.len(), 1, "{source}");
+            assert_eq!(tokens[0].tag, Tag::Identifier);
+            assert_eq!(
+                identifier_name(source.as_bytes(), &tokens[0]).unwrap(),
+                name
+            );
+
+            let literal = format!("\"{}\"", fmt_escapes(name));
+            assert_eq!(parse(literal.as_bytes()).unwrap(), name.as_bytes());
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::io::{self, Write};
+
+use crate::zig::language_version::LanguageVersion;
+use crate::zig::primitives::{is_primitive, Primitive};
+use crate::zig::tokenizer::{Category, Loc, Tag, TokenStream};
+
+/// What a span of source is highlighted as. Identifiers are classified the
+/// way upstream's doctest highlighter does it: primitive values, primitive
+/// and integer types, and the name following `fn`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Highlight {
+    Keyword,
+    Builtin,
+    String,
+    Number,
+    /// `true`, `false`, `null` and `undefined`.
+    Value,
+    Type,
+    /// The name in a function declaration.
+    Function,
+    Comment,
+    DocComment,
+    Operator,
+    Invalid,
+    /// Other identifiers, whitespace and newlines.
+    Plain,
+}
+
+impl Highlight {
+    /// CSS classes for HTML output, using upstream's `tok-*` names where
+    /// upstream has one. Doc comments also get `tok-comment`, so a
+    /// stylesheet written for upstream's output still applies.
+    pub fn class(self) -> Option<&'static str> {
+        match self {
+            Highlight::Keyword => Some("tok-kw"),
+            Highlight::Builtin => Some("tok-builtin"),
+            Highlight::String => Some("tok-str"),
+            Highlight::Number => Some("tok-number"),
+            Highlight::Value => Some("tok-null"),
+            Highlight::Type => Some("tok-type"),
+            Highlight::Function => Some("tok-fn"),
+            Highlight::Comment => Some("tok-comment"),
+            Highlight::DocComment => Some("tok-comment tok-doc"),
+            Highlight::Operator => Some("tok-op"),
+            Highlight::Invalid => Some("tok-invalid"),
+            Highlight::Plain => None,
+        }
+    }
+
+    /// SGR parameters for terminal output. Operators are left unstyled so
+    /// the default foreground works on both light and dark backgrounds.
+    pub fn ansi(self) -> Option<&'static str> {
+        match self {
+            Highlight::Keyword => Some("1;35"),
+            Highlight::Builtin => Some("36"),
+            Highlight::String => Some("32"),
+            Highlight::Number | Highlight::Value => Some("33"),
+            Highlight::Type => Some("34"),
+            Highlight::Function => Some("1;34"),
+            Highlight::Comment => Some("90"),
+            Highlight::DocComment => Some("3;90"),
+            Highlight::Invalid => Some("4;31"),
+            Highlight::Operator | Highlight::Plain => None,
+        }
+    }
+}
+
+/// Splits a source into highlighted spans that cover every byte, comments
+/// and whitespace included, in order.
+pub struct Highlighter<'a> {
+    source: &'a [u8],
+    stream: TokenStream<'a>,
+    after_fn: bool,
+}
+
+impl<'a> Highlighter<'a> {
+    pub fn new(source: &'a [u8]) -> Self {
+        Self {
+            source,
+            stream: TokenStream::with_trivia(source),
+            after_fn: false,
+        }
+    }
+
+    pub fn language_version(self, version: LanguageVersion) -> Self {
+        Self {
+            stream: self.stream.language_version(version),
+            ..self
+        }
+    }
+
+    fn classify(&self, tag: &Tag, loc: Loc) -> Highlight {
+        match tag.category() {
+            Category::Identifier => {
+                let name = &self.source[loc.start..loc.end];
+                match Primitive::from_name(name) {
+                    Some(Primitive::Value(_)) => Highlight::Value,
+                    _ if self.after_fn => Highlight::Function,
+                    // Oversized integer types are still types to the reader.
+                    _ if is_primitive(name) => Highlight::Type,
+                    _ => Highlight::Plain,
+                }
+            }
+            Category::Literal if *tag == Tag::NumberLiteral => Highlight::Number,
+            Category::Literal => Highlight::String,
+            Category::Builtin => Highlight::Builtin,
+            Category::Keyword => Highlight::Keyword,
+            Category::Operator | Category::Assignment | Category::Punctuation => {
+                Highlight::Operator
+            }
+            Category::DocComment => Highlight::DocComment,
+            Category::Trivia if *tag == Tag::LineComment => Highlight::Comment,
+            Category::Trivia | Category::Eof => Highlight::Plain,
+            Category::Invalid => Highlight::Invalid,
+        }
+    }
+}
+
+impl Iterator for Highlighter<'_> {
+    type Item = (Loc, Highlight);
+
+    fn next(&mut self) -> Option<(Loc, Highlight)> {
+        let token = self.stream.next()?;
+        let highlight = self.classify(&token.tag, token.loc);
+        if !token.tag.is_trivia() {
+            self.after_fn = token.tag == Tag::KWFn;
+        }
+        Some((token.loc, highlight))
+    }
+}
+
+/// Writes `source` as HTML, wrapping styled spans in
+/// `<span class="tok-...">`. Like upstream, only the spans are written; the
+/// caller supplies the enclosing `<pre><code>`. Invalid UTF-8 is replaced.
+pub fn write_html<W: Write>(mut out: W, source: &[u8]) -> io::Result<()> {
+    for (loc, highlight) in Highlighter::new(source) {
+        let class = highlight.class();
+        if let Some(class) = class {
+            write!(out, "<span class=\"{class}\">")?;
+        }
+        for c in String::from_utf8_lossy(&source[loc.start..loc.end]).chars() {
+            match c {
+                '&' => out.write_all(b"&amp;")?,
+                '<' => out.write_all(b"&lt;")?,
+                '>' => out.write_all(b"&gt;")?,
+                '"' => out.write_all(b"&quot;")?,
+                c => write!(out, "{c}")?,
+            }
+        }
+        if class.is_some() {
+            out.write_all(b"</span>")?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes `source` with ANSI escape sequences. The bytes between the escapes
+/// are exactly the input.
+pub fn write_ansi<W: Write>(mut out: W, source: &[u8]) -> io::Result<()> {
+    for (loc, highlight) in Highlighter::new(source) {
+        let text = &source[loc.start..loc.end];
+        match highlight.ansi() {
+            Some(style) => {
+                write!(out, "\x1b[{style}m")?;
+                out.write_all(text)?;
+                out.write_all(b"\x1b[0m")?;
+            }
+            None => out.write_all(text)?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlights(source: &str) -> Vec<(&str, Highlight)> {
+        Highlighter::new(source.as_bytes())
+            .filter(|(_, highlight)| *highlight != Highlight::Plain)
+            .map(|(loc, highlight)| (&source[loc.start..loc.end], highlight))
+            .collect()
+    }
+
+    #[test]
+    fn test_classify() {
+        use Highlight::*;
+        assert_eq!(
+            highlights("/// doc\npub fn main() u8 { // done\n    return @as(u8, null) + 0x1; }"),
+            [
+                ("/// doc", DocComment),
+                ("pub", Keyword),
+                ("fn", Keyword),
+                ("main", Function),
+                ("(", Operator),
+                (")", Operator),
+                ("u8", Type),
+                ("{", Operator),
+                ("// done", Comment),
+                ("return", Keyword),
+                ("@as", Builtin),
+                ("(", Operator),
+                ("u8", Type),
+                (",", Operator),
+                ("null", Value),
+                (")", Operator),
+                ("+", Operator),
+                ("0x1", Number),
+                (";", Operator),
+                ("}", Operator),
+            ]
+        );
+        assert_eq!(
+            highlights("'a' \\\\x\n$"),
+            [("'a'", String), ("\\\\x", String), ("$", Invalid)]
+        );
+    }
+
+    #[test]
+    fn test_ansi_is_lossless() {
+        let source = b"\xEF\xBB\xBFconst s = \"\xff\"; // \xc3\xa9\r\n";
+        let mut out = Vec::new();
+        write_ansi(&mut out, source).unwrap();
+        let mut stripped = Vec::new();
+        let mut rest = &out[..];
+        while let Some(escape) = rest.iter().position(|&b| b == 0x1b) {
+            stripped.extend_from_slice(&rest[..escape]);
+            let end = rest[escape..].iter().position(|&b| b == b'm').unwrap();
+            rest = &rest[escape + end + 1..];
+        }
+        stripped.extend_from_slice(rest);
+        assert_eq!(stripped, source);
+    }
+
+    #[test]
+    fn test_html() {
+        let mut out = Vec::new();
+        write_html(&mut out, b"if (a < \"&\") {}").unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "<span class=\"tok-kw\">if</span> <span class=\"tok-op\">(</span>a ",
+                "<span class=\"tok-op\">&lt;</span> <span class=\"tok-str\">&quot;&amp;&quot;</span>",
+                "<span class=\"tok-op\">)</span> <span class=\"tok-op\">{</span>",
+                "<span class=\"tok-op\">}</span>",
+            )
+        );
+    }
+}
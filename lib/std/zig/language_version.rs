@@ -0,0 +1,149 @@
+use std::fmt;
+
+use crate::zig::builtin_fn::BuiltinFn;
+use crate::zig::tokenizer::Tag;
+
+/// Zig release whose keyword and builtin sets apply. `Master` tracks the
+/// development branch after 0.14, which has dropped `async`, `await` and
+/// `usingnamespace` along with the async builtins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum LanguageVersion {
+    V0_11,
+    V0_12,
+    V0_13,
+    #[default]
+    V0_14,
+    Master,
+}
+
+impl LanguageVersion {
+    pub const ALL: [LanguageVersion; 5] = [
+        LanguageVersion::V0_11,
+        LanguageVersion::V0_12,
+        LanguageVersion::V0_13,
+        LanguageVersion::V0_14,
+        LanguageVersion::Master,
+    ];
+
+    /// Parses `0.11` through `0.14`, with or without a patch number, and
+    /// `master`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let release = name
+            .strip_prefix("0.")
+            .map(|rest| match rest.split_once('.') {
+                Some((minor, patch)) if patch.bytes().all(|b| b.is_ascii_digit()) => minor,
+                Some(_) => "",
+                None => rest,
+            });
+        match release {
+            Some("11") => Some(LanguageVersion::V0_11),
+            Some("12") => Some(LanguageVersion::V0_12),
+            Some("13") => Some(LanguageVersion::V0_13),
+            Some("14") => Some(LanguageVersion::V0_14),
+            _ if name == "master" => Some(LanguageVersion::Master),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LanguageVersion::V0_11 => "0.11",
+            LanguageVersion::V0_12 => "0.12",
+            LanguageVersion::V0_13 => "0.13",
+            LanguageVersion::V0_14 => "0.14",
+            LanguageVersion::Master => "master",
+        }
+    }
+
+    /// Whether the keyword `tag` is reserved in this version. Non-keyword
+    /// tags return `false`.
+    pub fn is_keyword(self, tag: &Tag) -> bool {
+        match tag {
+            Tag::KWAsync | Tag::KWAwait | Tag::KWUsingnamespace => self < LanguageVersion::Master,
+            _ => tag.is_keyword(),
+        }
+    }
+
+    /// Whether `@name` is a builtin function in this version. `name` is
+    /// given without the `@`.
+    pub fn has_builtin(self, name: &str) -> bool {
+        BuiltinFn::from_name(name.as_bytes()).is_some_and(|builtin| builtin.available_in(self))
+    }
+
+    /// All builtins available in this version.
+    pub fn builtins(self) -> impl Iterator<Item = BuiltinFn> {
+        BuiltinFn::all().filter(move |builtin| builtin.available_in(self))
+    }
+}
+
+impl fmt::Display for LanguageVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zig::tokenizer::TokenStream;
+
+    #[test]
+    fn test_has_builtin() {
+        use LanguageVersion::*;
+        assert!(LanguageVersion::ALL
+            .iter()
+            .all(|v| v.has_builtin("intCast")));
+        assert!(V0_11.has_builtin("fabs") && !V0_12.has_builtin("fabs"));
+        assert!(!V0_11.has_builtin("abs") && V0_12.has_builtin("abs"));
+        assert!(V0_13.has_builtin("setCold") && !V0_14.has_builtin("setCold"));
+        assert!(!V0_13.has_builtin("branchHint") && V0_14.has_builtin("branchHint"));
+        assert!(V0_14.has_builtin("Frame") && !Master.has_builtin("Frame"));
+        assert!(!V0_14.has_builtin("memmove") && Master.has_builtin("memmove"));
+        assert!(!V0_14.has_builtin("foo"));
+        assert!(!V0_14.has_builtin("intcast"));
+        assert_eq!(V0_14.builtins().count(), BuiltinFn::all().count() - 5);
+    }
+
+    #[test]
+    fn test_keywords_by_version() {
+        let source = b"async await usingnamespace suspend";
+        let tags = |version| -> Vec<Tag> {
+            TokenStream::new(source)
+                .language_version(version)
+                .map(|token| token.tag)
+                .collect()
+        };
+        assert_eq!(
+            tags(LanguageVersion::V0_11),
+            [
+                Tag::KWAsync,
+                Tag::KWAwait,
+                Tag::KWUsingnamespace,
+                Tag::KWSuspend
+            ]
+        );
+        assert_eq!(
+            tags(LanguageVersion::Master),
+            [
+                Tag::Identifier,
+                Tag::Identifier,
+                Tag::Identifier,
+                Tag::KWSuspend
+            ]
+        );
+    }
+
+    #[test]
+    fn test_from_name() {
+        for version in LanguageVersion::ALL {
+            assert_eq!(LanguageVersion::from_name(version.name()), Some(version));
+        }
+        assert_eq!(
+            LanguageVersion::from_name("0.13.0"),
+            Some(LanguageVersion::V0_13)
+        );
+        assert_eq!(LanguageVersion::from_name("0.10"), None);
+        assert_eq!(LanguageVersion::from_name("0.12.x"), None);
+        assert_eq!(LanguageVersion::from_name("1.0"), None);
+    }
+}
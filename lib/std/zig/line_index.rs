@@ -0,0 +1,185 @@
+use std::fmt;
+
+use crate::zig::tokenizer::Loc;
+
+/// Precomputed start offsets of every line in a buffer. Build it once per
+/// buffer and reuse it for every lookup.
+#[derive(Debug, Clone)]
+pub struct LineIndex<'a> {
+    buffer: &'a [u8],
+    line_starts: Vec<usize>,
+}
+
+/// Zero-based position of a byte offset. `column` counts bytes,
+/// `char_column` counts UTF-8 code points and `utf16_column` counts UTF-16
+/// code units, which is what LSP clients expect by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+    pub char_column: usize,
+    pub utf16_column: usize,
+    pub line_start: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnKind {
+    Byte,
+    Char,
+    Utf16,
+}
+
+impl fmt::Display for Location {
+    /// Formats as one-based `line:column` with a byte column, matching the
+    /// compiler's own error messages.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line + 1, self.column + 1)
+    }
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(buffer: &'a [u8]) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(
+            buffer
+                .iter()
+                .enumerate()
+                .filter(|&(_, &byte)| byte == b'\n')
+                .map(|(i, _)| i + 1),
+        );
+        Self {
+            buffer,
+            line_starts,
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    pub fn line_start(&self, line: usize) -> usize {
+        self.line_starts[line]
+    }
+
+    /// End of `line`, excluding its `\n` (but not a preceding `\r`).
+    pub fn line_end(&self, line: usize) -> usize {
+        match self.line_starts.get(line + 1) {
+            Some(&next) => next - 1,
+            None => self.buffer.len(),
+        }
+    }
+
+    pub fn line_of(&self, offset: usize) -> usize {
+        assert!(offset <= self.buffer.len());
+        self.line_starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    pub fn location(&self, offset: usize) -> Location {
+        let line = self.line_of(offset);
+        let line_start = self.line_starts[line];
+        let prefix = &self.buffer[line_start..offset];
+        Location {
+            line,
+            column: prefix.len(),
+            char_column: prefix.iter().filter(|&&b| !is_continuation(b)).count(),
+            utf16_column: prefix.iter().map(|&b| utf16_units(b)).sum(),
+            line_start,
+        }
+    }
+
+    pub fn loc_location(&self, loc: Loc) -> (Location, Location) {
+        (self.location(loc.start), self.location(loc.end))
+    }
+
+    /// Reverse of [`LineIndex::location`]. Returns `None` when `line` does
+    /// not exist or `column` is past the end of it; a column that lands in
+    /// the middle of a code point also yields `None`.
+    pub fn offset(&self, line: usize, column: usize, kind: ColumnKind) -> Option<usize> {
+        let line_start = *self.line_starts.get(line)?;
+        let line_end = self.line_end(line);
+        if kind == ColumnKind::Byte {
+            let offset = line_start + column;
+            let split = offset < line_end && is_continuation(self.buffer[offset]);
+            return (offset <= line_end && !split).then_some(offset);
+        }
+        let mut units = 0;
+        let mut offset = line_start;
+        while units < column {
+            let &byte = self.buffer[..line_end].get(offset)?;
+            units += match kind {
+                ColumnKind::Char => 1,
+                ColumnKind::Utf16 => utf16_units(byte),
+                ColumnKind::Byte => unreachable!(),
+            };
+            offset += 1;
+            while offset < line_end && is_continuation(self.buffer[offset]) {
+                offset += 1;
+            }
+        }
+        (units == column).then_some(offset)
+    }
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xc0 == 0x80
+}
+
+/// UTF-16 code units needed for the code point led by `byte`. Continuation
+/// bytes contribute nothing so that summing over a slice counts code points.
+fn utf16_units(byte: u8) -> usize {
+    match byte {
+        0x80..=0xbf => 0,
+        0xf0..=0xff => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location() {
+        let source = "const a = 1;\n// ä😀x\nb".as_bytes();
+        let index = LineIndex::new(source);
+        assert_eq!(index.line_count(), 3);
+
+        let x = source.iter().position(|&b| b == b'x').unwrap();
+        let location = index.location(x);
+        assert_eq!(location.line, 1);
+        assert_eq!(location.column, 9);
+        assert_eq!(location.char_column, 5);
+        assert_eq!(location.utf16_column, 6);
+        assert_eq!(location.to_string(), "2:10");
+
+        assert_eq!(index.location(source.len() - 1).line, 2);
+        assert_eq!(index.location(source.len()).column, 1);
+    }
+
+    #[test]
+    fn test_offset_round_trip() {
+        let source = "a\r\n\tä😀x = y;\n".as_bytes();
+        let index = LineIndex::new(source);
+        for offset in 0..=source.len() {
+            if offset < source.len() && is_continuation(source[offset]) {
+                continue;
+            }
+            let location = index.location(offset);
+            for (kind, column) in [
+                (ColumnKind::Byte, location.column),
+                (ColumnKind::Char, location.char_column),
+                (ColumnKind::Utf16, location.utf16_column),
+            ] {
+                assert_eq!(index.offset(location.line, column, kind), Some(offset));
+            }
+        }
+        assert_eq!(index.offset(0, 3, ColumnKind::Byte), None);
+        assert_eq!(index.offset(1, 3, ColumnKind::Utf16), None);
+        assert_eq!(index.offset(1, 2, ColumnKind::Byte), None);
+        assert_eq!(
+            LineIndex::new("é".as_bytes()).offset(0, 1, ColumnKind::Byte),
+            None
+        );
+        assert_eq!(index.offset(5, 0, ColumnKind::Byte), None);
+    }
+}
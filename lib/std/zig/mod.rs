@@ -1 +1,13 @@
+pub mod ast;
+pub mod builtin_fn;
+pub mod fmt;
+pub mod highlight;
+pub mod language_version;
+pub mod line_index;
+pub mod number_literal;
+mod parse;
+pub mod parse_float;
+pub mod primitives;
+pub mod string_literal;
+pub mod token_dump;
 pub mod tokenizer;
@@ -0,0 +1,421 @@
+//! Validation of number literal tokens, ported from upstream
+//! `std.zig.number_literal`. The tokenizer only decides where a
+//! `Tag::NumberLiteral` ends; this checks base prefixes, digits, digit
+//! separators and exponents, and evaluates integers that fit in a `u64`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Base {
+    Decimal = 10,
+    Hex = 16,
+    Binary = 2,
+    Octal = 8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatBase {
+    Decimal = 10,
+    Hex = 16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberLiteral {
+    /// Integer that fits in a `u64`.
+    Int(u64),
+    /// Integer that does not fit in a `u64`; only its base is recorded.
+    BigInt(Base),
+    Float(FloatBase),
+}
+
+/// Why a literal is invalid. Offsets are relative to the start of the token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NumberError {
+    /// Decimal literal with a leading zero, e.g. `0123`.
+    LeadingZero,
+    /// Base prefix with no digits after it, e.g. `0x`.
+    DigitAfterBase,
+    UpperCaseBase(usize),
+    InvalidFloatBase(usize),
+    RepeatedUnderscore(usize),
+    InvalidUnderscoreAfterSpecial(usize),
+    InvalidDigit {
+        i: usize,
+        base: Base,
+    },
+    InvalidDigitExponent(usize),
+    DuplicatePeriod(usize),
+    DuplicateExponent(usize),
+    ExponentAfterUnderscore(usize),
+    SpecialAfterUnderscore(usize),
+    TrailingUnderscore(usize),
+    TrailingSpecial(usize),
+    InvalidCharacter(usize),
+    InvalidExponentSign(usize),
+}
+
+impl Base {
+    fn from_radix(radix: u8) -> Self {
+        match radix {
+            2 => Base::Binary,
+            8 => Base::Octal,
+            10 => Base::Decimal,
+            16 => Base::Hex,
+            _ => unreachable!(),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Base::Decimal => "decimal",
+            Base::Hex => "hex",
+            Base::Binary => "binary",
+            Base::Octal => "octal",
+        }
+    }
+}
+
+impl NumberError {
+    pub fn offset(&self) -> usize {
+        match *self {
+            NumberError::LeadingZero => 0,
+            NumberError::DigitAfterBase => 2,
+            NumberError::InvalidDigit { i, .. } => i,
+            NumberError::UpperCaseBase(i)
+            | NumberError::InvalidFloatBase(i)
+            | NumberError::RepeatedUnderscore(i)
+            | NumberError::InvalidUnderscoreAfterSpecial(i)
+            | NumberError::InvalidDigitExponent(i)
+            | NumberError::DuplicatePeriod(i)
+            | NumberError::DuplicateExponent(i)
+            | NumberError::ExponentAfterUnderscore(i)
+            | NumberError::SpecialAfterUnderscore(i)
+            | NumberError::TrailingUnderscore(i)
+            | NumberError::TrailingSpecial(i)
+            | NumberError::InvalidCharacter(i)
+            | NumberError::InvalidExponentSign(i) => i,
+        }
+    }
+
+    /// Human readable description, worded like the compiler's own errors.
+    /// `bytes` must be the literal the error was produced from.
+    pub fn message(&self, bytes: &[u8]) -> String {
+        let at = |i: usize| bytes[i] as char;
+        match *self {
+            NumberError::LeadingZero => format!(
+                "number '{}' has leading zero",
+                String::from_utf8_lossy(bytes)
+            ),
+            NumberError::DigitAfterBase => "expected a digit after base prefix".to_string(),
+            NumberError::UpperCaseBase(_) => "base prefix must be lowercase".to_string(),
+            NumberError::InvalidFloatBase(_) => "invalid base for float literal".to_string(),
+            NumberError::RepeatedUnderscore(_) => "repeated digit separator".to_string(),
+            NumberError::InvalidUnderscoreAfterSpecial(_) => {
+                "expected digit before digit separator".to_string()
+            }
+            NumberError::InvalidDigit { i, base } => {
+                format!("invalid digit '{}' for {} base", at(i), base.name())
+            }
+            NumberError::InvalidDigitExponent(i) => {
+                format!("invalid digit '{}' in exponent", at(i))
+            }
+            NumberError::DuplicatePeriod(_) => "duplicate period".to_string(),
+            NumberError::DuplicateExponent(_) => "duplicate exponent".to_string(),
+            NumberError::ExponentAfterUnderscore(_) => "expected digit before exponent".to_string(),
+            NumberError::SpecialAfterUnderscore(i) => {
+                format!("expected digit before '{}'", at(i))
+            }
+            NumberError::TrailingSpecial(i) => format!("expected digit after '{}'", at(i)),
+            NumberError::TrailingUnderscore(_) => "trailing digit separator".to_string(),
+            NumberError::InvalidCharacter(i) => format!("invalid character '{}'", at(i)),
+            NumberError::InvalidExponentSign(i) => {
+                let base = if bytes.starts_with(b"0x") {
+                    "hex"
+                } else {
+                    "decimal"
+                };
+                format!(
+                    "sign '{}' cannot follow digit '{}' in {} base",
+                    at(i),
+                    at(i - 1),
+                    base
+                )
+            }
+        }
+    }
+}
+
+/// Validates the text of a `Tag::NumberLiteral` token and evaluates it if it
+/// is an integer.
+pub fn parse_number_literal(bytes: &[u8]) -> Result<NumberLiteral, NumberError> {
+    let mut i = 0;
+    let mut base: u8 = 10;
+    if bytes.len() >= 2 && bytes[0] == b'0' {
+        match bytes[1] {
+            b'b' => {
+                base = 2;
+                i = 2;
+            }
+            b'o' => {
+                base = 8;
+                i = 2;
+            }
+            b'x' => {
+                base = 16;
+                i = 2;
+            }
+            b'B' | b'O' | b'X' => return Err(NumberError::UpperCaseBase(1)),
+            b'.' | b'e' | b'E' => {}
+            _ => return Err(NumberError::LeadingZero),
+        }
+    }
+    if bytes.len() == 2 && base != 10 {
+        return Err(NumberError::DigitAfterBase);
+    }
+
+    let mut x: u64 = 0;
+    let mut overflow = false;
+    let mut underscore = false;
+    let mut period = false;
+    let mut special: u8 = 0;
+    let mut exponent = false;
+    let mut float = false;
+    while i < bytes.len() {
+        let c = bytes[i];
+        match c {
+            b'_' => {
+                if (i == 2 && base != 10) || special != 0 {
+                    return Err(NumberError::InvalidUnderscoreAfterSpecial(i));
+                }
+                if underscore {
+                    return Err(NumberError::RepeatedUnderscore(i));
+                }
+                underscore = true;
+                i += 1;
+                continue;
+            }
+            b'e' | b'E' | b'p' | b'P'
+                if matches!((c, base), (b'e' | b'E', 10) | (b'p' | b'P', 16)) =>
+            {
+                if base == 16 && i == 2 {
+                    return Err(NumberError::DigitAfterBase);
+                }
+                float = true;
+                if exponent {
+                    return Err(NumberError::DuplicateExponent(i));
+                }
+                if underscore {
+                    return Err(NumberError::ExponentAfterUnderscore(i));
+                }
+                special = c;
+                exponent = true;
+                i += 1;
+                continue;
+            }
+            b'.' => {
+                if exponent {
+                    if i >= 2 && bytes[i - 2].is_ascii_digit() {
+                        return Err(NumberError::TrailingSpecial(i));
+                    }
+                    return Err(NumberError::InvalidCharacter(i));
+                }
+                float = true;
+                if base != 10 && base != 16 {
+                    return Err(NumberError::InvalidFloatBase(2));
+                }
+                if period {
+                    return Err(NumberError::DuplicatePeriod(i));
+                }
+                period = true;
+                if underscore {
+                    return Err(NumberError::SpecialAfterUnderscore(i));
+                }
+                special = c;
+                i += 1;
+                continue;
+            }
+            b'+' | b'-' => {
+                match special {
+                    b'p' | b'P' => {}
+                    b'e' | b'E' if base == 10 => {}
+                    _ => return Err(NumberError::InvalidExponentSign(i)),
+                }
+                special = c;
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+        let digit = match c {
+            b'0'..=b'9' => c - b'0',
+            b'A'..=b'Z' => c - b'A' + 10,
+            b'a'..=b'z' => c - b'a' + 10,
+            _ => return Err(NumberError::InvalidCharacter(i)),
+        };
+        if digit >= base {
+            return Err(NumberError::InvalidDigit {
+                i,
+                base: Base::from_radix(base),
+            });
+        }
+        if exponent && digit >= 10 {
+            return Err(NumberError::InvalidDigitExponent(i));
+        }
+        underscore = false;
+        special = 0;
+        i += 1;
+
+        if float {
+            continue;
+        }
+        match x
+            .checked_mul(base as u64)
+            .and_then(|x| x.checked_add(digit as u64))
+        {
+            Some(value) => x = value,
+            None => overflow = true,
+        }
+    }
+    if underscore {
+        return Err(NumberError::TrailingUnderscore(bytes.len() - 1));
+    }
+    if special != 0 {
+        return Err(NumberError::TrailingSpecial(bytes.len() - 1));
+    }
+
+    if float {
+        return Ok(NumberLiteral::Float(if base == 16 {
+            FloatBase::Hex
+        } else {
+            FloatBase::Decimal
+        }));
+    }
+    if overflow {
+        return Ok(NumberLiteral::BigInt(Base::from_radix(base)));
+    }
+    Ok(NumberLiteral::Int(x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zig::tokenizer::{Tag, TokenStream};
+
+    #[test]
+    fn test_parse_number_literal() {
+        assert_eq!(parse_number_literal(b"0"), Ok(NumberLiteral::Int(0)));
+        assert_eq!(parse_number_literal(b"1_000"), Ok(NumberLiteral::Int(1000)));
+        assert_eq!(
+            parse_number_literal(b"0xFF_ff"),
+            Ok(NumberLiteral::Int(0xffff))
+        );
+        assert_eq!(
+            parse_number_literal(b"0o777"),
+            Ok(NumberLiteral::Int(0o777))
+        );
+        assert_eq!(parse_number_literal(b"0b1010"), Ok(NumberLiteral::Int(10)));
+        assert_eq!(
+            parse_number_literal(b"18446744073709551615"),
+            Ok(NumberLiteral::Int(u64::MAX))
+        );
+        assert_eq!(
+            parse_number_literal(b"18446744073709551616"),
+            Ok(NumberLiteral::BigInt(Base::Decimal))
+        );
+        assert_eq!(
+            parse_number_literal(b"0x1_0000_0000_0000_0000"),
+            Ok(NumberLiteral::BigInt(Base::Hex))
+        );
+        for float in ["1.5", "1e10", "1.5e-3", "0.0", "1_0.0_1E+1_0"] {
+            assert_eq!(
+                parse_number_literal(float.as_bytes()),
+                Ok(NumberLiteral::Float(FloatBase::Decimal)),
+                "{float}"
+            );
+        }
+        for float in ["0x1p3", "0x1.8p-3", "0xa.bP+1", "0x1.f"] {
+            assert_eq!(
+                parse_number_literal(float.as_bytes()),
+                Ok(NumberLiteral::Float(FloatBase::Hex)),
+                "{float}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_number_literal_errors() {
+        let cases: &[(&str, NumberError)] = &[
+            ("0123", NumberError::LeadingZero),
+            ("0x", NumberError::DigitAfterBase),
+            ("0xp1", NumberError::DigitAfterBase),
+            ("0X1", NumberError::UpperCaseBase(1)),
+            ("0b1.0", NumberError::InvalidFloatBase(2)),
+            ("1__0", NumberError::RepeatedUnderscore(2)),
+            ("0x_1", NumberError::InvalidUnderscoreAfterSpecial(2)),
+            ("1._0", NumberError::InvalidUnderscoreAfterSpecial(2)),
+            (
+                "0b102",
+                NumberError::InvalidDigit {
+                    i: 4,
+                    base: Base::Binary,
+                },
+            ),
+            (
+                "0o8",
+                NumberError::InvalidDigit {
+                    i: 2,
+                    base: Base::Octal,
+                },
+            ),
+            ("0x1pa", NumberError::InvalidDigitExponent(4)),
+            (
+                "0b1p1",
+                NumberError::InvalidDigit {
+                    i: 3,
+                    base: Base::Binary,
+                },
+            ),
+            ("1.0.0", NumberError::DuplicatePeriod(3)),
+            ("1e1e1", NumberError::DuplicateExponent(3)),
+            ("1_e1", NumberError::ExponentAfterUnderscore(2)),
+            ("1_.0", NumberError::SpecialAfterUnderscore(2)),
+            ("1_", NumberError::TrailingUnderscore(1)),
+            ("1e", NumberError::TrailingSpecial(1)),
+            ("1.", NumberError::TrailingSpecial(1)),
+            ("1e+", NumberError::TrailingSpecial(2)),
+            ("1+1", NumberError::InvalidExponentSign(1)),
+            ("0x1e+1", NumberError::InvalidExponentSign(4)),
+        ];
+        for &(literal, expected) in cases {
+            assert_eq!(
+                parse_number_literal(literal.as_bytes()),
+                Err(expected),
+                "{literal}"
+            );
+        }
+        assert_eq!(
+            NumberError::InvalidDigit {
+                i: 4,
+                base: Base::Binary
+            }
+            .message(b"0b102"),
+            "invalid digit '2' for binary base"
+        );
+    }
+
+    #[test]
+    fn test_number_literal_tokens() {
+        let source = b"0x1p-3 1e10 0o17 1_000_000";
+        let values: Vec<_> = TokenStream::new(source)
+            .inspect(|token| assert_eq!(token.tag, Tag::NumberLiteral))
+            .map(|token| parse_number_literal(&source[token.loc.start..token.loc.end]))
+            .collect();
+        assert_eq!(
+            values,
+            [
+                Ok(NumberLiteral::Float(FloatBase::Hex)),
+                Ok(NumberLiteral::Float(FloatBase::Decimal)),
+                Ok(NumberLiteral::Int(0o17)),
+                Ok(NumberLiteral::Int(1_000_000)),
+            ]
+        );
+    }
+}
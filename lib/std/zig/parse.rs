@@ -0,0 +1,3335 @@
+//! Recursive-descent parser, ported from upstream `std.zig.Ast.Parse`. Use
+//! [`Ast::parse`](crate::zig::ast::Ast::parse) rather than this directly.
+//!
+//! Functions are named and ordered after their upstream counterparts, so the
+//! two can be compared side by side. `parse_*` functions return
+//! [`NULL_NODE`] when the construct is absent, and `expect_*` functions
+//! report an error instead.
+
+use crate::zig::ast::{
+    ArrayTypeSentinel, Asm, ContainerField, Data, Error, ErrorTag, Extra, FnProto, FnProtoOne, For,
+    GlobalVarDecl, If, LocalVarDecl, Node, NodeIndex, NodeList, NodeTag, PtrType, PtrTypeBitRange,
+    Slice, SliceSentinel, SubRange, TokenIndex, While, WhileCont, NULL_NODE,
+};
+use crate::zig::tokenizer::{Tag, TokenList};
+
+/// Parsing of the current construct failed and an error was recorded. The
+/// caller either recovers by skipping ahead or passes it up.
+#[derive(Debug)]
+struct ParseError;
+
+type Result<T> = std::result::Result<T, ParseError>;
+
+pub(crate) struct Parser<'a> {
+    source: &'a [u8],
+    token_tags: &'a [Tag],
+    token_starts: &'a [u32],
+    tok_i: TokenIndex,
+    errors: Vec<Error>,
+    nodes: NodeList,
+    extra_data: Vec<u32>,
+    scratch: Vec<NodeIndex>,
+}
+
+/// Members of a container, stored inline when there are at most two.
+struct Members {
+    len: usize,
+    lhs: NodeIndex,
+    rhs: NodeIndex,
+    trailing: bool,
+}
+
+impl Members {
+    fn to_span(&self, p: &mut Parser) -> SubRange {
+        match self.len {
+            0 => p.list_to_span(&[]),
+            1 => p.list_to_span(&[self.lhs]),
+            2 => p.list_to_span(&[self.lhs, self.rhs]),
+            _ => SubRange {
+                start: self.lhs,
+                end: self.rhs,
+            },
+        }
+    }
+}
+
+/// Parameters of a function prototype, stored inline when there is at most
+/// one.
+enum SmallSpan {
+    ZeroOrOne(NodeIndex),
+    Multi(SubRange),
+}
+
+#[derive(Default)]
+struct PtrModifiers {
+    align_node: NodeIndex,
+    addrspace_node: NodeIndex,
+    bit_range_start: NodeIndex,
+    bit_range_end: NodeIndex,
+}
+
+/// Where the container members loop is relative to the fields, to report
+/// declarations placed between them.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FieldState {
+    None,
+    Seen,
+    End(NodeIndex),
+    Err,
+}
+
+/// What an expression body is parsed as: an expression or a type
+/// expression. Upstream passes the function itself.
+#[derive(Clone, Copy)]
+enum Body {
+    Expr,
+    TypeExpr,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(source: &'a [u8], tokens: &'a TokenList) -> Self {
+        // Same estimate upstream uses: roughly one node per two tokens.
+        let estimated_nodes = (tokens.len() + 2) / 2;
+        Self {
+            source,
+            token_tags: tokens.tags(),
+            token_starts: tokens.starts(),
+            tok_i: 0,
+            errors: Vec::new(),
+            nodes: NodeList::with_capacity(estimated_nodes),
+            extra_data: Vec::new(),
+            scratch: Vec::new(),
+        }
+    }
+
+    pub(crate) fn finish(self) -> (NodeList, Vec<u32>, Vec<Error>) {
+        (self.nodes, self.extra_data, self.errors)
+    }
+
+    /// Tag of token `index`. Lookahead past the end sees `Eof`.
+    fn tag_at(&self, index: TokenIndex) -> Tag {
+        self.token_tags
+            .get(index as usize)
+            .copied()
+            .unwrap_or(Tag::Eof)
+    }
+
+    fn current(&self) -> Tag {
+        self.tag_at(self.tok_i)
+    }
+
+    fn peek(&self, offset: u32) -> Tag {
+        self.tag_at(self.tok_i + offset)
+    }
+
+    fn list_to_span(&mut self, list: &[NodeIndex]) -> SubRange {
+        let start = self.extra_data.len() as u32;
+        self.extra_data.extend_from_slice(list);
+        SubRange {
+            start,
+            end: self.extra_data.len() as u32,
+        }
+    }
+
+    /// Copies the scratch entries from `top` on into `extra_data`.
+    fn scratch_to_span(&mut self, top: usize) -> SubRange {
+        let start = self.extra_data.len() as u32;
+        self.extra_data.extend_from_slice(&self.scratch[top..]);
+        SubRange {
+            start,
+            end: self.extra_data.len() as u32,
+        }
+    }
+
+    /// Runs `f` with the scratch length on entry and truncates the scratch
+    /// back to it afterwards, like upstream's `defer shrinkRetainingCapacity`.
+    fn with_scratch<T>(&mut self, f: impl FnOnce(&mut Self, usize) -> Result<T>) -> Result<T> {
+        let top = self.scratch.len();
+        let result = f(self, top);
+        self.scratch.truncate(top);
+        result
+    }
+
+    fn add_node(&mut self, tag: NodeTag, main_token: TokenIndex, lhs: u32, rhs: u32) -> NodeIndex {
+        self.nodes.push(Node {
+            tag,
+            main_token,
+            data: Data { lhs, rhs },
+        })
+    }
+
+    fn set_node(
+        &mut self,
+        index: NodeIndex,
+        tag: NodeTag,
+        main_token: TokenIndex,
+        lhs: u32,
+        rhs: u32,
+    ) -> NodeIndex {
+        self.nodes.set(
+            index,
+            Node {
+                tag,
+                main_token,
+                data: Data { lhs, rhs },
+            },
+        );
+        index
+    }
+
+    /// Reserves a slot so a node can come before its children in the
+    /// array.
+    fn reserve_node(&mut self, tag: NodeTag) -> NodeIndex {
+        self.add_node(tag, 0, 0, 0)
+    }
+
+    fn unreserve_node(&mut self, index: NodeIndex) {
+        if self.nodes.len() == index as usize + 1 {
+            self.nodes.pop();
+        } else {
+            // There is a zombie node left in the tree; make it as
+            // inoffensive as possible, since there is no no-op node.
+            let tok_i = self.tok_i;
+            self.set_node(index, NodeTag::UnreachableLiteral, tok_i, 0, 0);
+        }
+    }
+
+    fn add_extra(&mut self, extra: impl Extra) -> u32 {
+        let index = self.extra_data.len() as u32;
+        extra.write(&mut self.extra_data);
+        index
+    }
+
+    fn warn_expected(&mut self, expected: Tag) {
+        self.warn_msg(Error {
+            tag: ErrorTag::ExpectedToken,
+            is_note: false,
+            token_is_prev: false,
+            token: self.tok_i,
+            expected_tag: Some(expected),
+        });
+    }
+
+    fn warn(&mut self, tag: ErrorTag) {
+        self.warn_msg(Self::msg(tag, self.tok_i));
+    }
+
+    fn msg(tag: ErrorTag, token: TokenIndex) -> Error {
+        Error {
+            tag,
+            is_note: false,
+            token_is_prev: false,
+            token,
+            expected_tag: None,
+        }
+    }
+
+    fn warn_msg(&mut self, mut msg: Error) {
+        use ErrorTag::*;
+        let points_after_previous = matches!(
+            msg.tag,
+            ExpectedSemiAfterDecl
+                | ExpectedSemiAfterStmt
+                | ExpectedCommaAfterField
+                | ExpectedCommaAfterArg
+                | ExpectedCommaAfterParam
+                | ExpectedCommaAfterInitializer
+                | ExpectedCommaAfterSwitchProng
+                | ExpectedCommaAfterForOperand
+                | ExpectedCommaAfterCapture
+                | ExpectedSemiOrElse
+                | ExpectedSemiOrLbrace
+                | ExpectedToken
+                | ExpectedBlock
+                | ExpectedBlockOrAssignment
+                | ExpectedBlockOrExpr
+                | ExpectedBlockOrField
+                | ExpectedExpr
+                | ExpectedExprOrAssignment
+                | ExpectedFn
+                | ExpectedInlinable
+                | ExpectedLabelable
+                | ExpectedParamList
+                | ExpectedPrefixExpr
+                | ExpectedPrimaryTypeExpr
+                | ExpectedPubItem
+                | ExpectedReturnType
+                | ExpectedSuffixOp
+                | ExpectedTypeExpr
+                | ExpectedVarDecl
+                | ExpectedVarDeclOrFn
+                | ExpectedLoopPayload
+                | ExpectedContainer
+        );
+        // When the problem is on a later line, point just past the previous
+        // token, where the missing piece belongs.
+        if points_after_previous
+            && msg.token != 0
+            && !self.tokens_on_same_line(msg.token - 1, msg.token)
+        {
+            msg.token_is_prev = true;
+            msg.token -= 1;
+        }
+        self.errors.push(msg);
+    }
+
+    fn fail<T>(&mut self, tag: ErrorTag) -> Result<T> {
+        self.fail_msg(Self::msg(tag, self.tok_i))
+    }
+
+    fn fail_expected<T>(&mut self, expected: Tag) -> Result<T> {
+        self.warn_expected(expected);
+        Err(ParseError)
+    }
+
+    fn fail_msg<T>(&mut self, msg: Error) -> Result<T> {
+        self.warn_msg(msg);
+        Err(ParseError)
+    }
+
+    fn note(&mut self, tag: ErrorTag, token: TokenIndex) {
+        self.warn_msg(Error {
+            is_note: true,
+            ..Self::msg(tag, token)
+        });
+    }
+
+    /// Root <- skip container_doc_comment? ContainerMembers eof
+    pub(crate) fn parse_root(&mut self) {
+        self.add_node(NodeTag::Root, 0, 0, 0);
+        let root_members = self.parse_container_members();
+        let root_decls = root_members.to_span(self);
+        if self.current() != Tag::Eof {
+            self.warn_expected(Tag::Eof);
+        }
+        *self.nodes.data_mut(0) = Data {
+            lhs: root_decls.start,
+            rhs: root_decls.end,
+        };
+    }
+
+    /// Parses a ZON file: a single expression.
+    pub(crate) fn parse_zon(&mut self) {
+        self.add_node(NodeTag::Root, 0, 0, 0);
+        let Ok(node) = self.expect_expr() else {
+            debug_assert!(!self.errors.is_empty());
+            return;
+        };
+        if self.current() != Tag::Eof {
+            self.warn_expected(Tag::Eof);
+        }
+        *self.nodes.data_mut(0) = Data { lhs: node, rhs: 0 };
+    }
+
+    /// ContainerMembers <- ContainerDeclaration* (ContainerField COMMA)* (ContainerField / ContainerDeclaration*)
+    ///
+    /// ContainerDeclaration <- TestDecl / ComptimeDecl / doc_comment? KEYWORD_pub? Decl
+    ///
+    /// ComptimeDecl <- KEYWORD_comptime Block
+    fn parse_container_members(&mut self) -> Members {
+        let top = self.scratch.len();
+        let mut field_state = FieldState::None;
+        let mut last_field: TokenIndex = 0;
+
+        // Skip container doc comments.
+        while self.eat_token(Tag::ContainerDocComment).is_some() {}
+
+        let mut trailing = false;
+        loop {
+            let doc_comment = self.eat_doc_comments();
+
+            match self.current() {
+                Tag::KWTest => {
+                    if let Some(some) = doc_comment {
+                        self.warn_msg(Self::msg(ErrorTag::TestDocComment, some));
+                    }
+                    if let Some(test_decl) = self.expect_test_decl_recoverable() {
+                        if field_state == FieldState::Seen {
+                            field_state = FieldState::End(test_decl);
+                        }
+                        self.scratch.push(test_decl);
+                    }
+                    trailing = false;
+                }
+                Tag::KWComptime if self.peek(1) == Tag::LBrace => {
+                    if let Some(some) = doc_comment {
+                        self.warn_msg(Self::msg(ErrorTag::ComptimeDocComment, some));
+                    }
+                    let comptime_token = self.next_token();
+                    let block = self.parse_block().unwrap_or_else(|ParseError| {
+                        self.find_next_container_member();
+                        NULL_NODE
+                    });
+                    if block != NULL_NODE {
+                        let comptime_node =
+                            self.add_node(NodeTag::Comptime, comptime_token, block, 0);
+                        if field_state == FieldState::Seen {
+                            field_state = FieldState::End(comptime_node);
+                        }
+                        self.scratch.push(comptime_node);
+                    }
+                    trailing = false;
+                }
+                Tag::KWPub => {
+                    self.tok_i += 1;
+                    let top_level_decl = self.expect_top_level_decl_recoverable();
+                    if top_level_decl != NULL_NODE {
+                        if field_state == FieldState::Seen {
+                            field_state = FieldState::End(top_level_decl);
+                        }
+                        self.scratch.push(top_level_decl);
+                    }
+                    trailing = self.tag_at(self.tok_i - 1) == Tag::Semicolon;
+                }
+                Tag::KWUsingnamespace => {
+                    let node = self.expect_using_namespace_recoverable();
+                    if node != NULL_NODE {
+                        if field_state == FieldState::Seen {
+                            field_state = FieldState::End(node);
+                        }
+                        self.scratch.push(node);
+                    }
+                    trailing = self.tag_at(self.tok_i - 1) == Tag::Semicolon;
+                }
+                Tag::KWConst
+                | Tag::KWVar
+                | Tag::KWThreadlocal
+                | Tag::KWExport
+                | Tag::KWExtern
+                | Tag::KWInline
+                | Tag::KWNoinline
+                | Tag::KWFn => {
+                    let top_level_decl = self.expect_top_level_decl_recoverable();
+                    if top_level_decl != NULL_NODE {
+                        if field_state == FieldState::Seen {
+                            field_state = FieldState::End(top_level_decl);
+                        }
+                        self.scratch.push(top_level_decl);
+                    }
+                    trailing = self.tag_at(self.tok_i - 1) == Tag::Semicolon;
+                }
+                Tag::Eof | Tag::RBrace => {
+                    if let Some(token) = doc_comment {
+                        self.warn_msg(Self::msg(ErrorTag::UnattachedDocComment, token));
+                    }
+                    break;
+                }
+                tag => {
+                    let is_comptime_field = tag == Tag::KWComptime;
+                    if !is_comptime_field && self.parse_c_style_container().unwrap_or(false) {
+                        continue;
+                    }
+
+                    let identifier = self.tok_i;
+                    let container_field = self.expect_container_field();
+                    let previous_field = std::mem::replace(&mut last_field, identifier);
+                    let Ok(container_field) = container_field else {
+                        self.find_next_container_member();
+                        continue;
+                    };
+                    match field_state {
+                        FieldState::None => field_state = FieldState::Seen,
+                        FieldState::Err | FieldState::Seen => {}
+                        FieldState::End(node) => {
+                            let decl_token = self.nodes.main_tokens()[node as usize];
+                            self.warn_msg(Self::msg(ErrorTag::DeclBetweenFields, decl_token));
+                            self.note(ErrorTag::PreviousField, previous_field);
+                            self.note(ErrorTag::NextField, identifier);
+                            // Continue parsing; the error is already reported.
+                            field_state = FieldState::Err;
+                        }
+                    }
+                    self.scratch.push(container_field);
+                    match self.current() {
+                        Tag::Comma => {
+                            self.tok_i += 1;
+                            trailing = true;
+                            continue;
+                        }
+                        Tag::RBrace | Tag::Eof => {
+                            trailing = false;
+                            break;
+                        }
+                        _ => {}
+                    }
+                    // A declaration is not allowed after a field with no
+                    // comma. Report the error and recover.
+                    self.warn(ErrorTag::ExpectedCommaAfterField);
+                    if !is_comptime_field
+                        && self.current() == Tag::Semicolon
+                        && self.tag_at(identifier) == Tag::Identifier
+                    {
+                        self.note(ErrorTag::VarConstDecl, identifier);
+                    }
+                    self.find_next_container_member();
+                }
+            }
+        }
+
+        let items = &self.scratch[top..];
+        let members = match *items {
+            [] => Members {
+                len: 0,
+                lhs: 0,
+                rhs: 0,
+                trailing,
+            },
+            [lhs] => Members {
+                len: 1,
+                lhs,
+                rhs: 0,
+                trailing,
+            },
+            [lhs, rhs] => Members {
+                len: 2,
+                lhs,
+                rhs,
+                trailing,
+            },
+            _ => {
+                let len = items.len();
+                let span = self.scratch_to_span(top);
+                Members {
+                    len,
+                    lhs: span.start,
+                    rhs: span.end,
+                    trailing,
+                }
+            }
+        };
+        self.scratch.truncate(top);
+        members
+    }
+
+    /// Attempts to find the next container member by searching for certain
+    /// tokens.
+    fn find_next_container_member(&mut self) {
+        let mut level: u32 = 0;
+        loop {
+            let tok = self.next_token();
+            match self.tag_at(tok) {
+                // Any of these can start a new top level declaration.
+                Tag::KWTest
+                | Tag::KWComptime
+                | Tag::KWPub
+                | Tag::KWExport
+                | Tag::KWExtern
+                | Tag::KWInline
+                | Tag::KWNoinline
+                | Tag::KWUsingnamespace
+                | Tag::KWThreadlocal
+                | Tag::KWConst
+                | Tag::KWVar
+                | Tag::KWFn
+                    if level == 0 =>
+                {
+                    self.tok_i -= 1;
+                    return;
+                }
+                Tag::Identifier if self.tag_at(tok + 1) == Tag::Comma && level == 0 => {
+                    self.tok_i -= 1;
+                    return;
+                }
+                // This decl was likely meant to end here.
+                Tag::Comma | Tag::Semicolon if level == 0 => return,
+                Tag::LParen | Tag::LBrack | Tag::LBrace => level += 1,
+                Tag::RParen | Tag::RBrack => level = level.saturating_sub(1),
+                Tag::RBrace => {
+                    if level == 0 {
+                        // End of container, exit.
+                        self.tok_i -= 1;
+                        return;
+                    }
+                    level -= 1;
+                }
+                Tag::Eof => {
+                    self.tok_i -= 1;
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Attempts to find the next statement by searching for a semicolon.
+    fn find_next_stmt(&mut self) {
+        let mut level: u32 = 0;
+        loop {
+            let tok = self.next_token();
+            match self.tag_at(tok) {
+                Tag::LBrace => level += 1,
+                Tag::RBrace => {
+                    if level == 0 {
+                        self.tok_i -= 1;
+                        return;
+                    }
+                    level -= 1;
+                }
+                Tag::Semicolon if level == 0 => return,
+                Tag::Eof => {
+                    self.tok_i -= 1;
+                    return;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// TestDecl <- KEYWORD_test (STRINGLITERALSINGLE / IDENTIFIER)? Block
+    fn expect_test_decl(&mut self) -> Result<NodeIndex> {
+        let test_token = self.assert_token(Tag::KWTest);
+        let name_token = match self.current() {
+            Tag::StringLiteral | Tag::Identifier => self.next_token(),
+            _ => 0,
+        };
+        let block_node = self.parse_block()?;
+        if block_node == NULL_NODE {
+            return self.fail(ErrorTag::ExpectedBlock);
+        }
+        Ok(self.add_node(NodeTag::TestDecl, test_token, name_token, block_node))
+    }
+
+    fn expect_test_decl_recoverable(&mut self) -> Option<NodeIndex> {
+        match self.expect_test_decl() {
+            Ok(node) => Some(node),
+            Err(ParseError) => {
+                self.find_next_container_member();
+                None
+            }
+        }
+    }
+
+    /// Decl
+    ///     <- (KEYWORD_export / KEYWORD_extern STRINGLITERALSINGLE? / KEYWORD_inline / KEYWORD_noinline)? FnProto (SEMICOLON / Block)
+    ///      / (KEYWORD_export / KEYWORD_extern STRINGLITERALSINGLE?)? KEYWORD_threadlocal? GlobalVarDecl
+    ///      / KEYWORD_usingnamespace Expr SEMICOLON
+    fn expect_top_level_decl(&mut self) -> Result<NodeIndex> {
+        let extern_export_inline_token = self.next_token();
+        let mut is_extern = false;
+        let mut expect_fn = false;
+        let mut expect_var_or_fn = false;
+        match self.tag_at(extern_export_inline_token) {
+            Tag::KWExtern => {
+                self.eat_token(Tag::StringLiteral);
+                is_extern = true;
+                expect_var_or_fn = true;
+            }
+            Tag::KWExport => expect_var_or_fn = true,
+            Tag::KWInline | Tag::KWNoinline => expect_fn = true,
+            _ => self.tok_i -= 1,
+        }
+        let fn_proto = self.parse_fn_proto()?;
+        if fn_proto != NULL_NODE {
+            match self.current() {
+                Tag::Semicolon => {
+                    self.tok_i += 1;
+                    return Ok(fn_proto);
+                }
+                Tag::LBrace => {
+                    if is_extern {
+                        self.warn_msg(Self::msg(
+                            ErrorTag::ExternFnBody,
+                            extern_export_inline_token,
+                        ));
+                        return Ok(NULL_NODE);
+                    }
+                    let fn_decl_index = self.reserve_node(NodeTag::FnDecl);
+                    let body_block = match self.parse_block() {
+                        Ok(block) => block,
+                        Err(err) => {
+                            self.unreserve_node(fn_decl_index);
+                            return Err(err);
+                        }
+                    };
+                    debug_assert_ne!(body_block, NULL_NODE);
+                    let main_token = self.nodes.main_tokens()[fn_proto as usize];
+                    return Ok(self.set_node(
+                        fn_decl_index,
+                        NodeTag::FnDecl,
+                        main_token,
+                        fn_proto,
+                        body_block,
+                    ));
+                }
+                _ => {
+                    // Since parse_block only fails on a missing '}', this
+                    // function was probably supposed to end here.
+                    self.warn(ErrorTag::ExpectedSemiOrLbrace);
+                    return Ok(NULL_NODE);
+                }
+            }
+        }
+        if expect_fn {
+            return self.fail(ErrorTag::ExpectedFn);
+        }
+
+        let thread_local_token = self.eat_token(Tag::KWThreadlocal);
+        let var_decl = self.parse_global_var_decl()?;
+        if var_decl != NULL_NODE {
+            return Ok(var_decl);
+        }
+        if thread_local_token.is_some() {
+            return self.fail(ErrorTag::ExpectedVarDecl);
+        }
+        if expect_var_or_fn {
+            return self.fail(ErrorTag::ExpectedVarDeclOrFn);
+        }
+        if self.current() != Tag::KWUsingnamespace {
+            return self.fail(ErrorTag::ExpectedPubItem);
+        }
+        self.expect_using_namespace()
+    }
+
+    fn expect_top_level_decl_recoverable(&mut self) -> NodeIndex {
+        self.expect_top_level_decl().unwrap_or_else(|ParseError| {
+            self.find_next_container_member();
+            NULL_NODE
+        })
+    }
+
+    fn expect_using_namespace(&mut self) -> Result<NodeIndex> {
+        let usingnamespace_token = self.assert_token(Tag::KWUsingnamespace);
+        let expr = self.expect_expr()?;
+        self.expect_semicolon(ErrorTag::ExpectedSemiAfterDecl, false)?;
+        Ok(self.add_node(NodeTag::Usingnamespace, usingnamespace_token, expr, 0))
+    }
+
+    fn expect_using_namespace_recoverable(&mut self) -> NodeIndex {
+        self.expect_using_namespace().unwrap_or_else(|ParseError| {
+            self.find_next_container_member();
+            NULL_NODE
+        })
+    }
+
+    /// FnProto <- KEYWORD_fn IDENTIFIER? LPAREN ParamDeclList RPAREN ByteAlign? AddrSpace? LinkSection? CallConv? EXCLAMATIONMARK? TypeExpr
+    fn parse_fn_proto(&mut self) -> Result<NodeIndex> {
+        let Some(fn_token) = self.eat_token(Tag::KWFn) else {
+            return Ok(NULL_NODE);
+        };
+
+        // We want the fn proto node to be before its children in the array.
+        let fn_proto_index = self.reserve_node(NodeTag::FnProto);
+        match self.finish_fn_proto(fn_token, fn_proto_index) {
+            Ok(node) => Ok(node),
+            Err(err) => {
+                self.unreserve_node(fn_proto_index);
+                Err(err)
+            }
+        }
+    }
+
+    fn finish_fn_proto(&mut self, fn_token: TokenIndex, index: NodeIndex) -> Result<NodeIndex> {
+        self.eat_token(Tag::Identifier);
+        let params = self.parse_param_decl_list()?;
+        let align_expr = self.parse_byte_align()?;
+        let addrspace_expr = self.parse_addr_space()?;
+        let section_expr = self.parse_link_section()?;
+        let callconv_expr = self.parse_callconv()?;
+        self.eat_token(Tag::Bang);
+
+        let return_type_expr = self.parse_type_expr()?;
+        if return_type_expr == NULL_NODE {
+            // Most likely the user forgot to specify the return type. Mark
+            // the return type as invalid and try to continue.
+            self.warn(ErrorTag::ExpectedReturnType);
+        }
+
+        if align_expr == 0 && section_expr == 0 && callconv_expr == 0 && addrspace_expr == 0 {
+            return Ok(match params {
+                SmallSpan::ZeroOrOne(param) => self.set_node(
+                    index,
+                    NodeTag::FnProtoSimple,
+                    fn_token,
+                    param,
+                    return_type_expr,
+                ),
+                SmallSpan::Multi(span) => {
+                    let extra = self.add_extra(span);
+                    self.set_node(
+                        index,
+                        NodeTag::FnProtoMulti,
+                        fn_token,
+                        extra,
+                        return_type_expr,
+                    )
+                }
+            });
+        }
+        Ok(match params {
+            SmallSpan::ZeroOrOne(param) => {
+                let extra = self.add_extra(FnProtoOne {
+                    param,
+                    align_expr,
+                    addrspace_expr,
+                    section_expr,
+                    callconv_expr,
+                });
+                self.set_node(
+                    index,
+                    NodeTag::FnProtoOne,
+                    fn_token,
+                    extra,
+                    return_type_expr,
+                )
+            }
+            SmallSpan::Multi(span) => {
+                let extra = self.add_extra(FnProto {
+                    params_start: span.start,
+                    params_end: span.end,
+                    align_expr,
+                    addrspace_expr,
+                    section_expr,
+                    callconv_expr,
+                });
+                self.set_node(index, NodeTag::FnProto, fn_token, extra, return_type_expr)
+            }
+        })
+    }
+
+    /// VarDeclProto <- (KEYWORD_const / KEYWORD_var) IDENTIFIER (COLON TypeExpr)? ByteAlign? AddrSpace? LinkSection?
+    ///
+    /// Returns a `*_var_decl` node with its rhs (init expression) initialized to 0.
+    fn parse_var_decl_proto(&mut self) -> Result<NodeIndex> {
+        let Some(mut_token) = self
+            .eat_token(Tag::KWConst)
+            .or_else(|| self.eat_token(Tag::KWVar))
+        else {
+            return Ok(NULL_NODE);
+        };
+
+        self.expect_token(Tag::Identifier)?;
+        let type_node = match self.eat_token(Tag::Colon) {
+            Some(_) => self.expect_type_expr()?,
+            None => 0,
+        };
+        let align_node = self.parse_byte_align()?;
+        let addrspace_node = self.parse_addr_space()?;
+        let section_node = self.parse_link_section()?;
+
+        if section_node == 0 && addrspace_node == 0 {
+            if align_node == 0 {
+                return Ok(self.add_node(NodeTag::SimpleVarDecl, mut_token, type_node, 0));
+            }
+            if type_node == 0 {
+                return Ok(self.add_node(NodeTag::AlignedVarDecl, mut_token, align_node, 0));
+            }
+            let extra = self.add_extra(LocalVarDecl {
+                type_node,
+                align_node,
+            });
+            return Ok(self.add_node(NodeTag::LocalVarDecl, mut_token, extra, 0));
+        }
+        let extra = self.add_extra(GlobalVarDecl {
+            type_node,
+            align_node,
+            addrspace_node,
+            section_node,
+        });
+        Ok(self.add_node(NodeTag::GlobalVarDecl, mut_token, extra, 0))
+    }
+
+    /// GlobalVarDecl <- VarDeclProto (EQUAL Expr?) SEMICOLON
+    fn parse_global_var_decl(&mut self) -> Result<NodeIndex> {
+        let var_decl = self.parse_var_decl_proto()?;
+        if var_decl == NULL_NODE {
+            return Ok(NULL_NODE);
+        }
+
+        let init_node = match self.current() {
+            Tag::EqualEqual => {
+                self.warn(ErrorTag::WrongEqualVarDecl);
+                self.tok_i += 1;
+                self.expect_expr()?
+            }
+            Tag::Equal => {
+                self.tok_i += 1;
+                self.expect_expr()?
+            }
+            _ => 0,
+        };
+
+        self.nodes.data_mut(var_decl).rhs = init_node;
+
+        self.expect_semicolon(ErrorTag::ExpectedSemiAfterDecl, false)?;
+        Ok(var_decl)
+    }
+
+    /// ContainerField <- doc_comment? KEYWORD_comptime? !KEYWORD_fn (IDENTIFIER COLON)? TypeExpr ByteAlign? (EQUAL Expr)?
+    fn expect_container_field(&mut self) -> Result<NodeIndex> {
+        self.eat_token(Tag::KWComptime);
+        let main_token = self.tok_i;
+        self.eat_tokens(&[Tag::Identifier, Tag::Colon]);
+        let type_expr = self.expect_type_expr()?;
+        let align_expr = self.parse_byte_align()?;
+        let value_expr = match self.eat_token(Tag::Equal) {
+            Some(_) => self.expect_expr()?,
+            None => 0,
+        };
+
+        if align_expr == 0 {
+            Ok(self.add_node(
+                NodeTag::ContainerFieldInit,
+                main_token,
+                type_expr,
+                value_expr,
+            ))
+        } else if value_expr == 0 {
+            Ok(self.add_node(
+                NodeTag::ContainerFieldAlign,
+                main_token,
+                type_expr,
+                align_expr,
+            ))
+        } else {
+            let extra = self.add_extra(ContainerField {
+                align_expr,
+                value_expr,
+            });
+            Ok(self.add_node(NodeTag::ContainerField, main_token, type_expr, extra))
+        }
+    }
+
+    /// Statement
+    ///     <- KEYWORD_comptime ComptimeStatement
+    ///      / KEYWORD_nosuspend BlockExprStatement
+    ///      / KEYWORD_suspend BlockExprStatement
+    ///      / KEYWORD_defer BlockExprStatement
+    ///      / KEYWORD_errdefer Payload? BlockExprStatement
+    ///      / IfStatement
+    ///      / LabeledStatement
+    ///      / VarDeclExprStatement
+    ///
+    /// ComptimeStatement
+    ///     <- BlockExpr
+    ///      / VarDeclExprStatement
+    fn expect_statement(&mut self, allow_defer_var: bool) -> Result<NodeIndex> {
+        if let Some(comptime_token) = self.eat_token(Tag::KWComptime) {
+            let block_expr = self.parse_block_expr()?;
+            if block_expr != NULL_NODE {
+                return Ok(self.add_node(NodeTag::Comptime, comptime_token, block_expr, 0));
+            }
+
+            if allow_defer_var {
+                return self.expect_var_decl_expr_statement(Some(comptime_token));
+            }
+            let assign = self.expect_assign_expr()?;
+            self.expect_semicolon(ErrorTag::ExpectedSemiAfterStmt, true)?;
+            return Ok(self.add_node(NodeTag::Comptime, comptime_token, assign, 0));
+        }
+
+        match self.current() {
+            Tag::KWNosuspend => {
+                let token = self.next_token();
+                let block_expr = self.expect_block_expr_statement()?;
+                return Ok(self.add_node(NodeTag::Nosuspend, token, block_expr, 0));
+            }
+            Tag::KWSuspend => {
+                let token = self.next_token();
+                let block_expr = self.expect_block_expr_statement()?;
+                return Ok(self.add_node(NodeTag::Suspend, token, block_expr, 0));
+            }
+            Tag::KWDefer if allow_defer_var => {
+                let token = self.next_token();
+                let expr = self.expect_block_expr_statement()?;
+                return Ok(self.add_node(NodeTag::Defer, token, 0, expr));
+            }
+            Tag::KWErrdefer if allow_defer_var => {
+                let token = self.next_token();
+                let payload = self.parse_payload()?;
+                let expr = self.expect_block_expr_statement()?;
+                return Ok(self.add_node(NodeTag::Errdefer, token, payload, expr));
+            }
+            Tag::KWIf => return self.expect_if_statement(),
+            Tag::KWEnum | Tag::KWStruct | Tag::KWUnion => {
+                let identifier = self.tok_i + 1;
+                if self.parse_c_style_container()? {
+                    // Return something so that `expect_statement` is happy.
+                    return Ok(self.add_node(NodeTag::Identifier, identifier, 0, 0));
+                }
+            }
+            _ => {}
+        }
+
+        let labeled_statement = self.parse_labeled_statement()?;
+        if labeled_statement != NULL_NODE {
+            return Ok(labeled_statement);
+        }
+
+        if allow_defer_var {
+            self.expect_var_decl_expr_statement(None)
+        } else {
+            let assign = self.expect_assign_expr()?;
+            self.expect_semicolon(ErrorTag::ExpectedSemiAfterStmt, true)?;
+            Ok(assign)
+        }
+    }
+
+    /// Returns `None` when the block ends after an error was skipped.
+    fn expect_statement_recoverable(&mut self) -> Result<Option<NodeIndex>> {
+        loop {
+            match self.expect_statement(true) {
+                Ok(statement) => return Ok(Some(statement)),
+                Err(ParseError) => {
+                    // Try to skip to the next statement.
+                    self.find_next_stmt();
+                    match self.current() {
+                        Tag::RBrace => return Ok(None),
+                        Tag::Eof => return Err(ParseError),
+                        _ => continue,
+                    }
+                }
+            }
+        }
+    }
+
+    /// VarDeclExprStatement
+    ///    <- VarDeclProto (COMMA (VarDeclProto / Expr))* EQUAL Expr SEMICOLON
+    ///     / Expr (AssignOp Expr / (COMMA (VarDeclProto / Expr))+ EQUAL Expr)? SEMICOLON
+    ///
+    /// If a parse error occurs, reports an error, but then finds the next
+    /// statement and continues.
+    fn expect_var_decl_expr_statement(
+        &mut self,
+        comptime_token: Option<TokenIndex>,
+    ) -> Result<NodeIndex> {
+        self.with_scratch(|p, top| {
+            loop {
+                let var_decl_proto = p.parse_var_decl_proto()?;
+                if var_decl_proto != NULL_NODE {
+                    p.scratch.push(var_decl_proto);
+                } else {
+                    let expr = p.parse_expr()?;
+                    if expr == NULL_NODE {
+                        if p.scratch.len() == top {
+                            // We parsed nothing.
+                            return p.fail(ErrorTag::ExpectedStatement);
+                        }
+                        // We've had at least one LHS, but had a bad comma.
+                        return p.fail(ErrorTag::ExpectedExprOrVarDecl);
+                    }
+                    p.scratch.push(expr);
+                }
+                if p.eat_token(Tag::Comma).is_none() {
+                    break;
+                }
+            }
+
+            let lhs_count = p.scratch.len() - top;
+            debug_assert!(lhs_count > 0);
+            let is_var_decl = |p: &Parser, node: NodeIndex| {
+                matches!(
+                    p.nodes.tags()[node as usize],
+                    NodeTag::GlobalVarDecl
+                        | NodeTag::LocalVarDecl
+                        | NodeTag::SimpleVarDecl
+                        | NodeTag::AlignedVarDecl
+                )
+            };
+
+            let equal_token = match p.eat_token(Tag::Equal) {
+                Some(token) => token,
+                None => {
+                    let lhs = p.scratch[top];
+                    // A destructure or a var decl, so allow recovering from `==`.
+                    if lhs_count > 1 || is_var_decl(p, lhs) {
+                        match p.eat_token(Tag::EqualEqual) {
+                            Some(token) => {
+                                p.warn_msg(Self::msg(ErrorTag::WrongEqualVarDecl, token));
+                                token
+                            }
+                            None => return p.fail_expected(Tag::Equal),
+                        }
+                    } else {
+                        let expr = p.finish_assign_expr(lhs)?;
+                        p.expect_semicolon(ErrorTag::ExpectedSemiAfterStmt, true)?;
+                        return Ok(match comptime_token {
+                            Some(token) => p.add_node(NodeTag::Comptime, token, expr, 0),
+                            None => expr,
+                        });
+                    }
+                }
+            };
+
+            let rhs = p.expect_expr()?;
+            p.expect_semicolon(ErrorTag::ExpectedSemiAfterStmt, true)?;
+
+            if lhs_count == 1 {
+                let lhs = p.scratch[top];
+                if is_var_decl(p, lhs) {
+                    p.nodes.data_mut(lhs).rhs = rhs;
+                    // Don't need to wrap in comptime.
+                    return Ok(lhs);
+                }
+                let expr = p.add_node(NodeTag::Assign, equal_token, lhs, rhs);
+                return Ok(match comptime_token {
+                    Some(token) => p.add_node(NodeTag::Comptime, token, expr, 0),
+                    None => expr,
+                });
+            }
+
+            // An actual destructure! No need for any `comptime` wrapper here.
+            let extra_start = p.extra_data.len() as u32;
+            p.extra_data.push(lhs_count as u32);
+            p.extra_data.extend_from_slice(&p.scratch[top..]);
+            Ok(p.add_node(NodeTag::AssignDestructure, equal_token, extra_start, rhs))
+        })
+    }
+
+    /// IfStatement
+    ///     <- IfPrefix BlockExpr ( KEYWORD_else Payload? Statement )?
+    ///      / IfPrefix AssignExpr ( SEMICOLON / KEYWORD_else Payload? Statement )
+    fn expect_if_statement(&mut self) -> Result<NodeIndex> {
+        let if_token = self.assert_token(Tag::KWIf);
+        self.expect_token(Tag::LParen)?;
+        let condition = self.expect_expr()?;
+        self.expect_token(Tag::RParen)?;
+        self.parse_ptr_payload()?;
+
+        // TODO propose to change the syntax so that semicolons are always
+        // required inside if statements, even if there is an `else`.
+        let mut else_required = false;
+        let then_expr = {
+            let block_expr = self.parse_block_expr()?;
+            if block_expr != NULL_NODE {
+                block_expr
+            } else {
+                let assign_expr = self.parse_assign_expr()?;
+                if assign_expr == NULL_NODE {
+                    return self.fail(ErrorTag::ExpectedBlockOrAssignment);
+                }
+                if self.eat_token(Tag::Semicolon).is_some() {
+                    return Ok(self.add_node(NodeTag::IfSimple, if_token, condition, assign_expr));
+                }
+                else_required = true;
+                assign_expr
+            }
+        };
+        if self.eat_token(Tag::KWElse).is_none() {
+            if else_required {
+                self.warn(ErrorTag::ExpectedSemiOrElse);
+            }
+            return Ok(self.add_node(NodeTag::IfSimple, if_token, condition, then_expr));
+        }
+        self.parse_payload()?;
+        let else_expr = self.expect_statement(false)?;
+        let extra = self.add_extra(If {
+            then_expr,
+            else_expr,
+        });
+        Ok(self.add_node(NodeTag::If, if_token, condition, extra))
+    }
+
+    /// LabeledStatement <- BlockLabel? (Block / LoopStatement / SwitchExpr)
+    fn parse_labeled_statement(&mut self) -> Result<NodeIndex> {
+        let label_token = self.parse_block_label();
+        let block = self.parse_block()?;
+        if block != NULL_NODE {
+            return Ok(block);
+        }
+
+        let loop_stmt = self.parse_loop_statement()?;
+        if loop_stmt != NULL_NODE {
+            return Ok(loop_stmt);
+        }
+
+        let switch_expr = self.parse_switch_expr(label_token != 0)?;
+        if switch_expr != NULL_NODE {
+            return Ok(switch_expr);
+        }
+
+        if label_token != 0 {
+            let after_colon = self.tok_i;
+            let node = self.parse_type_expr()?;
+            if node != NULL_NODE {
+                let a = self.parse_byte_align()?;
+                let b = self.parse_addr_space()?;
+                let c = self.parse_link_section()?;
+                let d = match self.eat_token(Tag::Equal) {
+                    Some(_) => self.expect_expr()?,
+                    None => 0,
+                };
+                if a != 0 || b != 0 || c != 0 || d != 0 {
+                    return self.fail_msg(Self::msg(ErrorTag::ExpectedVarConst, label_token));
+                }
+            }
+            return self.fail_msg(Self::msg(ErrorTag::ExpectedLabelable, after_colon));
+        }
+
+        Ok(NULL_NODE)
+    }
+
+    /// LoopStatement <- KEYWORD_inline? (ForStatement / WhileStatement)
+    fn parse_loop_statement(&mut self) -> Result<NodeIndex> {
+        let inline_token = self.eat_token(Tag::KWInline);
+
+        let for_statement = self.parse_for_statement()?;
+        if for_statement != NULL_NODE {
+            return Ok(for_statement);
+        }
+
+        let while_statement = self.parse_while_statement()?;
+        if while_statement != NULL_NODE {
+            return Ok(while_statement);
+        }
+
+        if inline_token.is_none() {
+            return Ok(NULL_NODE);
+        }
+
+        // If we've seen "inline", there should have been a "for" or "while".
+        self.fail(ErrorTag::ExpectedInlinable)
+    }
+
+    /// ForStatement
+    ///     <- ForPrefix BlockExpr ( KEYWORD_else Statement )?
+    ///      / ForPrefix AssignExpr ( SEMICOLON / KEYWORD_else Statement )
+    fn parse_for_statement(&mut self) -> Result<NodeIndex> {
+        let Some(for_token) = self.eat_token(Tag::KWFor) else {
+            return Ok(NULL_NODE);
+        };
+
+        self.with_scratch(|p, top| {
+            let inputs = p.for_prefix()?;
+
+            let mut else_required = false;
+            let mut seen_semicolon = false;
+            let then_expr = {
+                let block_expr = p.parse_block_expr()?;
+                if block_expr != NULL_NODE {
+                    block_expr
+                } else {
+                    let assign_expr = p.parse_assign_expr()?;
+                    if assign_expr == NULL_NODE {
+                        return p.fail(ErrorTag::ExpectedBlockOrAssignment);
+                    }
+                    if p.eat_token(Tag::Semicolon).is_some() {
+                        seen_semicolon = true;
+                    } else {
+                        else_required = true;
+                    }
+                    assign_expr
+                }
+            };
+            let mut has_else = false;
+            if !seen_semicolon && p.eat_token(Tag::KWElse).is_some() {
+                p.scratch.push(then_expr);
+                let else_stmt = p.expect_statement(false)?;
+                p.scratch.push(else_stmt);
+                has_else = true;
+            } else if inputs == 1 {
+                if else_required {
+                    p.warn(ErrorTag::ExpectedSemiOrElse);
+                }
+                let input = p.scratch[top];
+                return Ok(p.add_node(NodeTag::ForSimple, for_token, input, then_expr));
+            } else {
+                if else_required {
+                    p.warn(ErrorTag::ExpectedSemiOrElse);
+                }
+                p.scratch.push(then_expr);
+            }
+            let span = p.scratch_to_span(top);
+            let packed = For { inputs, has_else }.to_u32();
+            Ok(p.add_node(NodeTag::For, for_token, span.start, packed))
+        })
+    }
+
+    /// WhilePrefix <- KEYWORD_while LPAREN Expr RPAREN PtrPayload? WhileContinueExpr?
+    ///
+    /// WhileStatement
+    ///     <- WhilePrefix BlockExpr ( KEYWORD_else Payload? Statement )?
+    ///      / WhilePrefix AssignExpr ( SEMICOLON / KEYWORD_else Payload? Statement )
+    fn parse_while_statement(&mut self) -> Result<NodeIndex> {
+        let Some(while_token) = self.eat_token(Tag::KWWhile) else {
+            return Ok(NULL_NODE);
+        };
+        self.expect_token(Tag::LParen)?;
+        let condition = self.expect_expr()?;
+        self.expect_token(Tag::RParen)?;
+        self.parse_ptr_payload()?;
+        let cont_expr = self.parse_while_continue_expr()?;
+
+        // TODO propose to change the syntax so that semicolons are always
+        // required inside while statements, even if there is an `else`.
+        let mut else_required = false;
+        let then_expr = {
+            let block_expr = self.parse_block_expr()?;
+            if block_expr != NULL_NODE {
+                block_expr
+            } else {
+                let assign_expr = self.parse_assign_expr()?;
+                if assign_expr == NULL_NODE {
+                    return self.fail(ErrorTag::ExpectedBlockOrAssignment);
+                }
+                if self.eat_token(Tag::Semicolon).is_some() {
+                    return Ok(self.finish_while_without_else(
+                        while_token,
+                        condition,
+                        cont_expr,
+                        assign_expr,
+                    ));
+                }
+                else_required = true;
+                assign_expr
+            }
+        };
+        if self.eat_token(Tag::KWElse).is_none() {
+            if else_required {
+                self.warn(ErrorTag::ExpectedSemiOrElse);
+            }
+            return Ok(self.finish_while_without_else(
+                while_token,
+                condition,
+                cont_expr,
+                then_expr,
+            ));
+        }
+        self.parse_payload()?;
+        let else_expr = self.expect_statement(false)?;
+        let extra = self.add_extra(While {
+            cont_expr,
+            then_expr,
+            else_expr,
+        });
+        Ok(self.add_node(NodeTag::While, while_token, condition, extra))
+    }
+
+    /// Adds a `while_simple` or `while_cont` node.
+    fn finish_while_without_else(
+        &mut self,
+        while_token: TokenIndex,
+        condition: NodeIndex,
+        cont_expr: NodeIndex,
+        then_expr: NodeIndex,
+    ) -> NodeIndex {
+        if cont_expr == NULL_NODE {
+            return self.add_node(NodeTag::WhileSimple, while_token, condition, then_expr);
+        }
+        let extra = self.add_extra(WhileCont {
+            cont_expr,
+            then_expr,
+        });
+        self.add_node(NodeTag::WhileCont, while_token, condition, extra)
+    }
+
+    /// BlockExprStatement
+    ///     <- BlockExpr
+    ///      / AssignExpr SEMICOLON
+    fn parse_block_expr_statement(&mut self) -> Result<NodeIndex> {
+        let block_expr = self.parse_block_expr()?;
+        if block_expr != NULL_NODE {
+            return Ok(block_expr);
+        }
+        let assign_expr = self.parse_assign_expr()?;
+        if assign_expr != NULL_NODE {
+            self.expect_semicolon(ErrorTag::ExpectedSemiAfterStmt, true)?;
+            return Ok(assign_expr);
+        }
+        Ok(NULL_NODE)
+    }
+
+    fn expect_block_expr_statement(&mut self) -> Result<NodeIndex> {
+        let node = self.parse_block_expr_statement()?;
+        if node == NULL_NODE {
+            return self.fail(ErrorTag::ExpectedBlockOrExpr);
+        }
+        Ok(node)
+    }
+
+    /// BlockExpr <- BlockLabel? Block
+    fn parse_block_expr(&mut self) -> Result<NodeIndex> {
+        match self.current() {
+            Tag::Identifier => {
+                if self.peek(1) == Tag::Colon && self.peek(2) == Tag::LBrace {
+                    self.tok_i += 2;
+                    self.parse_block()
+                } else {
+                    Ok(NULL_NODE)
+                }
+            }
+            Tag::LBrace => self.parse_block(),
+            _ => Ok(NULL_NODE),
+        }
+    }
+
+    /// AssignExpr <- Expr (AssignOp Expr / (COMMA Expr)+ EQUAL Expr)?
+    ///
+    /// AssignOp
+    ///     <- ASTERISKEQUAL
+    ///      / ASTERISKPIPEEQUAL
+    ///      / SLASHEQUAL
+    ///      / PERCENTEQUAL
+    ///      / PLUSEQUAL
+    ///      / PLUSPIPEEQUAL
+    ///      / MINUSEQUAL
+    ///      / MINUSPIPEEQUAL
+    ///      / LARROW2EQUAL
+    ///      / LARROW2PIPEEQUAL
+    ///      / RARROW2EQUAL
+    ///      / AMPERSANDEQUAL
+    ///      / CARETEQUAL
+    ///      / PIPEEQUAL
+    ///      / ASTERISKPERCENTEQUAL
+    ///      / PLUSPERCENTEQUAL
+    ///      / MINUSPERCENTEQUAL
+    ///      / EQUAL
+    fn parse_assign_expr(&mut self) -> Result<NodeIndex> {
+        let expr = self.parse_expr()?;
+        if expr == NULL_NODE {
+            return Ok(NULL_NODE);
+        }
+        self.finish_assign_expr(expr)
+    }
+
+    /// SingleAssignExpr <- Expr (AssignOp Expr)?
+    fn parse_single_assign_expr(&mut self) -> Result<NodeIndex> {
+        let lhs = self.parse_expr()?;
+        if lhs == NULL_NODE {
+            return Ok(NULL_NODE);
+        }
+        let Some(tag) = assign_op_node(self.current()) else {
+            return Ok(lhs);
+        };
+        let main_token = self.next_token();
+        let rhs = self.expect_expr()?;
+        Ok(self.add_node(tag, main_token, lhs, rhs))
+    }
+
+    fn finish_assign_expr(&mut self, lhs: NodeIndex) -> Result<NodeIndex> {
+        let tok = self.current();
+        if tok == Tag::Comma {
+            return self.finish_assign_destructure_expr(lhs);
+        }
+        let Some(tag) = assign_op_node(tok) else {
+            return Ok(lhs);
+        };
+        let main_token = self.next_token();
+        let rhs = self.expect_expr()?;
+        Ok(self.add_node(tag, main_token, lhs, rhs))
+    }
+
+    fn finish_assign_destructure_expr(&mut self, first_lhs: NodeIndex) -> Result<NodeIndex> {
+        self.with_scratch(|p, top| {
+            p.scratch.push(first_lhs);
+
+            while p.eat_token(Tag::Comma).is_some() {
+                let expr = p.expect_expr()?;
+                p.scratch.push(expr);
+            }
+
+            let equal_token = p.expect_token(Tag::Equal)?;
+
+            let rhs = p.expect_expr()?;
+
+            let lhs_count = p.scratch.len() - top;
+            // We already had first_lhs, and must have at least one more.
+            debug_assert!(lhs_count > 1);
+
+            let extra_start = p.extra_data.len() as u32;
+            p.extra_data.push(lhs_count as u32);
+            p.extra_data.extend_from_slice(&p.scratch[top..]);
+            Ok(p.add_node(NodeTag::AssignDestructure, equal_token, extra_start, rhs))
+        })
+    }
+
+    fn expect_single_assign_expr(&mut self) -> Result<NodeIndex> {
+        let expr = self.parse_single_assign_expr()?;
+        if expr == NULL_NODE {
+            return self.fail(ErrorTag::ExpectedExprOrAssignment);
+        }
+        Ok(expr)
+    }
+
+    fn expect_assign_expr(&mut self) -> Result<NodeIndex> {
+        let expr = self.parse_assign_expr()?;
+        if expr == NULL_NODE {
+            return self.fail(ErrorTag::ExpectedExprOrAssignment);
+        }
+        Ok(expr)
+    }
+
+    fn parse_expr(&mut self) -> Result<NodeIndex> {
+        self.parse_expr_precedence(0)
+    }
+
+    fn expect_expr(&mut self) -> Result<NodeIndex> {
+        let node = self.parse_expr()?;
+        if node == NULL_NODE {
+            return self.fail(ErrorTag::ExpectedExpr);
+        }
+        Ok(node)
+    }
+
+    /// Precedence climbing over the binary operators, with binding powers
+    /// from [`Tag::binary_precedence`].
+    fn parse_expr_precedence(&mut self, min_prec: u8) -> Result<NodeIndex> {
+        let mut node = self.parse_prefix_expr()?;
+        if node == NULL_NODE {
+            return Ok(NULL_NODE);
+        }
+
+        let mut banned_prec = None;
+
+        loop {
+            let tok_tag = self.current();
+            let Some(prec) = tok_tag.binary_precedence().filter(|&prec| prec >= min_prec) else {
+                break;
+            };
+            if banned_prec == Some(prec) {
+                return self.fail(ErrorTag::ChainedComparisonOperators);
+            }
+
+            let oper_token = self.next_token();
+            // Special-case handling for "catch".
+            if tok_tag == Tag::KWCatch {
+                self.parse_payload()?;
+            }
+            let rhs = self.parse_expr_precedence(prec + 1)?;
+            if rhs == NULL_NODE {
+                self.warn(ErrorTag::ExpectedExpr);
+                return Ok(node);
+            }
+
+            {
+                let start = self.token_starts[oper_token as usize] as usize;
+                let tok_len = tok_tag.lexeme().map_or(0, str::len);
+                let char_before = start.checked_sub(1).map_or(0, |i| self.source[i]);
+                let char_after = self.source.get(start + tok_len).copied().unwrap_or(0);
+                if tok_tag == Tag::Ampersand && char_after == b'&' {
+                    // Without types we don't know if '&&' was intended as
+                    // 'bitwise_and address_of' or a C-style logical and.
+                    // The best the parser can do is recommend changing it
+                    // to 'and' or ' & &'.
+                    self.warn_msg(Self::msg(ErrorTag::InvalidAmpersandAmpersand, oper_token));
+                } else if is_whitespace(char_before) != is_whitespace(char_after) {
+                    self.warn_msg(Self::msg(
+                        ErrorTag::MismatchedBinaryOpWhitespace,
+                        oper_token,
+                    ));
+                }
+            }
+
+            node = self.add_node(binary_op_node(tok_tag), oper_token, node, rhs);
+
+            // Comparison operators do not chain.
+            if prec == 30 {
+                banned_prec = Some(prec);
+            }
+        }
+
+        Ok(node)
+    }
+
+    /// PrefixExpr <- PrefixOp* PrimaryExpr
+    ///
+    /// PrefixOp
+    ///     <- EXCLAMATIONMARK
+    ///      / MINUS
+    ///      / TILDE
+    ///      / MINUSPERCENT
+    ///      / AMPERSAND
+    ///      / KEYWORD_try
+    ///      / KEYWORD_await
+    fn parse_prefix_expr(&mut self) -> Result<NodeIndex> {
+        let tag = match self.current() {
+            Tag::Bang => NodeTag::BoolNot,
+            Tag::Minus => NodeTag::Negation,
+            Tag::Tilde => NodeTag::BitNot,
+            Tag::MinusPercent => NodeTag::NegationWrap,
+            Tag::Ampersand => NodeTag::AddressOf,
+            Tag::KWTry => NodeTag::Try,
+            Tag::KWAwait => NodeTag::Await,
+            _ => return self.parse_primary_expr(),
+        };
+        let main_token = self.next_token();
+        let operand = self.expect_prefix_expr()?;
+        Ok(self.add_node(tag, main_token, operand, 0))
+    }
+
+    fn expect_prefix_expr(&mut self) -> Result<NodeIndex> {
+        let node = self.parse_prefix_expr()?;
+        if node == NULL_NODE {
+            return self.fail(ErrorTag::ExpectedPrefixExpr);
+        }
+        Ok(node)
+    }
+
+    /// TypeExpr <- PrefixTypeOp* ErrorUnionExpr
+    ///
+    /// PrefixTypeOp
+    ///     <- QUESTIONMARK
+    ///      / KEYWORD_anyframe MINUSRARROW
+    ///      / SliceTypeStart (ByteAlign / AddrSpace / KEYWORD_const / KEYWORD_volatile / KEYWORD_allowzero)*
+    ///      / PtrTypeStart (AddrSpace / KEYWORD_align LPAREN Expr (COLON Expr COLON Expr)? RPAREN / KEYWORD_const / KEYWORD_volatile / KEYWORD_allowzero)*
+    ///      / ArrayTypeStart
+    ///
+    /// SliceTypeStart <- LBRACKET (COLON Expr)? RBRACKET
+    ///
+    /// PtrTypeStart
+    ///     <- ASTERISK
+    ///      / ASTERISK2
+    ///      / LBRACKET ASTERISK (LETTERC / COLON Expr)? RBRACKET
+    ///
+    /// ArrayTypeStart <- LBRACKET Expr (COLON Expr)? RBRACKET
+    fn parse_type_expr(&mut self) -> Result<NodeIndex> {
+        match self.current() {
+            Tag::QuestionMark => {
+                let main_token = self.next_token();
+                let child = self.expect_type_expr()?;
+                Ok(self.add_node(NodeTag::OptionalType, main_token, child, 0))
+            }
+            Tag::KWAnyframe if self.peek(1) == Tag::Arrow => {
+                let main_token = self.next_token();
+                let arrow = self.next_token();
+                let child = self.expect_type_expr()?;
+                Ok(self.add_node(NodeTag::AnyframeType, main_token, arrow, child))
+            }
+            Tag::Asterisk => {
+                let asterisk = self.next_token();
+                let mods = self.parse_ptr_modifiers()?;
+                let elem_type = self.expect_type_expr()?;
+                Ok(self.add_ptr_type(asterisk, 0, &mods, elem_type))
+            }
+            Tag::AsteriskAsterisk => {
+                let asterisk = self.next_token();
+                let mods = self.parse_ptr_modifiers()?;
+                let elem_type = self.expect_type_expr()?;
+                let inner = self.add_ptr_type(asterisk, 0, &mods, elem_type);
+                Ok(self.add_node(NodeTag::PtrTypeAligned, asterisk, 0, inner))
+            }
+            Tag::LBrack if self.peek(1) == Tag::Asterisk => {
+                self.next_token();
+                let asterisk = self.next_token();
+                let mut sentinel = 0;
+                if let Some(ident) = self.eat_token(Tag::Identifier) {
+                    let start = self.token_starts[ident as usize] as usize;
+                    let end = self.token_starts[ident as usize + 1] as usize;
+                    let mut ident_slice = &self.source[start..end];
+                    while let [rest @ .., last] = ident_slice {
+                        if !is_whitespace(*last) {
+                            break;
+                        }
+                        ident_slice = rest;
+                    }
+                    if ident_slice != b"c" {
+                        self.tok_i -= 1;
+                    }
+                } else if self.eat_token(Tag::Colon).is_some() {
+                    sentinel = self.expect_expr()?;
+                }
+                self.expect_token(Tag::RBrack)?;
+                let mods = self.parse_ptr_modifiers()?;
+                let elem_type = self.expect_type_expr()?;
+                Ok(self.add_ptr_type(asterisk, sentinel, &mods, elem_type))
+            }
+            Tag::LBrack => {
+                let lbracket = self.next_token();
+                let len_expr = self.parse_expr()?;
+                let sentinel = match self.eat_token(Tag::Colon) {
+                    Some(_) => self.expect_expr()?,
+                    None => 0,
+                };
+                self.expect_token(Tag::RBrack)?;
+                if len_expr == NULL_NODE {
+                    let mods = self.parse_ptr_modifiers()?;
+                    let elem_type = self.expect_type_expr()?;
+                    if mods.bit_range_start != 0 {
+                        let token = self.nodes.main_tokens()[mods.bit_range_start as usize];
+                        self.warn_msg(Self::msg(ErrorTag::InvalidBitRange, token));
+                    }
+                    let mods = PtrModifiers {
+                        bit_range_start: 0,
+                        bit_range_end: 0,
+                        ..mods
+                    };
+                    return Ok(self.add_ptr_type(lbracket, sentinel, &mods, elem_type));
+                }
+                if matches!(
+                    self.current(),
+                    Tag::KWAlign
+                        | Tag::KWConst
+                        | Tag::KWVolatile
+                        | Tag::KWAllowzero
+                        | Tag::KWAddrspace
+                ) {
+                    return self.fail(ErrorTag::PtrModOnArrayChildType);
+                }
+                let elem_type = self.expect_type_expr()?;
+                if sentinel == NULL_NODE {
+                    return Ok(self.add_node(NodeTag::ArrayType, lbracket, len_expr, elem_type));
+                }
+                let extra = self.add_extra(ArrayTypeSentinel {
+                    sentinel,
+                    elem_type,
+                });
+                Ok(self.add_node(NodeTag::ArrayTypeSentinel, lbracket, len_expr, extra))
+            }
+            _ => self.parse_error_union_expr(),
+        }
+    }
+
+    /// Adds the most compact pointer type node that can hold `sentinel`
+    /// and `mods`.
+    fn add_ptr_type(
+        &mut self,
+        main_token: TokenIndex,
+        sentinel: NodeIndex,
+        mods: &PtrModifiers,
+        elem_type: NodeIndex,
+    ) -> NodeIndex {
+        if mods.bit_range_start != 0 {
+            let extra = self.add_extra(PtrTypeBitRange {
+                sentinel,
+                align_node: mods.align_node,
+                addrspace_node: mods.addrspace_node,
+                bit_range_start: mods.bit_range_start,
+                bit_range_end: mods.bit_range_end,
+            });
+            self.add_node(NodeTag::PtrTypeBitRange, main_token, extra, elem_type)
+        } else if sentinel == 0 && mods.addrspace_node == 0 {
+            self.add_node(
+                NodeTag::PtrTypeAligned,
+                main_token,
+                mods.align_node,
+                elem_type,
+            )
+        } else if mods.align_node == 0 && mods.addrspace_node == 0 {
+            self.add_node(NodeTag::PtrTypeSentinel, main_token, sentinel, elem_type)
+        } else {
+            let extra = self.add_extra(PtrType {
+                sentinel,
+                align_node: mods.align_node,
+                addrspace_node: mods.addrspace_node,
+            });
+            self.add_node(NodeTag::PtrType, main_token, extra, elem_type)
+        }
+    }
+
+    fn expect_type_expr(&mut self) -> Result<NodeIndex> {
+        let node = self.parse_type_expr()?;
+        if node == NULL_NODE {
+            return self.fail(ErrorTag::ExpectedTypeExpr);
+        }
+        Ok(node)
+    }
+
+    /// PrimaryExpr
+    ///     <- AsmExpr
+    ///      / IfExpr
+    ///      / KEYWORD_break BreakLabel? Expr?
+    ///      / KEYWORD_comptime Expr
+    ///      / KEYWORD_nosuspend Expr
+    ///      / KEYWORD_continue BreakLabel? Expr?
+    ///      / KEYWORD_resume Expr
+    ///      / KEYWORD_return Expr?
+    ///      / BlockLabel? LoopExpr
+    ///      / Block
+    ///      / CurlySuffixExpr
+    fn parse_primary_expr(&mut self) -> Result<NodeIndex> {
+        match self.current() {
+            Tag::KWAsm => self.expect_asm_expr(),
+            Tag::KWIf => self.parse_if(Body::Expr),
+            Tag::KWBreak | Tag::KWContinue => {
+                let tag = match self.current() {
+                    Tag::KWBreak => NodeTag::Break,
+                    _ => NodeTag::Continue,
+                };
+                let main_token = self.next_token();
+                let label = self.parse_break_label()?;
+                let operand = self.parse_expr()?;
+                Ok(self.add_node(tag, main_token, label, operand))
+            }
+            Tag::KWComptime | Tag::KWNosuspend | Tag::KWResume => {
+                let tag = match self.current() {
+                    Tag::KWComptime => NodeTag::Comptime,
+                    Tag::KWNosuspend => NodeTag::Nosuspend,
+                    _ => NodeTag::Resume,
+                };
+                let main_token = self.next_token();
+                let operand = self.expect_expr()?;
+                Ok(self.add_node(tag, main_token, operand, 0))
+            }
+            Tag::KWReturn => {
+                let main_token = self.next_token();
+                let operand = self.parse_expr()?;
+                Ok(self.add_node(NodeTag::Return, main_token, operand, 0))
+            }
+            Tag::Identifier if self.peek(1) == Tag::Colon => match self.peek(2) {
+                Tag::KWInline => {
+                    self.tok_i += 3;
+                    self.expect_inlinable(Body::Expr)
+                }
+                Tag::KWFor | Tag::KWWhile => {
+                    self.tok_i += 2;
+                    self.expect_inlinable(Body::Expr)
+                }
+                Tag::LBrace => {
+                    self.tok_i += 2;
+                    self.parse_block()
+                }
+                Tag::KWSwitch => {
+                    self.tok_i += 2;
+                    self.expect_switch_expr(true)
+                }
+                _ => self.parse_curly_suffix_expr(),
+            },
+            Tag::KWInline => {
+                self.tok_i += 1;
+                self.expect_inlinable(Body::Expr)
+            }
+            Tag::KWFor | Tag::KWWhile => self.expect_inlinable(Body::Expr),
+            Tag::LBrace => self.parse_block(),
+            _ => self.parse_curly_suffix_expr(),
+        }
+    }
+
+    /// Parses the `for` or `while` loop at the current token, which must
+    /// be one of them after an `inline`.
+    fn expect_inlinable(&mut self, body: Body) -> Result<NodeIndex> {
+        match (self.current(), body) {
+            (Tag::KWFor, _) => self.parse_for(body),
+            (Tag::KWWhile, Body::Expr) => self.parse_while_expr(),
+            (Tag::KWWhile, Body::TypeExpr) => self.parse_while_type_expr(),
+            _ => self.fail(ErrorTag::ExpectedInlinable),
+        }
+    }
+
+    fn parse_body(&mut self, body: Body) -> Result<NodeIndex> {
+        match body {
+            Body::Expr => self.expect_expr(),
+            Body::TypeExpr => self.expect_type_expr(),
+        }
+    }
+
+    /// IfExpr <- IfPrefix Expr (KEYWORD_else Payload? Expr)?
+    ///
+    /// IfTypeExpr <- IfPrefix TypeExpr (KEYWORD_else Payload? TypeExpr)?
+    fn parse_if(&mut self, body: Body) -> Result<NodeIndex> {
+        let Some(if_token) = self.eat_token(Tag::KWIf) else {
+            return Ok(NULL_NODE);
+        };
+        self.expect_token(Tag::LParen)?;
+        let condition = self.expect_expr()?;
+        self.expect_token(Tag::RParen)?;
+        self.parse_ptr_payload()?;
+
+        let then_expr = self.parse_body(body)?;
+
+        if self.eat_token(Tag::KWElse).is_none() {
+            return Ok(self.add_node(NodeTag::IfSimple, if_token, condition, then_expr));
+        }
+        self.parse_payload()?;
+        let else_expr = self.parse_body(body)?;
+
+        let extra = self.add_extra(If {
+            then_expr,
+            else_expr,
+        });
+        Ok(self.add_node(NodeTag::If, if_token, condition, extra))
+    }
+
+    /// ForExpr <- ForPrefix Expr (KEYWORD_else Expr)?
+    ///
+    /// ForTypeExpr <- ForPrefix TypeExpr (KEYWORD_else TypeExpr)?
+    fn parse_for(&mut self, body: Body) -> Result<NodeIndex> {
+        let Some(for_token) = self.eat_token(Tag::KWFor) else {
+            return Ok(NULL_NODE);
+        };
+
+        self.with_scratch(|p, top| {
+            let inputs = p.for_prefix()?;
+
+            let then_expr = p.parse_body(body)?;
+            let mut has_else = false;
+            if p.eat_token(Tag::KWElse).is_some() {
+                p.scratch.push(then_expr);
+                let else_expr = p.parse_body(body)?;
+                p.scratch.push(else_expr);
+                has_else = true;
+            } else if inputs == 1 {
+                let input = p.scratch[top];
+                return Ok(p.add_node(NodeTag::ForSimple, for_token, input, then_expr));
+            } else {
+                p.scratch.push(then_expr);
+            }
+            let span = p.scratch_to_span(top);
+            let packed = For { inputs, has_else }.to_u32();
+            Ok(p.add_node(NodeTag::For, for_token, span.start, packed))
+        })
+    }
+
+    /// ForPrefix <- KEYWORD_for LPAREN ForInput (COMMA ForInput)* COMMA? RPAREN ForPayload
+    ///
+    /// ForInput <- Expr (DOT2 Expr?)?
+    ///
+    /// ForPayload <- PIPE ASTERISK? IDENTIFIER (COMMA ASTERISK? IDENTIFIER)* PIPE
+    ///
+    /// Pushes the inputs onto the scratch and returns how many there are.
+    fn for_prefix(&mut self) -> Result<u32> {
+        let start = self.scratch.len();
+        self.expect_token(Tag::LParen)?;
+
+        loop {
+            let mut input = self.expect_expr()?;
+            if let Some(ellipsis) = self.eat_token(Tag::Ellipsis2) {
+                let end = self.parse_expr()?;
+                input = self.add_node(NodeTag::ForRange, ellipsis, input, end);
+            }
+
+            self.scratch.push(input);
+            match self.current() {
+                Tag::Comma => self.tok_i += 1,
+                Tag::RParen => {
+                    self.tok_i += 1;
+                    break;
+                }
+                Tag::Colon | Tag::RBrace | Tag::RBrack => {
+                    return self.fail_expected(Tag::RParen);
+                }
+                // Likely just a missing comma; give error but continue parsing.
+                _ => self.warn(ErrorTag::ExpectedCommaAfterForOperand),
+            }
+            if self.eat_token(Tag::RParen).is_some() {
+                break;
+            }
+        }
+        let inputs = (self.scratch.len() - start) as u32;
+
+        if self.eat_token(Tag::Pipe).is_none() {
+            self.warn(ErrorTag::ExpectedLoopPayload);
+            return Ok(inputs);
+        }
+
+        let mut warned_excess = false;
+        let mut captures = 0;
+        loop {
+            self.eat_token(Tag::Asterisk);
+            let identifier = self.expect_token(Tag::Identifier)?;
+            captures += 1;
+            if captures > inputs && !warned_excess {
+                self.warn_msg(Self::msg(ErrorTag::ExtraForCapture, identifier));
+                warned_excess = true;
+            }
+            match self.current() {
+                Tag::Comma => self.tok_i += 1,
+                Tag::Pipe => {
+                    self.tok_i += 1;
+                    break;
+                }
+                _ => self.warn(ErrorTag::ExpectedCommaAfterCapture),
+            }
+            if self.eat_token(Tag::Pipe).is_some() {
+                break;
+            }
+        }
+
+        if captures < inputs {
+            let index = self.scratch.len() - captures as usize;
+            let input = self.nodes.main_tokens()[self.scratch[index] as usize];
+            self.warn_msg(Self::msg(ErrorTag::ForInputNotCaptured, input));
+        }
+        Ok(inputs)
+    }
+
+    /// WhileExpr <- WhilePrefix Expr (KEYWORD_else Payload? Expr)?
+    fn parse_while_expr(&mut self) -> Result<NodeIndex> {
+        self.parse_while(Body::Expr)
+    }
+
+    /// WhileTypeExpr <- WhilePrefix TypeExpr (KEYWORD_else Payload? TypeExpr)?
+    fn parse_while_type_expr(&mut self) -> Result<NodeIndex> {
+        self.parse_while(Body::TypeExpr)
+    }
+
+    fn parse_while(&mut self, body: Body) -> Result<NodeIndex> {
+        let Some(while_token) = self.eat_token(Tag::KWWhile) else {
+            return Ok(NULL_NODE);
+        };
+        self.expect_token(Tag::LParen)?;
+        let condition = self.expect_expr()?;
+        self.expect_token(Tag::RParen)?;
+        self.parse_ptr_payload()?;
+        let cont_expr = self.parse_while_continue_expr()?;
+
+        let then_expr = self.parse_body(body)?;
+        if self.eat_token(Tag::KWElse).is_none() {
+            return Ok(self.finish_while_without_else(
+                while_token,
+                condition,
+                cont_expr,
+                then_expr,
+            ));
+        }
+        self.parse_payload()?;
+        let else_expr = self.parse_body(body)?;
+        let extra = self.add_extra(While {
+            cont_expr,
+            then_expr,
+            else_expr,
+        });
+        Ok(self.add_node(NodeTag::While, while_token, condition, extra))
+    }
+
+    /// CurlySuffixExpr <- TypeExpr InitList?
+    ///
+    /// InitList
+    ///     <- LBRACE FieldInit (COMMA FieldInit)* COMMA? RBRACE
+    ///      / LBRACE Expr (COMMA Expr)* COMMA? RBRACE
+    ///      / LBRACE RBRACE
+    fn parse_curly_suffix_expr(&mut self) -> Result<NodeIndex> {
+        let lhs = self.parse_type_expr()?;
+        if lhs == NULL_NODE {
+            return Ok(NULL_NODE);
+        }
+        let Some(lbrace) = self.eat_token(Tag::LBrace) else {
+            return Ok(lhs);
+        };
+
+        // If there are 0 or 1 items, we can use ArrayInitOne/StructInitOne;
+        // otherwise we use the full ArrayInit/StructInit.
+        self.with_scratch(|p, top| {
+            let is_struct = p.parse_init_list(top)?;
+            let comma = p.tag_at(p.tok_i - 2) == Tag::Comma;
+            let inits = &p.scratch[top..];
+            Ok(match (is_struct, inits.len()) {
+                (_, 0) => p.add_node(NodeTag::StructInitOne, lbrace, lhs, 0),
+                (true, 1) => {
+                    let tag = if comma {
+                        NodeTag::StructInitOneComma
+                    } else {
+                        NodeTag::StructInitOne
+                    };
+                    let init = inits[0];
+                    p.add_node(tag, lbrace, lhs, init)
+                }
+                (false, 1) => {
+                    let tag = if comma {
+                        NodeTag::ArrayInitOneComma
+                    } else {
+                        NodeTag::ArrayInitOne
+                    };
+                    let init = inits[0];
+                    p.add_node(tag, lbrace, lhs, init)
+                }
+                (true, _) => {
+                    let tag = if comma {
+                        NodeTag::StructInitComma
+                    } else {
+                        NodeTag::StructInit
+                    };
+                    let span = p.scratch_to_span(top);
+                    let extra = p.add_extra(span);
+                    p.add_node(tag, lbrace, lhs, extra)
+                }
+                (false, _) => {
+                    let tag = if comma {
+                        NodeTag::ArrayInitComma
+                    } else {
+                        NodeTag::ArrayInit
+                    };
+                    let span = p.scratch_to_span(top);
+                    let extra = p.add_extra(span);
+                    p.add_node(tag, lbrace, lhs, extra)
+                }
+            })
+        })
+    }
+
+    /// Parses the contents of an initializer list after its `{` through
+    /// the closing `}`, pushing the initializers onto the scratch. Returns
+    /// whether they are field initializers.
+    fn parse_init_list(&mut self, top: usize) -> Result<bool> {
+        let field_init = self.parse_field_init()?;
+        if field_init != NULL_NODE {
+            self.scratch.push(field_init);
+            loop {
+                match self.current() {
+                    Tag::Comma => self.tok_i += 1,
+                    Tag::RBrace => {
+                        self.tok_i += 1;
+                        break;
+                    }
+                    Tag::Colon | Tag::RParen | Tag::RBrack => {
+                        return self.fail_expected(Tag::RBrace);
+                    }
+                    // Likely just a missing comma; give error but continue parsing.
+                    _ => self.warn(ErrorTag::ExpectedCommaAfterInitializer),
+                }
+                if self.eat_token(Tag::RBrace).is_some() {
+                    break;
+                }
+                let next = self.expect_field_init()?;
+                self.scratch.push(next);
+            }
+            return Ok(true);
+        }
+
+        loop {
+            if self.eat_token(Tag::RBrace).is_some() {
+                break;
+            }
+            let elem_init = self.expect_expr()?;
+            self.scratch.push(elem_init);
+            match self.current() {
+                Tag::Comma => self.tok_i += 1,
+                Tag::RBrace => {
+                    self.tok_i += 1;
+                    break;
+                }
+                Tag::Colon | Tag::RParen | Tag::RBrack => {
+                    return self.fail_expected(Tag::RBrace);
+                }
+                // Likely just a missing comma; give error but continue parsing.
+                _ => self.warn(ErrorTag::ExpectedCommaAfterInitializer),
+            }
+        }
+        debug_assert!(self.scratch.len() >= top);
+        Ok(false)
+    }
+
+    /// ErrorUnionExpr <- SuffixExpr (EXCLAMATIONMARK TypeExpr)?
+    fn parse_error_union_expr(&mut self) -> Result<NodeIndex> {
+        let suffix_expr = self.parse_suffix_expr()?;
+        if suffix_expr == NULL_NODE {
+            return Ok(NULL_NODE);
+        }
+        let Some(bang) = self.eat_token(Tag::Bang) else {
+            return Ok(suffix_expr);
+        };
+        let rhs = self.expect_type_expr()?;
+        Ok(self.add_node(NodeTag::ErrorUnion, bang, suffix_expr, rhs))
+    }
+
+    /// SuffixExpr
+    ///     <- KEYWORD_async PrimaryTypeExpr SuffixOp* FnCallArguments
+    ///      / PrimaryTypeExpr (SuffixOp / FnCallArguments)*
+    ///
+    /// FnCallArguments <- LPAREN ExprList RPAREN
+    ///
+    /// ExprList <- (Expr COMMA)* Expr?
+    fn parse_suffix_expr(&mut self) -> Result<NodeIndex> {
+        if self.eat_token(Tag::KWAsync).is_some() {
+            let mut res = self.expect_primary_type_expr()?;
+            loop {
+                let node = self.parse_suffix_op(res)?;
+                if node == NULL_NODE {
+                    break;
+                }
+                res = node;
+            }
+            let Some(lparen) = self.eat_token(Tag::LParen) else {
+                self.warn(ErrorTag::ExpectedParamList);
+                return Ok(res);
+            };
+            return self.parse_call_arguments(res, lparen, true);
+        }
+
+        let mut res = self.parse_primary_type_expr()?;
+        if res == NULL_NODE {
+            return Ok(res);
+        }
+        loop {
+            let suffix_op = self.parse_suffix_op(res)?;
+            if suffix_op != NULL_NODE {
+                res = suffix_op;
+                continue;
+            }
+            let Some(lparen) = self.eat_token(Tag::LParen) else {
+                return Ok(res);
+            };
+            res = self.parse_call_arguments(res, lparen, false)?;
+        }
+    }
+
+    /// Parses the arguments of a call on `callee` after its `(`.
+    fn parse_call_arguments(
+        &mut self,
+        callee: NodeIndex,
+        lparen: TokenIndex,
+        is_async: bool,
+    ) -> Result<NodeIndex> {
+        self.with_scratch(|p, top| {
+            loop {
+                if p.eat_token(Tag::RParen).is_some() {
+                    break;
+                }
+                let param = p.expect_expr()?;
+                p.scratch.push(param);
+                match p.current() {
+                    Tag::Comma => p.tok_i += 1,
+                    Tag::RParen => {
+                        p.tok_i += 1;
+                        break;
+                    }
+                    Tag::Colon | Tag::RBrace | Tag::RBrack => {
+                        return p.fail_expected(Tag::RParen);
+                    }
+                    // Likely just a missing comma; give error but continue parsing.
+                    _ => p.warn(ErrorTag::ExpectedCommaAfterArg),
+                }
+            }
+            let comma = p.tag_at(p.tok_i - 2) == Tag::Comma;
+            let params = &p.scratch[top..];
+            let (one, one_comma, multi, multi_comma) = if is_async {
+                (
+                    NodeTag::AsyncCallOne,
+                    NodeTag::AsyncCallOneComma,
+                    NodeTag::AsyncCall,
+                    NodeTag::AsyncCallComma,
+                )
+            } else {
+                (
+                    NodeTag::CallOne,
+                    NodeTag::CallOneComma,
+                    NodeTag::Call,
+                    NodeTag::CallComma,
+                )
+            };
+            Ok(match *params {
+                [] => p.add_node(one, lparen, callee, 0),
+                [param] => {
+                    let tag = if comma { one_comma } else { one };
+                    p.add_node(tag, lparen, callee, param)
+                }
+                _ => {
+                    let tag = if comma { multi_comma } else { multi };
+                    let span = p.scratch_to_span(top);
+                    let extra = p.add_extra(span);
+                    p.add_node(tag, lparen, callee, extra)
+                }
+            })
+        })
+    }
+
+    /// PrimaryTypeExpr
+    ///     <- BUILTINIDENTIFIER FnCallArguments
+    ///      / CHAR_LITERAL
+    ///      / ContainerDecl
+    ///      / DOT IDENTIFIER
+    ///      / DOT InitList
+    ///      / ErrorSetDecl
+    ///      / FLOAT
+    ///      / FnProto
+    ///      / GroupedExpr
+    ///      / LabeledTypeExpr
+    ///      / IDENTIFIER
+    ///      / IfTypeExpr
+    ///      / INTEGER
+    ///      / KEYWORD_comptime TypeExpr
+    ///      / KEYWORD_error DOT IDENTIFIER
+    ///      / KEYWORD_anyframe
+    ///      / KEYWORD_unreachable
+    ///      / STRINGLITERAL
+    ///
+    /// ContainerDecl <- (KEYWORD_extern / KEYWORD_packed)? ContainerDeclAuto
+    ///
+    /// ErrorSetDecl <- KEYWORD_error LBRACE IdentifierList RBRACE
+    ///
+    /// GroupedExpr <- LPAREN Expr RPAREN
+    ///
+    /// LabeledTypeExpr
+    ///     <- BlockLabel Block
+    ///      / BlockLabel? LoopTypeExpr
+    ///      / BlockLabel? SwitchExpr
+    ///
+    /// LoopTypeExpr <- KEYWORD_inline? (ForTypeExpr / WhileTypeExpr)
+    fn parse_primary_type_expr(&mut self) -> Result<NodeIndex> {
+        let literal = match self.current() {
+            Tag::CharLiteral => Some(NodeTag::CharLiteral),
+            Tag::NumberLiteral => Some(NodeTag::NumberLiteral),
+            Tag::KWUnreachable => Some(NodeTag::UnreachableLiteral),
+            Tag::KWAnyframe => Some(NodeTag::AnyframeLiteral),
+            Tag::StringLiteral => Some(NodeTag::StringLiteral),
+            _ => None,
+        };
+        if let Some(tag) = literal {
+            let main_token = self.next_token();
+            return Ok(self.add_node(tag, main_token, 0, 0));
+        }
+        match self.current() {
+            Tag::Builtin => self.parse_builtin_call(),
+            Tag::KWFn => self.parse_fn_proto(),
+            Tag::KWIf => self.parse_if(Body::TypeExpr),
+            Tag::KWSwitch => self.expect_switch_expr(false),
+
+            Tag::KWExtern | Tag::KWPacked => {
+                self.tok_i += 1;
+                self.parse_container_decl_auto()
+            }
+
+            Tag::KWStruct | Tag::KWOpaque | Tag::KWEnum | Tag::KWUnion => {
+                self.parse_container_decl_auto()
+            }
+
+            Tag::KWComptime => {
+                let main_token = self.next_token();
+                let operand = self.expect_type_expr()?;
+                Ok(self.add_node(NodeTag::Comptime, main_token, operand, 0))
+            }
+            Tag::MultilineStringLiteralLine => {
+                let first_line = self.next_token();
+                while self.current() == Tag::MultilineStringLiteralLine {
+                    self.tok_i += 1;
+                }
+                let last_line = self.tok_i - 1;
+                Ok(self.add_node(
+                    NodeTag::MultilineStringLiteral,
+                    first_line,
+                    first_line,
+                    last_line,
+                ))
+            }
+            Tag::Identifier if self.peek(1) == Tag::Colon => match self.peek(2) {
+                Tag::KWInline => {
+                    self.tok_i += 3;
+                    self.expect_inlinable(Body::TypeExpr)
+                }
+                Tag::KWFor | Tag::KWWhile => {
+                    self.tok_i += 2;
+                    self.expect_inlinable(Body::TypeExpr)
+                }
+                Tag::KWSwitch => {
+                    self.tok_i += 2;
+                    self.expect_switch_expr(true)
+                }
+                Tag::LBrace => {
+                    self.tok_i += 2;
+                    self.parse_block()
+                }
+                _ => {
+                    let main_token = self.next_token();
+                    Ok(self.add_node(NodeTag::Identifier, main_token, 0, 0))
+                }
+            },
+            Tag::Identifier => {
+                let main_token = self.next_token();
+                Ok(self.add_node(NodeTag::Identifier, main_token, 0, 0))
+            }
+            Tag::KWInline => {
+                self.tok_i += 1;
+                self.expect_inlinable(Body::TypeExpr)
+            }
+            Tag::KWFor | Tag::KWWhile => self.expect_inlinable(Body::TypeExpr),
+            Tag::Period => match self.peek(1) {
+                Tag::Identifier => {
+                    // An enum literal, or the start of a decl literal such
+                    // as `.init(x)` once a call suffix follows.
+                    self.tok_i += 1;
+                    let main_token = self.next_token();
+                    Ok(self.add_node(NodeTag::EnumLiteral, main_token, 0, 0))
+                }
+                Tag::LBrace => {
+                    let lbrace = self.tok_i + 1;
+                    self.tok_i = lbrace + 1;
+                    self.parse_anon_init_list(lbrace)
+                }
+                _ => Ok(NULL_NODE),
+            },
+            Tag::KWError => match self.peek(1) {
+                Tag::LBrace => {
+                    let error_token = self.tok_i;
+                    self.tok_i += 2;
+                    loop {
+                        if self.eat_token(Tag::RBrace).is_some() {
+                            break;
+                        }
+                        self.eat_doc_comments();
+                        self.expect_token(Tag::Identifier)?;
+                        match self.current() {
+                            Tag::Comma => self.tok_i += 1,
+                            Tag::RBrace => {
+                                self.tok_i += 1;
+                                break;
+                            }
+                            Tag::Colon | Tag::RParen | Tag::RBrack => {
+                                return self.fail_expected(Tag::RBrace);
+                            }
+                            // Likely just a missing comma; give error but
+                            // continue parsing.
+                            _ => self.warn(ErrorTag::ExpectedCommaAfterField),
+                        }
+                    }
+                    let rbrace = self.tok_i - 1;
+                    Ok(self.add_node(NodeTag::ErrorSetDecl, error_token, 0, rbrace))
+                }
+                _ => {
+                    let main_token = self.next_token();
+                    let period = self.eat_token(Tag::Period);
+                    if period.is_none() {
+                        self.warn_expected(Tag::Period);
+                    }
+                    let identifier = self.eat_token(Tag::Identifier);
+                    if identifier.is_none() {
+                        self.warn_expected(Tag::Identifier);
+                    }
+                    Ok(self.add_node(
+                        NodeTag::ErrorValue,
+                        main_token,
+                        period.unwrap_or(0),
+                        identifier.unwrap_or(0),
+                    ))
+                }
+            },
+            Tag::LParen => {
+                let lparen = self.next_token();
+                let expr = self.expect_expr()?;
+                let rparen = self.expect_token(Tag::RParen)?;
+                Ok(self.add_node(NodeTag::GroupedExpression, lparen, expr, rparen))
+            }
+            _ => Ok(NULL_NODE),
+        }
+    }
+
+    /// Parses `.{...}` after its `{`. If there are 0, 1, or 2 items we can
+    /// use ArrayInitDotTwo/StructInitDotTwo; otherwise we use the full
+    /// ArrayInitDot/StructInitDot.
+    fn parse_anon_init_list(&mut self, lbrace: TokenIndex) -> Result<NodeIndex> {
+        self.with_scratch(|p, top| {
+            let is_struct = p.parse_init_list(top)?;
+            let comma = p.tag_at(p.tok_i - 2) == Tag::Comma;
+            let (two, two_comma, many, many_comma) = if is_struct {
+                (
+                    NodeTag::StructInitDotTwo,
+                    NodeTag::StructInitDotTwoComma,
+                    NodeTag::StructInitDot,
+                    NodeTag::StructInitDotComma,
+                )
+            } else {
+                (
+                    NodeTag::ArrayInitDotTwo,
+                    NodeTag::ArrayInitDotTwoComma,
+                    NodeTag::ArrayInitDot,
+                    NodeTag::ArrayInitDotComma,
+                )
+            };
+            let inits = &p.scratch[top..];
+            Ok(match *inits {
+                [] => p.add_node(NodeTag::StructInitDotTwo, lbrace, 0, 0),
+                [a] => p.add_node(if comma { two_comma } else { two }, lbrace, a, 0),
+                [a, b] => p.add_node(if comma { two_comma } else { two }, lbrace, a, b),
+                _ => {
+                    let span = p.scratch_to_span(top);
+                    let tag = if comma { many_comma } else { many };
+                    p.add_node(tag, lbrace, span.start, span.end)
+                }
+            })
+        })
+    }
+
+    fn expect_primary_type_expr(&mut self) -> Result<NodeIndex> {
+        let node = self.parse_primary_type_expr()?;
+        if node == NULL_NODE {
+            return self.fail(ErrorTag::ExpectedPrimaryTypeExpr);
+        }
+        Ok(node)
+    }
+
+    /// FieldInit <- DOT IDENTIFIER EQUAL Expr
+    fn parse_field_init(&mut self) -> Result<NodeIndex> {
+        if self
+            .eat_tokens(&[Tag::Period, Tag::Identifier, Tag::Equal])
+            .is_none()
+        {
+            return Ok(NULL_NODE);
+        }
+        self.expect_expr()
+    }
+
+    fn expect_field_init(&mut self) -> Result<NodeIndex> {
+        if self
+            .eat_tokens(&[Tag::Period, Tag::Identifier, Tag::Equal])
+            .is_none()
+        {
+            return self.fail(ErrorTag::ExpectedInitializer);
+        }
+        self.expect_expr()
+    }
+
+    /// WhileContinueExpr <- COLON LPAREN AssignExpr RPAREN
+    fn parse_while_continue_expr(&mut self) -> Result<NodeIndex> {
+        if self.eat_token(Tag::Colon).is_none() {
+            if self.current() == Tag::LParen && self.tokens_on_same_line(self.tok_i - 1, self.tok_i)
+            {
+                return self.fail(ErrorTag::ExpectedContinueExpr);
+            }
+            return Ok(NULL_NODE);
+        }
+        self.expect_token(Tag::LParen)?;
+        let node = self.parse_assign_expr()?;
+        if node == NULL_NODE {
+            return self.fail(ErrorTag::ExpectedExprOrAssignment);
+        }
+        self.expect_token(Tag::RParen)?;
+        Ok(node)
+    }
+
+    /// LinkSection <- KEYWORD_linksection LPAREN Expr RPAREN
+    fn parse_link_section(&mut self) -> Result<NodeIndex> {
+        self.parse_keyword_operand(Tag::KWLinksection)
+    }
+
+    /// CallConv <- KEYWORD_callconv LPAREN Expr RPAREN
+    fn parse_callconv(&mut self) -> Result<NodeIndex> {
+        self.parse_keyword_operand(Tag::KWCallconv)
+    }
+
+    /// AddrSpace <- KEYWORD_addrspace LPAREN Expr RPAREN
+    fn parse_addr_space(&mut self) -> Result<NodeIndex> {
+        self.parse_keyword_operand(Tag::KWAddrspace)
+    }
+
+    /// ByteAlign <- KEYWORD_align LPAREN Expr RPAREN
+    fn parse_byte_align(&mut self) -> Result<NodeIndex> {
+        self.parse_keyword_operand(Tag::KWAlign)
+    }
+
+    /// `keyword(Expr)`, returning the expression.
+    fn parse_keyword_operand(&mut self, keyword: Tag) -> Result<NodeIndex> {
+        if self.eat_token(keyword).is_none() {
+            return Ok(NULL_NODE);
+        }
+        self.expect_token(Tag::LParen)?;
+        let expr_node = self.expect_expr()?;
+        self.expect_token(Tag::RParen)?;
+        Ok(expr_node)
+    }
+
+    /// ParamDeclList <- (ParamDecl COMMA)* ParamDecl?
+    fn parse_param_decl_list(&mut self) -> Result<SmallSpan> {
+        self.expect_token(Tag::LParen)?;
+        self.with_scratch(|p, top| {
+            #[derive(PartialEq)]
+            enum Varargs {
+                None,
+                Seen,
+                Nonfinal(TokenIndex),
+            }
+            let mut varargs = Varargs::None;
+            loop {
+                if p.eat_token(Tag::RParen).is_some() {
+                    break;
+                }
+                if varargs == Varargs::Seen {
+                    varargs = Varargs::Nonfinal(p.tok_i);
+                }
+                let param = p.expect_param_decl()?;
+                if param != NULL_NODE {
+                    p.scratch.push(param);
+                } else if p.tag_at(p.tok_i - 1) == Tag::Ellipsis3 && varargs == Varargs::None {
+                    varargs = Varargs::Seen;
+                }
+                match p.current() {
+                    Tag::Comma => p.tok_i += 1,
+                    Tag::RParen => {
+                        p.tok_i += 1;
+                        break;
+                    }
+                    Tag::Colon | Tag::RBrace | Tag::RBrack => {
+                        return p.fail_expected(Tag::RParen);
+                    }
+                    // Likely just a missing comma; give error but continue parsing.
+                    _ => p.warn(ErrorTag::ExpectedCommaAfterParam),
+                }
+            }
+            if let Varargs::Nonfinal(token) = varargs {
+                p.warn_msg(Self::msg(ErrorTag::VarargsNonfinal, token));
+            }
+            Ok(match p.scratch[top..] {
+                [] => SmallSpan::ZeroOrOne(0),
+                [param] => SmallSpan::ZeroOrOne(param),
+                _ => SmallSpan::Multi(p.scratch_to_span(top)),
+            })
+        })
+    }
+
+    /// ParamDecl
+    ///     <- doc_comment? (KEYWORD_noalias / KEYWORD_comptime)? (IDENTIFIER COLON)? ParamType
+    ///      / DOT3
+    ///
+    /// ParamType
+    ///     <- KEYWORD_anytype
+    ///      / TypeExpr
+    ///
+    /// Returns 0 for `anytype` and `...`, which have no type node.
+    fn expect_param_decl(&mut self) -> Result<NodeIndex> {
+        self.eat_doc_comments();
+        match self.current() {
+            Tag::KWNoalias | Tag::KWComptime => self.tok_i += 1,
+            Tag::Ellipsis3 => {
+                self.tok_i += 1;
+                return Ok(NULL_NODE);
+            }
+            _ => {}
+        }
+        self.eat_tokens(&[Tag::Identifier, Tag::Colon]);
+        if self.eat_token(Tag::KWAnytype).is_some() {
+            Ok(NULL_NODE)
+        } else {
+            self.expect_type_expr()
+        }
+    }
+
+    /// Payload <- PIPE IDENTIFIER PIPE
+    fn parse_payload(&mut self) -> Result<TokenIndex> {
+        if self.eat_token(Tag::Pipe).is_none() {
+            return Ok(0);
+        }
+        let identifier = self.expect_token(Tag::Identifier)?;
+        self.expect_token(Tag::Pipe)?;
+        Ok(identifier)
+    }
+
+    /// PtrPayload <- PIPE ASTERISK? IDENTIFIER PIPE
+    fn parse_ptr_payload(&mut self) -> Result<TokenIndex> {
+        if self.eat_token(Tag::Pipe).is_none() {
+            return Ok(0);
+        }
+        self.eat_token(Tag::Asterisk);
+        let identifier = self.expect_token(Tag::Identifier)?;
+        self.expect_token(Tag::Pipe)?;
+        Ok(identifier)
+    }
+
+    /// PtrIndexPayload <- PIPE ASTERISK? IDENTIFIER (COMMA IDENTIFIER)? PIPE
+    ///
+    /// Returns the first identifier token, if any.
+    fn parse_ptr_index_payload(&mut self) -> Result<TokenIndex> {
+        if self.eat_token(Tag::Pipe).is_none() {
+            return Ok(0);
+        }
+        self.eat_token(Tag::Asterisk);
+        let identifier = self.expect_token(Tag::Identifier)?;
+        if self.eat_token(Tag::Comma).is_some() {
+            self.expect_token(Tag::Identifier)?;
+        }
+        self.expect_token(Tag::Pipe)?;
+        Ok(identifier)
+    }
+
+    /// SwitchProng <- KEYWORD_inline? SwitchCase EQUALRARROW PtrIndexPayload? SingleAssignExpr
+    ///
+    /// SwitchCase
+    ///     <- SwitchItem (COMMA SwitchItem)* COMMA?
+    ///      / KEYWORD_else
+    fn parse_switch_prong(&mut self) -> Result<NodeIndex> {
+        self.with_scratch(|p, top| {
+            let is_inline = p.eat_token(Tag::KWInline).is_some();
+
+            if p.eat_token(Tag::KWElse).is_none() {
+                loop {
+                    let item = p.parse_switch_item()?;
+                    if item == NULL_NODE {
+                        break;
+                    }
+                    p.scratch.push(item);
+                    if p.eat_token(Tag::Comma).is_none() {
+                        break;
+                    }
+                }
+                if p.scratch.len() == top {
+                    if is_inline {
+                        p.tok_i -= 1;
+                    }
+                    return Ok(NULL_NODE);
+                }
+            }
+            let arrow_token = p.expect_token(Tag::EqualAngleBrackRight)?;
+            p.parse_ptr_index_payload()?;
+
+            let (one, many) = if is_inline {
+                (NodeTag::SwitchCaseInlineOne, NodeTag::SwitchCaseInline)
+            } else {
+                (NodeTag::SwitchCaseOne, NodeTag::SwitchCase)
+            };
+            match p.scratch[top..] {
+                [] => {
+                    let target = p.expect_single_assign_expr()?;
+                    Ok(p.add_node(one, arrow_token, 0, target))
+                }
+                [item] => {
+                    let target = p.expect_single_assign_expr()?;
+                    Ok(p.add_node(one, arrow_token, item, target))
+                }
+                _ => {
+                    let span = p.scratch_to_span(top);
+                    let items = p.add_extra(span);
+                    let target = p.expect_single_assign_expr()?;
+                    Ok(p.add_node(many, arrow_token, items, target))
+                }
+            }
+        })
+    }
+
+    /// SwitchItem <- Expr (DOT3 Expr)?
+    fn parse_switch_item(&mut self) -> Result<NodeIndex> {
+        let expr = self.parse_expr()?;
+        if expr == NULL_NODE {
+            return Ok(NULL_NODE);
+        }
+
+        if let Some(token) = self.eat_token(Tag::Ellipsis3) {
+            let end = self.expect_expr()?;
+            return Ok(self.add_node(NodeTag::SwitchRange, token, expr, end));
+        }
+        Ok(expr)
+    }
+
+    /// PtrModifiers <- (AddrSpace / KEYWORD_align LPAREN Expr (COLON Expr COLON Expr)? RPAREN / KEYWORD_const / KEYWORD_volatile / KEYWORD_allowzero)*
+    fn parse_ptr_modifiers(&mut self) -> Result<PtrModifiers> {
+        let mut result = PtrModifiers::default();
+        let mut saw_const = false;
+        let mut saw_volatile = false;
+        let mut saw_allowzero = false;
+        loop {
+            match self.current() {
+                Tag::KWAlign => {
+                    if result.align_node != 0 {
+                        self.warn(ErrorTag::ExtraAlignQualifier);
+                    }
+                    self.tok_i += 1;
+                    self.expect_token(Tag::LParen)?;
+                    result.align_node = self.expect_expr()?;
+
+                    if self.eat_token(Tag::Colon).is_some() {
+                        result.bit_range_start = self.expect_expr()?;
+                        self.expect_token(Tag::Colon)?;
+                        result.bit_range_end = self.expect_expr()?;
+                    }
+
+                    self.expect_token(Tag::RParen)?;
+                }
+                Tag::KWConst => {
+                    if saw_const {
+                        self.warn(ErrorTag::ExtraConstQualifier);
+                    }
+                    self.tok_i += 1;
+                    saw_const = true;
+                }
+                Tag::KWVolatile => {
+                    if saw_volatile {
+                        self.warn(ErrorTag::ExtraVolatileQualifier);
+                    }
+                    self.tok_i += 1;
+                    saw_volatile = true;
+                }
+                Tag::KWAllowzero => {
+                    if saw_allowzero {
+                        self.warn(ErrorTag::ExtraAllowzeroQualifier);
+                    }
+                    self.tok_i += 1;
+                    saw_allowzero = true;
+                }
+                Tag::KWAddrspace => {
+                    if result.addrspace_node != 0 {
+                        self.warn(ErrorTag::ExtraAddrspaceQualifier);
+                    }
+                    result.addrspace_node = self.parse_addr_space()?;
+                }
+                _ => return Ok(result),
+            }
+        }
+    }
+
+    /// SuffixOp
+    ///     <- LBRACKET Expr (DOT2 (Expr? (COLON Expr)?)?)? RBRACKET
+    ///      / DOT IDENTIFIER
+    ///      / DOTASTERISK
+    ///      / DOTQUESTIONMARK
+    fn parse_suffix_op(&mut self, lhs: NodeIndex) -> Result<NodeIndex> {
+        match self.current() {
+            Tag::LBrack => {
+                let lbracket = self.next_token();
+                let index_expr = self.expect_expr()?;
+
+                if self.eat_token(Tag::Ellipsis2).is_some() {
+                    let end_expr = self.parse_expr()?;
+                    if self.eat_token(Tag::Colon).is_some() {
+                        let sentinel = self.expect_expr()?;
+                        self.expect_token(Tag::RBrack)?;
+                        let extra = self.add_extra(SliceSentinel {
+                            start: index_expr,
+                            end: end_expr,
+                            sentinel,
+                        });
+                        return Ok(self.add_node(NodeTag::SliceSentinel, lbracket, lhs, extra));
+                    }
+                    self.expect_token(Tag::RBrack)?;
+                    if end_expr == NULL_NODE {
+                        return Ok(self.add_node(NodeTag::SliceOpen, lbracket, lhs, index_expr));
+                    }
+                    let extra = self.add_extra(Slice {
+                        start: index_expr,
+                        end: end_expr,
+                    });
+                    return Ok(self.add_node(NodeTag::Slice, lbracket, lhs, extra));
+                }
+                self.expect_token(Tag::RBrack)?;
+                Ok(self.add_node(NodeTag::ArrayAccess, lbracket, lhs, index_expr))
+            }
+            Tag::PeriodAsterisk => {
+                let main_token = self.next_token();
+                Ok(self.add_node(NodeTag::Deref, main_token, lhs, 0))
+            }
+            Tag::InvalidPeriodAsterisks => {
+                self.warn(ErrorTag::AsteriskAfterPtrDeref);
+                let main_token = self.next_token();
+                Ok(self.add_node(NodeTag::Deref, main_token, lhs, 0))
+            }
+            Tag::Period => match self.peek(1) {
+                Tag::Identifier | Tag::QuestionMark => {
+                    let tag = match self.peek(1) {
+                        Tag::Identifier => NodeTag::FieldAccess,
+                        _ => NodeTag::UnwrapOptional,
+                    };
+                    let dot = self.next_token();
+                    let rhs = self.next_token();
+                    Ok(self.add_node(tag, dot, lhs, rhs))
+                }
+                // This is a misplaced `.{`; the error is handled elsewhere.
+                Tag::LBrace => Ok(NULL_NODE),
+                _ => {
+                    self.tok_i += 1;
+                    self.warn(ErrorTag::ExpectedSuffixOp);
+                    Ok(NULL_NODE)
+                }
+            },
+            _ => Ok(NULL_NODE),
+        }
+    }
+
+    /// Caller must have already verified the first token.
+    ///
+    /// ContainerDeclAuto <- ContainerDeclType LBRACE container_doc_comment? ContainerMembers RBRACE
+    ///
+    /// ContainerDeclType
+    ///     <- KEYWORD_struct (LPAREN Expr RPAREN)?
+    ///      / KEYWORD_opaque
+    ///      / KEYWORD_enum (LPAREN Expr RPAREN)?
+    ///      / KEYWORD_union (LPAREN (KEYWORD_enum (LPAREN Expr RPAREN)? / Expr) RPAREN)?
+    fn parse_container_decl_auto(&mut self) -> Result<NodeIndex> {
+        let main_token = self.next_token();
+        let arg_expr = match self.tag_at(main_token) {
+            Tag::KWOpaque => NULL_NODE,
+            Tag::KWStruct | Tag::KWEnum => {
+                if self.eat_token(Tag::LParen).is_some() {
+                    let expr = self.expect_expr()?;
+                    self.expect_token(Tag::RParen)?;
+                    expr
+                } else {
+                    NULL_NODE
+                }
+            }
+            Tag::KWUnion => {
+                if self.eat_token(Tag::LParen).is_none() {
+                    NULL_NODE
+                } else if self.eat_token(Tag::KWEnum).is_some() {
+                    return self.finish_tagged_union(main_token);
+                } else {
+                    let expr = self.expect_expr()?;
+                    self.expect_token(Tag::RParen)?;
+                    expr
+                }
+            }
+            _ => {
+                self.tok_i -= 1;
+                return self.fail(ErrorTag::ExpectedContainer);
+            }
+        };
+        self.expect_token(Tag::LBrace)?;
+        let members = self.parse_container_members();
+        self.expect_token(Tag::RBrace)?;
+        let pick = |plain, trailing| if members.trailing { trailing } else { plain };
+        if arg_expr == NULL_NODE {
+            if members.len <= 2 {
+                let tag = pick(NodeTag::ContainerDeclTwo, NodeTag::ContainerDeclTwoTrailing);
+                return Ok(self.add_node(tag, main_token, members.lhs, members.rhs));
+            }
+            let span = members.to_span(self);
+            let tag = pick(NodeTag::ContainerDecl, NodeTag::ContainerDeclTrailing);
+            return Ok(self.add_node(tag, main_token, span.start, span.end));
+        }
+        let span = members.to_span(self);
+        let extra = self.add_extra(span);
+        let tag = pick(NodeTag::ContainerDeclArg, NodeTag::ContainerDeclArgTrailing);
+        Ok(self.add_node(tag, main_token, arg_expr, extra))
+    }
+
+    /// Parses a `union(enum ...)` after the `enum`.
+    fn finish_tagged_union(&mut self, main_token: TokenIndex) -> Result<NodeIndex> {
+        if self.eat_token(Tag::LParen).is_some() {
+            let enum_tag_expr = self.expect_expr()?;
+            self.expect_token(Tag::RParen)?;
+            self.expect_token(Tag::RParen)?;
+
+            self.expect_token(Tag::LBrace)?;
+            let members = self.parse_container_members();
+            let members_span = members.to_span(self);
+            self.expect_token(Tag::RBrace)?;
+            let tag = if members.trailing {
+                NodeTag::TaggedUnionEnumTagTrailing
+            } else {
+                NodeTag::TaggedUnionEnumTag
+            };
+            let extra = self.add_extra(members_span);
+            return Ok(self.add_node(tag, main_token, enum_tag_expr, extra));
+        }
+        self.expect_token(Tag::RParen)?;
+
+        self.expect_token(Tag::LBrace)?;
+        let members = self.parse_container_members();
+        self.expect_token(Tag::RBrace)?;
+        if members.len <= 2 {
+            let tag = if members.trailing {
+                NodeTag::TaggedUnionTwoTrailing
+            } else {
+                NodeTag::TaggedUnionTwo
+            };
+            return Ok(self.add_node(tag, main_token, members.lhs, members.rhs));
+        }
+        let span = members.to_span(self);
+        let tag = if members.trailing {
+            NodeTag::TaggedUnionTrailing
+        } else {
+            NodeTag::TaggedUnion
+        };
+        Ok(self.add_node(tag, main_token, span.start, span.end))
+    }
+
+    /// Gives a helpful error message for those transitioning from C's
+    /// `struct Foo {};`, and skips the declaration.
+    fn parse_c_style_container(&mut self) -> Result<bool> {
+        let main_token = self.tok_i;
+        let keyword = self.current();
+        if !matches!(keyword, Tag::KWEnum | Tag::KWUnion | Tag::KWStruct) {
+            return Ok(false);
+        }
+        let identifier = self.tok_i + 1;
+        if self.tag_at(identifier) != Tag::Identifier {
+            return Ok(false);
+        }
+        self.tok_i += 2;
+
+        self.warn_msg(Error {
+            expected_tag: Some(self.tag_at(main_token)),
+            ..Self::msg(ErrorTag::CStyleContainer, identifier)
+        });
+        self.warn_msg(Error {
+            is_note: true,
+            expected_tag: Some(keyword),
+            ..Self::msg(ErrorTag::ZigStyleContainer, identifier)
+        });
+
+        self.expect_token(Tag::LBrace)?;
+        self.parse_container_members();
+        self.expect_token(Tag::RBrace)?;
+        self.expect_semicolon(ErrorTag::ExpectedSemiAfterDecl, true)?;
+        Ok(true)
+    }
+
+    /// Block <- LBRACE Statement* RBRACE
+    fn parse_block(&mut self) -> Result<NodeIndex> {
+        let Some(lbrace) = self.eat_token(Tag::LBrace) else {
+            return Ok(NULL_NODE);
+        };
+        self.with_scratch(|p, top| {
+            while p.current() != Tag::RBrace {
+                let Some(statement) = p.expect_statement_recoverable()? else {
+                    break;
+                };
+                p.scratch.push(statement);
+            }
+            p.expect_token(Tag::RBrace)?;
+            let semicolon = p.tag_at(p.tok_i - 2) == Tag::Semicolon;
+            let (two, many) = if semicolon {
+                (NodeTag::BlockTwoSemicolon, NodeTag::BlockSemicolon)
+            } else {
+                (NodeTag::BlockTwo, NodeTag::Block)
+            };
+            Ok(match p.scratch[top..] {
+                [] => p.add_node(NodeTag::BlockTwo, lbrace, 0, 0),
+                [a] => p.add_node(two, lbrace, a, 0),
+                [a, b] => p.add_node(two, lbrace, a, b),
+                _ => {
+                    let span = p.scratch_to_span(top);
+                    p.add_node(many, lbrace, span.start, span.end)
+                }
+            })
+        })
+    }
+
+    /// BreakLabel <- COLON IDENTIFIER
+    fn parse_break_label(&mut self) -> Result<TokenIndex> {
+        if self.eat_token(Tag::Colon).is_none() {
+            return Ok(0);
+        }
+        self.expect_token(Tag::Identifier)
+    }
+
+    /// BlockLabel <- IDENTIFIER COLON
+    fn parse_block_label(&mut self) -> TokenIndex {
+        if self.current() == Tag::Identifier && self.peek(1) == Tag::Colon {
+            let identifier = self.tok_i;
+            self.tok_i += 2;
+            return identifier;
+        }
+        0
+    }
+
+    /// SwitchExpr <- KEYWORD_switch LPAREN Expr RPAREN LBRACE SwitchProngList RBRACE
+    ///
+    /// A labeled switch has the label as its main token, so that `continue`
+    /// can target it.
+    fn parse_switch_expr(&mut self, is_labeled: bool) -> Result<NodeIndex> {
+        let Some(switch_token) = self.eat_token(Tag::KWSwitch) else {
+            return Ok(NULL_NODE);
+        };
+        let main_token = if is_labeled {
+            switch_token - 2
+        } else {
+            switch_token
+        };
+        self.expect_switch_suffix(main_token)
+    }
+
+    fn expect_switch_expr(&mut self, is_labeled: bool) -> Result<NodeIndex> {
+        let switch_token = self.assert_token(Tag::KWSwitch);
+        let main_token = if is_labeled {
+            switch_token - 2
+        } else {
+            switch_token
+        };
+        self.expect_switch_suffix(main_token)
+    }
+
+    fn expect_switch_suffix(&mut self, main_token: TokenIndex) -> Result<NodeIndex> {
+        self.expect_token(Tag::LParen)?;
+        let expr_node = self.expect_expr()?;
+        self.expect_token(Tag::RParen)?;
+        self.expect_token(Tag::LBrace)?;
+        let cases = self.parse_switch_prong_list()?;
+        let trailing_comma = self.tag_at(self.tok_i - 1) == Tag::Comma;
+        self.expect_token(Tag::RBrace)?;
+
+        let tag = if trailing_comma {
+            NodeTag::SwitchComma
+        } else {
+            NodeTag::Switch
+        };
+        let extra = self.add_extra(cases);
+        Ok(self.add_node(tag, main_token, expr_node, extra))
+    }
+
+    /// SwitchProngList <- (SwitchProng COMMA)* SwitchProng?
+    fn parse_switch_prong_list(&mut self) -> Result<SubRange> {
+        self.with_scratch(|p, top| {
+            loop {
+                let item = p.parse_switch_prong()?;
+                if item == NULL_NODE {
+                    break;
+                }
+                p.scratch.push(item);
+                match p.current() {
+                    Tag::Comma => p.tok_i += 1,
+                    // All possible delimiters.
+                    Tag::Colon | Tag::RParen | Tag::RBrace | Tag::RBrack => break,
+                    // Likely just a missing comma; give error but continue parsing.
+                    _ => p.warn(ErrorTag::ExpectedCommaAfterSwitchProng),
+                }
+            }
+            Ok(p.scratch_to_span(top))
+        })
+    }
+
+    /// AsmExpr <- KEYWORD_asm KEYWORD_volatile? LPAREN Expr AsmOutput? RPAREN
+    ///
+    /// AsmOutput <- COLON AsmOutputList AsmInput?
+    ///
+    /// AsmInput <- COLON AsmInputList AsmClobbers?
+    ///
+    /// AsmClobbers <- COLON StringList
+    ///
+    /// StringList <- (STRINGLITERAL COMMA)* STRINGLITERAL?
+    ///
+    /// AsmOutputList <- (AsmOutputItem COMMA)* AsmOutputItem?
+    ///
+    /// AsmInputList <- (AsmInputItem COMMA)* AsmInputItem?
+    fn expect_asm_expr(&mut self) -> Result<NodeIndex> {
+        let asm_token = self.assert_token(Tag::KWAsm);
+        self.eat_token(Tag::KWVolatile);
+        self.expect_token(Tag::LParen)?;
+        let template = self.expect_expr()?;
+
+        if let Some(rparen) = self.eat_token(Tag::RParen) {
+            return Ok(self.add_node(NodeTag::AsmSimple, asm_token, template, rparen));
+        }
+
+        self.expect_token(Tag::Colon)?;
+
+        self.with_scratch(|p, top| {
+            p.parse_asm_items(Self::parse_asm_output_item)?;
+            if p.eat_token(Tag::Colon).is_some() {
+                p.parse_asm_items(Self::parse_asm_input_item)?;
+                if p.eat_token(Tag::Colon).is_some() {
+                    while p.eat_token(Tag::StringLiteral).is_some() {
+                        if !p.eat_asm_item_delimiter() {
+                            break;
+                        }
+                    }
+                }
+            }
+            let rparen = p.expect_token(Tag::RParen)?;
+            let span = p.scratch_to_span(top);
+            let extra = p.add_extra(Asm {
+                items_start: span.start,
+                items_end: span.end,
+                rparen,
+            });
+            Ok(p.add_node(NodeTag::Asm, asm_token, template, extra))
+        })
+    }
+
+    /// Pushes the items `parse_item` finds onto the scratch until it finds
+    /// none or the list ends.
+    fn parse_asm_items(&mut self, parse_item: fn(&mut Self) -> Result<NodeIndex>) -> Result<()> {
+        loop {
+            let item = parse_item(self)?;
+            if item == NULL_NODE {
+                return Ok(());
+            }
+            self.scratch.push(item);
+            if !self.eat_asm_item_delimiter() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Eats the comma after an asm list item and returns whether the list
+    /// may continue.
+    fn eat_asm_item_delimiter(&mut self) -> bool {
+        match self.current() {
+            Tag::Comma => {
+                self.tok_i += 1;
+                true
+            }
+            // All possible delimiters.
+            Tag::Colon | Tag::RParen | Tag::RBrace | Tag::RBrack => false,
+            // Likely just a missing comma; give error but continue parsing.
+            _ => {
+                self.warn_expected(Tag::Comma);
+                true
+            }
+        }
+    }
+
+    /// AsmOutputItem <- LBRACKET IDENTIFIER RBRACKET STRINGLITERAL LPAREN (MINUSRARROW TypeExpr / IDENTIFIER) RPAREN
+    fn parse_asm_output_item(&mut self) -> Result<NodeIndex> {
+        if self.eat_token(Tag::LBrack).is_none() {
+            return Ok(NULL_NODE);
+        }
+        let identifier = self.expect_token(Tag::Identifier)?;
+        self.expect_token(Tag::RBrack)?;
+        self.expect_token(Tag::StringLiteral)?;
+        self.expect_token(Tag::LParen)?;
+        let type_expr = if self.eat_token(Tag::Arrow).is_some() {
+            self.expect_type_expr()?
+        } else {
+            self.expect_token(Tag::Identifier)?;
+            NULL_NODE
+        };
+        let rparen = self.expect_token(Tag::RParen)?;
+        Ok(self.add_node(NodeTag::AsmOutput, identifier, type_expr, rparen))
+    }
+
+    /// AsmInputItem <- LBRACKET IDENTIFIER RBRACKET STRINGLITERAL LPAREN Expr RPAREN
+    fn parse_asm_input_item(&mut self) -> Result<NodeIndex> {
+        if self.eat_token(Tag::LBrack).is_none() {
+            return Ok(NULL_NODE);
+        }
+        let identifier = self.expect_token(Tag::Identifier)?;
+        self.expect_token(Tag::RBrack)?;
+        self.expect_token(Tag::StringLiteral)?;
+        self.expect_token(Tag::LParen)?;
+        let expr = self.expect_expr()?;
+        let rparen = self.expect_token(Tag::RParen)?;
+        Ok(self.add_node(NodeTag::AsmInput, identifier, expr, rparen))
+    }
+
+    /// FnCallArguments of a builtin call, stored inline when there are at
+    /// most two.
+    fn parse_builtin_call(&mut self) -> Result<NodeIndex> {
+        let builtin_token = self.assert_token(Tag::Builtin);
+        if self.eat_token(Tag::LParen).is_none() {
+            self.warn(ErrorTag::ExpectedParamList);
+            // Pretend this was an identifier so we can continue parsing.
+            return Ok(self.add_node(NodeTag::Identifier, builtin_token, 0, 0));
+        }
+        self.with_scratch(|p, top| {
+            loop {
+                if p.eat_token(Tag::RParen).is_some() {
+                    break;
+                }
+                let param = p.expect_expr()?;
+                p.scratch.push(param);
+                match p.current() {
+                    Tag::Comma => p.tok_i += 1,
+                    Tag::RParen => {
+                        p.tok_i += 1;
+                        break;
+                    }
+                    // Likely just a missing comma; give error but continue parsing.
+                    _ => p.warn(ErrorTag::ExpectedCommaAfterArg),
+                }
+            }
+            let comma = p.tag_at(p.tok_i - 2) == Tag::Comma;
+            let (two, many) = if comma {
+                (NodeTag::BuiltinCallTwoComma, NodeTag::BuiltinCallComma)
+            } else {
+                (NodeTag::BuiltinCallTwo, NodeTag::BuiltinCall)
+            };
+            Ok(match p.scratch[top..] {
+                [] => p.add_node(NodeTag::BuiltinCallTwo, builtin_token, 0, 0),
+                [a] => p.add_node(two, builtin_token, a, 0),
+                [a, b] => p.add_node(two, builtin_token, a, b),
+                _ => {
+                    let span = p.scratch_to_span(top);
+                    p.add_node(many, builtin_token, span.start, span.end)
+                }
+            })
+        })
+    }
+
+    /// Skips over doc comment tokens. Returns the first one, if any.
+    fn eat_doc_comments(&mut self) -> Option<TokenIndex> {
+        let tok = self.eat_token(Tag::DocComment)?;
+        let mut first_line = tok;
+        if tok > 0 && self.tokens_on_same_line(tok - 1, tok) {
+            self.warn_msg(Self::msg(ErrorTag::SameLineDocComment, tok));
+            first_line = self.eat_token(Tag::DocComment)?;
+        }
+        while self.eat_token(Tag::DocComment).is_some() {}
+        Some(first_line)
+    }
+
+    fn tokens_on_same_line(&self, token1: TokenIndex, token2: TokenIndex) -> bool {
+        let start = self.token_starts[token1 as usize] as usize;
+        let end = self.token_starts[token2 as usize] as usize;
+        !self.source[start..end].contains(&b'\n')
+    }
+
+    fn eat_token(&mut self, tag: Tag) -> Option<TokenIndex> {
+        if self.current() == tag {
+            Some(self.next_token())
+        } else {
+            None
+        }
+    }
+
+    /// Eats `tags` if they are the next tokens, returning the last one.
+    fn eat_tokens(&mut self, tags: &[Tag]) -> Option<TokenIndex> {
+        let available = &self.token_tags[self.tok_i as usize..];
+        if !available.starts_with(tags) {
+            return None;
+        }
+        self.tok_i += tags.len() as u32;
+        Some(self.tok_i - 1)
+    }
+
+    fn assert_token(&mut self, tag: Tag) -> TokenIndex {
+        let token = self.next_token();
+        debug_assert_eq!(self.tag_at(token), tag);
+        token
+    }
+
+    fn expect_token(&mut self, tag: Tag) -> Result<TokenIndex> {
+        if self.current() != tag {
+            return self.fail_expected(tag);
+        }
+        Ok(self.next_token())
+    }
+
+    fn expect_semicolon(&mut self, error_tag: ErrorTag, recoverable: bool) -> Result<()> {
+        if self.current() == Tag::Semicolon {
+            self.next_token();
+            return Ok(());
+        }
+        self.warn(error_tag);
+        if !recoverable {
+            return Err(ParseError);
+        }
+        Ok(())
+    }
+
+    fn next_token(&mut self) -> TokenIndex {
+        let result = self.tok_i;
+        self.tok_i += 1;
+        result
+    }
+}
+
+fn assign_op_node(tok: Tag) -> Option<NodeTag> {
+    Some(match tok {
+        Tag::AsteriskEqual => NodeTag::AssignMul,
+        Tag::SlashEqual => NodeTag::AssignDiv,
+        Tag::PercentEqual => NodeTag::AssignMod,
+        Tag::PlusEqual => NodeTag::AssignAdd,
+        Tag::MinusEqual => NodeTag::AssignSub,
+        Tag::AngleBrackAngleBrackLeftEqual => NodeTag::AssignShl,
+        Tag::AngleBrackAngleBrackLeftPipeEqual => NodeTag::AssignShlSat,
+        Tag::AngleBrackAngleBrackRightEqual => NodeTag::AssignShr,
+        Tag::AmpersandEqual => NodeTag::AssignBitAnd,
+        Tag::CaretEqual => NodeTag::AssignBitXor,
+        Tag::PipeEqual => NodeTag::AssignBitOr,
+        Tag::AsteriskPercentEqual => NodeTag::AssignMulWrap,
+        Tag::PlusPercentEqual => NodeTag::AssignAddWrap,
+        Tag::MinusPercentEqual => NodeTag::AssignSubWrap,
+        Tag::AsteriskPipeEqual => NodeTag::AssignMulSat,
+        Tag::PlusPipeEqual => NodeTag::AssignAddSat,
+        Tag::MinusPipeEqual => NodeTag::AssignSubSat,
+        Tag::Equal => NodeTag::Assign,
+        _ => return None,
+    })
+}
+
+/// The node for a binary operator; the tag must have a
+/// [`Tag::binary_precedence`].
+fn binary_op_node(tok: Tag) -> NodeTag {
+    match tok {
+        Tag::KWOr => NodeTag::BoolOr,
+        Tag::KWAnd => NodeTag::BoolAnd,
+        Tag::EqualEqual => NodeTag::EqualEqual,
+        Tag::BangEqual => NodeTag::BangEqual,
+        Tag::AngleBrackLeft => NodeTag::LessThan,
+        Tag::AngleBrackRight => NodeTag::GreaterThan,
+        Tag::AngleBrackLeftEqual => NodeTag::LessOrEqual,
+        Tag::AngleBrackRightEqual => NodeTag::GreaterOrEqual,
+        Tag::Ampersand => NodeTag::BitAnd,
+        Tag::Caret => NodeTag::BitXor,
+        Tag::Pipe => NodeTag::BitOr,
+        Tag::KWOrelse => NodeTag::Orelse,
+        Tag::KWCatch => NodeTag::Catch,
+        Tag::AngleBrackAngleBrackLeft => NodeTag::Shl,
+        Tag::AngleBrackAngleBrackLeftPipe => NodeTag::ShlSat,
+        Tag::AngleBrackAngleBrackRight => NodeTag::Shr,
+        Tag::Plus => NodeTag::Add,
+        Tag::Minus => NodeTag::Sub,
+        Tag::PlusPlus => NodeTag::ArrayCat,
+        Tag::PlusPercent => NodeTag::AddWrap,
+        Tag::MinusPercent => NodeTag::SubWrap,
+        Tag::PlusPipe => NodeTag::AddSat,
+        Tag::MinusPipe => NodeTag::SubSat,
+        Tag::PipePipe => NodeTag::MergeErrorSets,
+        Tag::Asterisk => NodeTag::Mul,
+        Tag::Slash => NodeTag::Div,
+        Tag::Percent => NodeTag::Mod,
+        Tag::AsteriskAsterisk => NodeTag::ArrayMult,
+        Tag::AsteriskPercent => NodeTag::MulWrap,
+        Tag::AsteriskPipe => NodeTag::MulSat,
+        _ => unreachable!("{tok:?} is not a binary operator"),
+    }
+}
+
+/// `std.ascii.isWhitespace`: space, tab, newline, carriage return, vertical
+/// tab and form feed.
+fn is_whitespace(c: u8) -> bool {
+    matches!(c, b' ' | b'\t' | b'\n' | b'\r' | 0x0b | 0x0c)
+}
@@ -0,0 +1,574 @@
+//! Correctly rounded conversion of number literal tokens to IEEE floats of
+//! every width Zig supports. Literals are evaluated exactly with big integer
+//! arithmetic and rounded once, to nearest with ties to even, so the result
+//! is bit-identical to the compiler's for decimal and hex floats alike.
+
+use std::cmp::Ordering;
+
+use crate::zig::number_literal::{parse_number_literal, NumberError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FloatType {
+    F16,
+    F32,
+    F64,
+    F80,
+    F128,
+}
+
+/// How the rounded value relates to the literal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Exact,
+    Inexact,
+    /// The literal is too large for the type and became infinity.
+    Overflow,
+    /// The literal is nonzero but too small for the type and became zero.
+    Underflow,
+}
+
+/// Encoded value, in the low `FloatType::bits` bits of `bits`. Literals are
+/// never negative, so the sign bit is always clear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Float {
+    pub bits: u128,
+    pub status: Status,
+}
+
+impl FloatType {
+    pub fn bits(self) -> u32 {
+        match self {
+            FloatType::F16 => 16,
+            FloatType::F32 => 32,
+            FloatType::F64 => 64,
+            FloatType::F80 => 80,
+            FloatType::F128 => 128,
+        }
+    }
+
+    fn exponent_bits(self) -> u32 {
+        match self {
+            FloatType::F16 => 5,
+            FloatType::F32 => 8,
+            FloatType::F64 => 11,
+            FloatType::F80 | FloatType::F128 => 15,
+        }
+    }
+
+    /// Significand bits including the leading integer bit, which is implicit
+    /// in every format except `f80`.
+    fn precision(self) -> u32 {
+        match self {
+            FloatType::F16 => 11,
+            FloatType::F32 => 24,
+            FloatType::F64 => 53,
+            FloatType::F80 => 64,
+            FloatType::F128 => 113,
+        }
+    }
+
+    fn fraction_bits(self) -> u32 {
+        match self {
+            FloatType::F80 => 64,
+            _ => self.precision() - 1,
+        }
+    }
+
+    fn bias(self) -> i64 {
+        (1 << (self.exponent_bits() - 1)) - 1
+    }
+
+    fn infinity(self) -> u128 {
+        let exponent = ((1u128 << self.exponent_bits()) - 1) << self.fraction_bits();
+        match self {
+            FloatType::F80 => exponent | 1 << 63,
+            _ => exponent,
+        }
+    }
+
+    /// Rounds `(q + sticky) * 2^scale` to this type, where `sticky` stands
+    /// for any nonzero fraction below the last bit of `q`.
+    fn round(self, q: u128, sticky: bool, scale: i64) -> Float {
+        let p = self.precision() as i64;
+        let emin = 1 - self.bias();
+        let exponent = (128 - q.leading_zeros() as i64) - 1 + scale;
+        let lsb = exponent.max(emin) - (p - 1);
+        let shift = lsb - scale;
+
+        let (mut mantissa, round, sticky) = if shift <= 0 {
+            (q << -shift, false, sticky)
+        } else if shift > 128 {
+            (0, false, true)
+        } else {
+            let lower = q & ((1u128 << (shift - 1)) - 1);
+            (
+                q.checked_shr(shift as u32).unwrap_or(0),
+                q >> (shift - 1) & 1 == 1,
+                sticky || lower != 0,
+            )
+        };
+        if round && (sticky || mantissa & 1 == 1) {
+            mantissa += 1;
+        }
+        let mut lsb = lsb;
+        if mantissa == 1 << p {
+            mantissa >>= 1;
+            lsb += 1;
+        }
+
+        if mantissa == 0 {
+            return Float {
+                bits: 0,
+                status: Status::Underflow,
+            };
+        }
+        let status = if round || sticky {
+            Status::Inexact
+        } else {
+            Status::Exact
+        };
+        let biased = if mantissa >> (p - 1) == 1 {
+            lsb + (p - 1) + self.bias()
+        } else {
+            0
+        };
+        if biased >= (1 << self.exponent_bits()) - 1 {
+            return Float {
+                bits: self.infinity(),
+                status: Status::Overflow,
+            };
+        }
+        let fraction = match self {
+            FloatType::F80 => mantissa,
+            _ => mantissa & ((1 << self.fraction_bits()) - 1),
+        };
+        Float {
+            bits: (biased as u128) << self.fraction_bits() | fraction,
+            status,
+        }
+    }
+}
+
+/// Converts the text of a `Tag::NumberLiteral` token, integer or float, to
+/// the nearest value of type `ty`.
+pub fn parse_float(bytes: &[u8], ty: FloatType) -> Result<Float, NumberError> {
+    parse_number_literal(bytes)?;
+
+    let (radix, digits) = match bytes {
+        [b'0', b'x', rest @ ..] => (16, rest),
+        [b'0', b'o', rest @ ..] => (8, rest),
+        [b'0', b'b', rest @ ..] => (2, rest),
+        _ => (10, bytes),
+    };
+    let exponent_chars: &[u8] = if radix == 16 { b"pP" } else { b"eE" };
+    let (significand, exponent) = match digits.iter().position(|c| exponent_chars.contains(c)) {
+        Some(i) => (&digits[..i], parse_exponent(&digits[i + 1..])),
+        None => (digits, 0),
+    };
+
+    let mut mantissa = BigUint::zero();
+    let mut fraction_digits = 0i64;
+    let mut seen_period = false;
+    let mut significant_digits = 0i64;
+    for &c in significand {
+        match c {
+            b'_' => {}
+            b'.' => seen_period = true,
+            _ => {
+                let digit = (c as char).to_digit(radix).unwrap();
+                mantissa.mul_add_small(radix as u64, digit as u64);
+                if significant_digits > 0 || digit != 0 {
+                    significant_digits += 1;
+                }
+                if seen_period {
+                    fraction_digits += 1;
+                }
+            }
+        }
+    }
+    if mantissa.is_zero() {
+        return Ok(Float {
+            bits: 0,
+            status: Status::Exact,
+        });
+    }
+
+    match radix {
+        10 => Ok(decimal_to_float(
+            mantissa,
+            significant_digits,
+            exponent.saturating_sub(fraction_digits),
+            ty,
+        )),
+        16 => Ok(binary_to_float(
+            mantissa,
+            exponent.saturating_sub(4 * fraction_digits),
+            ty,
+        )),
+        _ => Ok(binary_to_float(mantissa, 0, ty)),
+    }
+}
+
+/// Parses a signed decimal exponent, saturating far beyond any float range so
+/// that absurd exponents cannot overflow the arithmetic below.
+fn parse_exponent(bytes: &[u8]) -> i64 {
+    const LIMIT: i64 = 1 << 40;
+    let (negative, digits) = match bytes {
+        [b'-', rest @ ..] => (true, rest),
+        [b'+', rest @ ..] => (false, rest),
+        _ => (false, bytes),
+    };
+    let magnitude = digits
+        .iter()
+        .filter(|&&c| c != b'_')
+        .fold(0i64, |acc, &c| (acc * 10 + (c - b'0') as i64).min(LIMIT));
+    if negative {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Rounds `mantissa * 2^scale`.
+fn binary_to_float(mantissa: BigUint, scale: i64, ty: FloatType) -> Float {
+    let (q, sticky, shift) = mantissa.truncate(ty.precision() + 3);
+    ty.round(q, sticky, scale.saturating_add(shift))
+}
+
+/// Rounds `mantissa * 10^exponent`, where the mantissa has `digits`
+/// significant decimal digits.
+fn decimal_to_float(mantissa: BigUint, digits: i64, exponent: i64, ty: FloatType) -> Float {
+    // Every format's range lies within [1e-4966, 1e4933): f128 tops out at
+    // 1.19e4932 and its smallest subnormal is 6.48e-4966. Settle anything
+    // outside that without computing enormous powers of ten.
+    if digits.saturating_add(exponent) < -4966 {
+        return Float {
+            bits: 0,
+            status: Status::Underflow,
+        };
+    }
+    if (digits - 1).saturating_add(exponent) > 4933 {
+        return Float {
+            bits: ty.infinity(),
+            status: Status::Overflow,
+        };
+    }
+
+    if exponent >= 0 {
+        return binary_to_float(mantissa.mul(&BigUint::pow10(exponent as u64)), 0, ty);
+    }
+
+    // Scale the numerator so that the quotient keeps a few bits beyond the
+    // target precision; the remainder only matters as a sticky bit.
+    let denominator = BigUint::pow10(-exponent as u64);
+    let k = (ty.precision() + 3) as i64 + denominator.bit_len() as i64 - mantissa.bit_len() as i64;
+    let (numerator, mut sticky) = if k >= 0 {
+        (mantissa.shl(k as u64), false)
+    } else {
+        mantissa.shr(-k as u64)
+    };
+    let (q, remainder_nonzero) = numerator.div_small_quotient(&denominator);
+    sticky |= remainder_nonzero;
+    ty.round(q, sticky, -k)
+}
+
+/// Minimal arbitrary precision unsigned integer, little-endian 64-bit limbs
+/// with no trailing zero limbs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BigUint(Vec<u64>);
+
+impl BigUint {
+    fn zero() -> Self {
+        BigUint(Vec::new())
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn normalize(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    fn bit_len(&self) -> u64 {
+        match self.0.last() {
+            Some(&top) => self.0.len() as u64 * 64 - top.leading_zeros() as u64,
+            None => 0,
+        }
+    }
+
+    /// `self = self * m + a`
+    fn mul_add_small(&mut self, m: u64, a: u64) {
+        let mut carry = a as u128;
+        for limb in &mut self.0 {
+            let product = *limb as u128 * m as u128 + carry;
+            *limb = product as u64;
+            carry = product >> 64;
+        }
+        if carry != 0 {
+            self.0.push(carry as u64);
+        }
+    }
+
+    fn pow10(mut n: u64) -> Self {
+        const TEN_19: u64 = 10_000_000_000_000_000_000;
+        let mut result = BigUint(vec![1]);
+        while n >= 19 {
+            result.mul_add_small(TEN_19, 0);
+            n -= 19;
+        }
+        result.mul_add_small(10u64.pow(n as u32), 0);
+        result
+    }
+
+    fn mul(&self, other: &BigUint) -> BigUint {
+        let mut result = vec![0u64; self.0.len() + other.0.len()];
+        for (i, &a) in self.0.iter().enumerate() {
+            let mut carry = 0u128;
+            for (j, &b) in other.0.iter().enumerate() {
+                let t = a as u128 * b as u128 + result[i + j] as u128 + carry;
+                result[i + j] = t as u64;
+                carry = t >> 64;
+            }
+            result[i + other.0.len()] = carry as u64;
+        }
+        let mut result = BigUint(result);
+        result.normalize();
+        result
+    }
+
+    fn shl(&self, n: u64) -> BigUint {
+        let limbs = (n / 64) as usize;
+        let bits = n % 64;
+        let mut result = vec![0u64; limbs];
+        let mut carry = 0;
+        for &limb in &self.0 {
+            result.push(limb << bits | carry);
+            carry = if bits == 0 { 0 } else { limb >> (64 - bits) };
+        }
+        result.push(carry);
+        let mut result = BigUint(result);
+        result.normalize();
+        result
+    }
+
+    /// Shifts right by `n`, also reporting whether any one bits fell off.
+    fn shr(&self, n: u64) -> (BigUint, bool) {
+        let limbs = (n / 64) as usize;
+        if limbs >= self.0.len() {
+            return (BigUint::zero(), !self.is_zero());
+        }
+        let bits = n % 64;
+        let lost = self.0[..limbs].iter().any(|&limb| limb != 0)
+            || (bits != 0 && self.0[limbs] & ((1 << bits) - 1) != 0);
+        let result: Vec<u64> = (limbs..self.0.len())
+            .map(|i| {
+                let high = match (bits, self.0.get(i + 1)) {
+                    (0, _) | (_, None) => 0,
+                    (_, Some(&next)) => next << (64 - bits),
+                };
+                self.0[i] >> bits | high
+            })
+            .collect();
+        let mut result = BigUint(result);
+        result.normalize();
+        (result, lost)
+    }
+
+    /// Keeps the top `bits` bits. Returns them along with whether anything
+    /// nonzero was dropped and how far the value was shifted right.
+    fn truncate(&self, bits: u32) -> (u128, bool, i64) {
+        let shift = self.bit_len().saturating_sub(bits as u64);
+        let (kept, sticky) = self.shr(shift);
+        (kept.to_u128(), sticky, shift as i64)
+    }
+
+    fn to_u128(&self) -> u128 {
+        assert!(self.0.len() <= 2);
+        self.0
+            .iter()
+            .rev()
+            .fold(0u128, |acc, &limb| acc << 64 | limb as u128)
+    }
+
+    fn sub_assign(&mut self, other: &BigUint) {
+        let mut borrow = false;
+        for i in 0..self.0.len() {
+            let b = other.0.get(i).copied().unwrap_or(0);
+            let (d, o1) = self.0[i].overflowing_sub(b);
+            let (d, o2) = d.overflowing_sub(borrow as u64);
+            self.0[i] = d;
+            borrow = o1 || o2;
+        }
+        debug_assert!(!borrow);
+        self.normalize();
+    }
+
+    /// Divides by `denominator` when the quotient is known to fit in a
+    /// `u128`. Returns the quotient and whether the remainder is nonzero.
+    fn div_small_quotient(mut self, denominator: &BigUint) -> (u128, bool) {
+        let mut q = 0u128;
+        let Some(shift) = self.bit_len().checked_sub(denominator.bit_len()) else {
+            return (0, !self.is_zero());
+        };
+        assert!(shift < 128);
+        for i in (0..=shift).rev() {
+            let shifted = denominator.shl(i);
+            if self.cmp(&shifted) != Ordering::Less {
+                self.sub_assign(&shifted);
+                q |= 1 << i;
+            }
+        }
+        (q, !self.is_zero())
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zig::tokenizer::TokenStream;
+
+    fn bits(literal: &str, ty: FloatType) -> u128 {
+        parse_float(literal.as_bytes(), ty).unwrap().bits
+    }
+
+    #[test]
+    fn test_matches_std_f32_f64() {
+        let literals = [
+            "0.1",
+            "1.5",
+            "3.141592653589793238462643383279502884197",
+            "1e10",
+            "1e-10",
+            "2.2250738585072011e-308",
+            "2.2250738585072014e-308",
+            "4.9406564584124654e-324",
+            "2.4703282292062327e-324",
+            "2.4703282292062328e-324",
+            "1.7976931348623157e308",
+            "1.7976931348623158e308",
+            "9007199254740993",
+            "1.00000005960464477539062499",
+            "1.00000005960464477539062500",
+            "1.00000005960464477539062501",
+            "1.4e-45",
+            "3.4028235e38",
+            "123456789012345678901234567890e-20",
+            "0.000000000000000000000000000000000000000000001",
+            "1_000.000_1",
+            "7e22",
+            "1e23",
+            "8.98846567431158e307",
+        ];
+        for literal in literals {
+            let std_text = literal.replace('_', "");
+            let f64_value: f64 = std_text.parse().unwrap();
+            let f32_value: f32 = std_text.parse().unwrap();
+            assert_eq!(
+                bits(literal, FloatType::F64),
+                f64_value.to_bits() as u128,
+                "{literal}"
+            );
+            assert_eq!(
+                bits(literal, FloatType::F32),
+                f32_value.to_bits() as u128,
+                "{literal}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_hex_and_integer_literals() {
+        assert_eq!(bits("0x1.8p3", FloatType::F64), 12.0f64.to_bits() as u128);
+        assert_eq!(bits("0x.8p1", FloatType::F32), 1.0f32.to_bits() as u128);
+        assert_eq!(
+            bits("0x1.fffffffffffffp1023", FloatType::F64),
+            f64::MAX.to_bits() as u128
+        );
+        assert_eq!(bits("0x1p-1074", FloatType::F64), 1);
+        assert_eq!(bits("0x10", FloatType::F16), 0x4c00);
+        assert_eq!(bits("0b101", FloatType::F32), 5.0f32.to_bits() as u128);
+        assert_eq!(bits("0o17", FloatType::F32), 15.0f32.to_bits() as u128);
+        assert_eq!(bits("0", FloatType::F128), 0);
+        assert_eq!(bits("0.0e99999999999", FloatType::F64), 0);
+    }
+
+    #[test]
+    fn test_f16_f80_f128() {
+        assert_eq!(bits("1.0", FloatType::F16), 0x3c00);
+        assert_eq!(bits("65504", FloatType::F16), 0x7bff);
+        assert_eq!(bits("0.1", FloatType::F16), 0x2e66);
+        assert_eq!(bits("6e-8", FloatType::F16), 0x0001);
+
+        assert_eq!(bits("1.0", FloatType::F80), 0x3fff_8000_0000_0000_0000);
+        assert_eq!(bits("0.1", FloatType::F80), 0x3ffb_cccc_cccc_cccc_cccd);
+        assert_eq!(bits("0x1p-16445", FloatType::F80), 1);
+
+        assert_eq!(bits("1.0", FloatType::F128), 0x3fff << 112);
+        assert_eq!(
+            bits("0.1", FloatType::F128),
+            0x3ffb_9999_9999_9999_9999_9999_9999_999a
+        );
+        assert_eq!(
+            bits(
+                "1.18973149535723176508575932662800702e4932",
+                FloatType::F128
+            ),
+            0x7ffe_ffff_ffff_ffff_ffff_ffff_ffff_ffff
+        );
+        assert_eq!(bits("0x1p-16494", FloatType::F128), 1);
+    }
+
+    #[test]
+    fn test_status() {
+        let status = |literal: &str, ty| parse_float(literal.as_bytes(), ty).unwrap().status;
+        assert_eq!(status("0.5", FloatType::F16), Status::Exact);
+        assert_eq!(status("0.1", FloatType::F64), Status::Inexact);
+        assert_eq!(status("65520", FloatType::F16), Status::Overflow);
+        assert_eq!(status("1e39", FloatType::F32), Status::Overflow);
+        assert_eq!(status("1e999999999", FloatType::F128), Status::Overflow);
+        assert_eq!(status("1e-50", FloatType::F32), Status::Underflow);
+        assert_eq!(
+            status("0x1p-99999999999", FloatType::F64),
+            Status::Underflow
+        );
+        assert_eq!(
+            bits("1e39", FloatType::F32),
+            f32::INFINITY.to_bits() as u128
+        );
+        assert_eq!(bits("1e5000", FloatType::F80), 0x7fff_8000_0000_0000_0000);
+        assert!(parse_float(b"1.0.0", FloatType::F64).is_err());
+    }
+
+    #[test]
+    fn test_number_literal_tokens() {
+        let source = b"0x1.8p3 2.5e-1 7";
+        let values: Vec<f64> = TokenStream::new(source)
+            .map(|token| {
+                let bits = bits(
+                    std::str::from_utf8(&source[token.loc.start..token.loc.end]).unwrap(),
+                    FloatType::F64,
+                );
+                f64::from_bits(bits as u64)
+            })
+            .collect();
+        assert_eq!(values, [12.0, 0.25, 7.0]);
+    }
+}
@@ -0,0 +1,244 @@
+use crate::zig::tokenizer::{parse_keyword, Tag, Token};
+
+/// Names that always refer to a primitive, from upstream `primitives.zig`.
+/// Sorted for binary search.
+const NAMES: &[(&str, Primitive)] = {
+    use Primitive::{Type, Value};
+    use PrimitiveType as T;
+    use PrimitiveValue as V;
+    &[
+        ("anyerror", Type(T::Anyerror)),
+        ("anyframe", Type(T::Anyframe)),
+        ("anyopaque", Type(T::Anyopaque)),
+        ("bool", Type(T::Bool)),
+        ("c_char", Type(T::CChar)),
+        ("c_int", Type(T::CInt)),
+        ("c_long", Type(T::CLong)),
+        ("c_longdouble", Type(T::CLongdouble)),
+        ("c_longlong", Type(T::CLonglong)),
+        ("c_short", Type(T::CShort)),
+        ("c_uint", Type(T::CUint)),
+        ("c_ulong", Type(T::CUlong)),
+        ("c_ulonglong", Type(T::CUlonglong)),
+        ("c_ushort", Type(T::CUshort)),
+        ("comptime_float", Type(T::ComptimeFloat)),
+        ("comptime_int", Type(T::ComptimeInt)),
+        ("f128", Type(T::F128)),
+        ("f16", Type(T::F16)),
+        ("f32", Type(T::F32)),
+        ("f64", Type(T::F64)),
+        ("f80", Type(T::F80)),
+        ("false", Value(V::False)),
+        ("isize", Type(T::Isize)),
+        ("noreturn", Type(T::Noreturn)),
+        ("null", Value(V::Null)),
+        ("true", Value(V::True)),
+        ("type", Type(T::Type)),
+        ("undefined", Value(V::Undefined)),
+        ("usize", Type(T::Usize)),
+        ("void", Type(T::Void)),
+    ]
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Primitive {
+    Type(PrimitiveType),
+    /// An arbitrary-width integer type, `u0` through `u65535` and `i0`
+    /// through `i65535`.
+    Int {
+        signedness: Signedness,
+        bits: u16,
+    },
+    Value(PrimitiveValue),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrimitiveType {
+    Anyerror,
+    Anyframe,
+    Anyopaque,
+    Bool,
+    CChar,
+    CInt,
+    CLong,
+    CLongdouble,
+    CLonglong,
+    CShort,
+    CUint,
+    CUlong,
+    CUlonglong,
+    CUshort,
+    ComptimeFloat,
+    ComptimeInt,
+    F16,
+    F32,
+    F64,
+    F80,
+    F128,
+    Isize,
+    Noreturn,
+    Type,
+    Usize,
+    Void,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PrimitiveValue {
+    True,
+    False,
+    Null,
+    Undefined,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Signedness {
+    Signed,
+    Unsigned,
+}
+
+impl Primitive {
+    /// Classifies an identifier. Integer types with a leading zero or more
+    /// than 65535 bits are rejected by the compiler and are not classified,
+    /// though [`is_primitive`] still reports them.
+    pub fn from_name(name: &[u8]) -> Option<Primitive> {
+        if let Ok(index) = NAMES.binary_search_by(|(probe, _)| probe.as_bytes().cmp(name)) {
+            return Some(NAMES[index].1);
+        }
+        let (&first, digits) = name.split_first()?;
+        let signedness = match first {
+            b'i' => Signedness::Signed,
+            b'u' => Signedness::Unsigned,
+            _ => return None,
+        };
+        if digits.is_empty()
+            || !digits.iter().all(u8::is_ascii_digit)
+            || (digits[0] == b'0' && digits.len() > 1)
+        {
+            return None;
+        }
+        let bits = std::str::from_utf8(digits).ok()?.parse().ok()?;
+        Some(Primitive::Int { signedness, bits })
+    }
+
+    /// Classifies a `Tag::Identifier` token. `@"u8"` is a plain identifier
+    /// and is not classified, like any other token.
+    pub fn from_token(source: &[u8], token: &Token) -> Option<Primitive> {
+        if token.tag != Tag::Identifier {
+            return None;
+        }
+        Primitive::from_name(&source[token.loc.start..token.loc.end])
+    }
+}
+
+/// Returns true if a name matches a primitive type or value, excluding `_`.
+/// Integer type names like `u8` or `i32` are only matched for syntax, so this
+/// still returns true when they have an oversized bit count or leading zeroes.
+pub fn is_primitive(name: &[u8]) -> bool {
+    if NAMES
+        .binary_search_by(|(probe, _)| probe.as_bytes().cmp(name))
+        .is_ok()
+    {
+        return true;
+    }
+    match name {
+        [b'i' | b'u', digits @ ..] if !digits.is_empty() => digits.iter().all(u8::is_ascii_digit),
+        _ => false,
+    }
+}
+
+/// Whether `name` is valid identifier syntax and not a keyword.
+pub fn is_valid_id(name: &[u8]) -> bool {
+    match name.split_first() {
+        Some((first, rest)) => {
+            (first.is_ascii_alphabetic() || *first == b'_')
+                && rest.iter().all(|&c| c.is_ascii_alphanumeric() || c == b'_')
+                && parse_keyword(name).is_none()
+        }
+        None => false,
+    }
+}
+
+/// Whether declaring something called `name` requires the `@"..."` form:
+/// it is not a valid identifier, is a keyword, shadows a primitive, or is
+/// the discard identifier `_`.
+pub fn needs_quoting(name: &[u8]) -> bool {
+    !is_valid_id(name) || is_primitive(name) || name == b"_"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zig::tokenizer::TokenStream;
+
+    #[test]
+    fn test_names_sorted() {
+        for pair in NAMES.windows(2) {
+            assert!(pair[0].0 < pair[1].0);
+        }
+    }
+
+    #[test]
+    fn test_from_name() {
+        use Signedness::*;
+        assert_eq!(
+            Primitive::from_name(b"bool"),
+            Some(Primitive::Type(PrimitiveType::Bool))
+        );
+        assert_eq!(
+            Primitive::from_name(b"undefined"),
+            Some(Primitive::Value(PrimitiveValue::Undefined))
+        );
+        let int = |signedness, bits| Some(Primitive::Int { signedness, bits });
+        assert_eq!(Primitive::from_name(b"u0"), int(Unsigned, 0));
+        assert_eq!(Primitive::from_name(b"i7"), int(Signed, 7));
+        assert_eq!(Primitive::from_name(b"u65535"), int(Unsigned, 65535));
+        for name in [
+            &b"u65536"[..],
+            b"u08",
+            b"u",
+            b"i1x",
+            b"x32",
+            b"U8",
+            b"_",
+            b"",
+        ] {
+            assert_eq!(Primitive::from_name(name), None);
+        }
+    }
+
+    #[test]
+    fn test_is_primitive_and_quoting() {
+        assert!(is_primitive(b"u08"));
+        assert!(is_primitive(b"i99999"));
+        assert!(is_primitive(b"void"));
+        assert!(!is_primitive(b"_"));
+        assert!(!is_primitive(b"u"));
+
+        assert!(!needs_quoting(b"foo_1"));
+        assert!(needs_quoting(b"u8"));
+        assert!(needs_quoting(b"null"));
+        assert!(needs_quoting(b"_"));
+        assert!(needs_quoting(b"fn"));
+        assert!(needs_quoting(b"1x"));
+        assert!(needs_quoting(b"a b"));
+        assert!(needs_quoting(b""));
+    }
+
+    #[test]
+    fn test_from_token() {
+        let source = b"const x: u32 = null; @\"u8\"";
+        let primitives: Vec<Primitive> = TokenStream::new(source)
+            .filter_map(|token| Primitive::from_token(source, &token))
+            .collect();
+        assert_eq!(
+            primitives,
+            [
+                Primitive::Int {
+                    signedness: Signedness::Unsigned,
+                    bits: 32
+                },
+                Primitive::Value(PrimitiveValue::Null),
+            ]
+        );
+    }
+}
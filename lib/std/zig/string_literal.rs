@@ -0,0 +1,508 @@
+//! Decoding of string and character literal tokens, ported from upstream
+//! `std.zig.string_literal`. All offsets are relative to the start of the
+//! token, i.e. the opening quote is at offset 0.
+
+use std::borrow::Cow;
+
+use crate::zig::tokenizer::{Loc, Tag, Token, TokenList};
+
+/// Why a literal failed to decode, along with the byte offset of the
+/// offending character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The character after the backslash is not a known escape.
+    InvalidEscapeCharacter(usize),
+    /// A `\x` escape is not followed by two hex digits.
+    ExpectedHexDigit(usize),
+    /// `\u{}` with nothing between the braces.
+    EmptyUnicodeEscapeSequence(usize),
+    /// A `\u{...}` escape contains something other than hex digits.
+    ExpectedHexDigitOrRbrace(usize),
+    /// A `\u{...}` escape is larger than `0x10ffff`.
+    InvalidUnicodeCodepoint(usize),
+    /// A `\u{...}` escape names a UTF-16 surrogate, which has no UTF-8 encoding.
+    SurrogateCodepoint(usize),
+    /// `\u` is not followed by `{`.
+    ExpectedLbrace(usize),
+    /// The literal ends inside a `\u{...}` escape.
+    ExpectedRbrace(usize),
+    /// A character literal holds more than one code point.
+    ExpectedSingleQuote(usize),
+    /// A byte that may not appear unescaped inside a literal, such as `\n`.
+    InvalidCharacter(usize),
+    /// An escape sequence consumed the closing quote, as in `"\"`; the
+    /// offset is the end of the literal.
+    UnterminatedString(usize),
+    /// A decoded `@"..."` identifier name is not valid UTF-8; the offset is
+    /// that of the character or escape that starts the invalid sequence.
+    InvalidUtf8(usize),
+    /// `''`
+    EmptyCharLiteral,
+}
+
+impl Error {
+    pub fn offset(&self) -> usize {
+        match *self {
+            Error::InvalidEscapeCharacter(i)
+            | Error::ExpectedHexDigit(i)
+            | Error::EmptyUnicodeEscapeSequence(i)
+            | Error::ExpectedHexDigitOrRbrace(i)
+            | Error::InvalidUnicodeCodepoint(i)
+            | Error::SurrogateCodepoint(i)
+            | Error::ExpectedLbrace(i)
+            | Error::ExpectedRbrace(i)
+            | Error::ExpectedSingleQuote(i)
+            | Error::InvalidCharacter(i)
+            | Error::UnterminatedString(i)
+            | Error::InvalidUtf8(i) => i,
+            Error::EmptyCharLiteral => 0,
+        }
+    }
+
+    /// Human readable description, worded like the compiler's own errors.
+    /// `bytes` must be the literal the error was produced from.
+    pub fn message(&self, bytes: &[u8]) -> String {
+        let found = |i: usize| match bytes.get(i) {
+            Some(&c) => (c as char).escape_default().to_string(),
+            None => "end of literal".to_string(),
+        };
+        match *self {
+            Error::InvalidEscapeCharacter(i) => format!("invalid escape character: '{}'", found(i)),
+            Error::ExpectedHexDigit(i) => format!("expected hex digit, found '{}'", found(i)),
+            Error::EmptyUnicodeEscapeSequence(_) => "empty unicode escape sequence".to_string(),
+            Error::ExpectedHexDigitOrRbrace(i) => {
+                format!("expected hex digit or '}}', found '{}'", found(i))
+            }
+            Error::InvalidUnicodeCodepoint(_) => {
+                "unicode escape does not correspond to a valid unicode scalar value".to_string()
+            }
+            Error::SurrogateCodepoint(_) => {
+                "unicode escape names a surrogate code point, which cannot be encoded".to_string()
+            }
+            Error::ExpectedLbrace(i) => format!("expected '{{', found '{}'", found(i)),
+            Error::ExpectedRbrace(i) => format!("expected '}}', found '{}'", found(i)),
+            Error::ExpectedSingleQuote(i) => {
+                format!("expected single quote ('), found '{}'", found(i))
+            }
+            Error::InvalidCharacter(i) => {
+                format!(
+                    "invalid byte in string or character literal: '{}'",
+                    found(i)
+                )
+            }
+            Error::UnterminatedString(_) => "unterminated string literal".to_string(),
+            Error::InvalidUtf8(_) => "identifier name is not valid UTF-8".to_string(),
+            Error::EmptyCharLiteral => "empty character literal".to_string(),
+        }
+    }
+}
+
+/// Decodes the escape sequence starting at `slice[*offset]`, which must be a
+/// backslash. On success `offset` is advanced past the sequence.
+pub fn parse_escape_sequence(slice: &[u8], offset: &mut usize) -> Result<u32, Error> {
+    assert!(slice.len() > *offset);
+    assert_eq!(slice[*offset], b'\\');
+
+    if slice.len() == *offset + 1 {
+        return Err(Error::InvalidEscapeCharacter(*offset + 1));
+    }
+
+    *offset += 2;
+    match slice[*offset - 1] {
+        b'n' => Ok('\n' as u32),
+        b'r' => Ok('\r' as u32),
+        b'\\' => Ok('\\' as u32),
+        b't' => Ok('\t' as u32),
+        b'\'' => Ok('\'' as u32),
+        b'"' => Ok('"' as u32),
+        b'x' => {
+            let mut value = 0;
+            let mut i = *offset;
+            while i < *offset + 2 {
+                match slice.get(i).and_then(|&c| hex_digit(c)) {
+                    Some(digit) => value = value * 16 + digit,
+                    None => return Err(Error::ExpectedHexDigit(i)),
+                }
+                i += 1;
+            }
+            *offset = i;
+            Ok(value)
+        }
+        b'u' => {
+            let mut i = *offset;
+            if slice.get(i) != Some(&b'{') {
+                return Err(Error::ExpectedLbrace(i));
+            }
+            i += 1;
+            match slice.get(i) {
+                None => return Err(Error::ExpectedHexDigitOrRbrace(i)),
+                Some(b'}') => return Err(Error::EmptyUnicodeEscapeSequence(i)),
+                Some(_) => {}
+            }
+
+            let mut value: u32 = 0;
+            while let Some(&c) = slice.get(i) {
+                if c == b'}' {
+                    *offset = i + 1;
+                    return Ok(value);
+                }
+                match hex_digit(c) {
+                    Some(digit) => value = value * 16 + digit,
+                    None => return Err(Error::ExpectedHexDigitOrRbrace(i)),
+                }
+                if value > 0x10ffff {
+                    return Err(Error::InvalidUnicodeCodepoint(i));
+                }
+                i += 1;
+            }
+            Err(Error::ExpectedRbrace(i))
+        }
+        _ => Err(Error::InvalidEscapeCharacter(*offset - 1)),
+    }
+}
+
+/// Appends the decoded contents of the string literal `bytes`, including its
+/// quotes, to `out`. On failure `out` holds whatever was decoded before the
+/// error.
+pub fn parse_write(out: &mut Vec<u8>, bytes: &[u8]) -> Result<(), Error> {
+    assert!(bytes.len() >= 2 && bytes[0] == b'"' && bytes[bytes.len() - 1] == b'"');
+
+    let mut index = 1;
+    loop {
+        let Some(&b) = bytes.get(index) else {
+            return Err(Error::UnterminatedString(index));
+        };
+        match b {
+            b'\\' => {
+                let escape_char_index = index + 1;
+                let codepoint = parse_escape_sequence(bytes, &mut index)?;
+                if bytes[escape_char_index] == b'u' {
+                    let c = char::from_u32(codepoint)
+                        .ok_or(Error::SurrogateCodepoint(escape_char_index + 1))?;
+                    out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                } else {
+                    out.push(codepoint as u8);
+                }
+            }
+            b'\n' => return Err(Error::InvalidCharacter(index)),
+            b'"' => return Ok(()),
+            _ => {
+                out.push(b);
+                index += 1;
+            }
+        }
+    }
+}
+
+/// Decodes a string literal token such as `"a\x41\u{1F600}\n"` into bytes.
+pub fn parse(bytes: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity(bytes.len());
+    parse_write(&mut out, bytes)?;
+    Ok(out)
+}
+
+/// The name an identifier token refers to: the token text for a plain
+/// identifier, or the decoded string for the `@"..."` form. Error offsets
+/// are relative to the opening quote, one past the `@`. A decoded name that
+/// is not valid UTF-8, such as `@"\xff"`, is an [`Error::InvalidUtf8`]
+/// rather than being replaced, so distinct names never compare equal.
+pub fn identifier_name<'a>(source: &'a [u8], token: &Token) -> Result<Cow<'a, str>, Error> {
+    assert_eq!(token.tag, Tag::Identifier);
+    let bytes = &source[token.loc.start..token.loc.end];
+    match bytes.strip_prefix(b"@") {
+        Some(quoted) => match String::from_utf8(parse(quoted)?) {
+            Ok(name) => Ok(Cow::Owned(name)),
+            Err(err) => {
+                let valid_up_to = err.utf8_error().valid_up_to();
+                Err(Error::InvalidUtf8(source_offset(quoted, valid_up_to)))
+            }
+        },
+        // The tokenizer only accepts ASCII in plain identifiers.
+        None => Ok(Cow::Borrowed(
+            std::str::from_utf8(bytes).expect("identifier is ASCII"),
+        )),
+    }
+}
+
+/// Offset in the string literal `bytes` of the character or escape sequence
+/// that produced byte `target` of its decoded contents. `bytes` must decode
+/// without error.
+fn source_offset(bytes: &[u8], target: usize) -> usize {
+    let mut index = 1;
+    let mut decoded = 0;
+    loop {
+        let start = index;
+        decoded += if bytes[index] == b'\\' {
+            let is_unicode = bytes[index + 1] == b'u';
+            let codepoint = parse_escape_sequence(bytes, &mut index).expect("literal decodes");
+            if is_unicode {
+                char::from_u32(codepoint).map_or(0, char::len_utf8)
+            } else {
+                1
+            }
+        } else {
+            index += 1;
+            1
+        };
+        if decoded > target {
+            return start;
+        }
+    }
+}
+
+/// Decodes a character literal token such as `'a'`, `'\n'` or `'😀'` into its
+/// code point. As in the compiler, a `\u{...}` escape naming a surrogate is
+/// accepted here because the value is never UTF-8 encoded.
+pub fn parse_char_literal(slice: &[u8]) -> Result<u32, Error> {
+    if slice.len() < 3 {
+        return Err(Error::EmptyCharLiteral);
+    }
+    match slice[1] {
+        b'\\' => {
+            let mut offset = 1;
+            let codepoint = parse_escape_sequence(slice, &mut offset)?;
+            if offset + 1 != slice.len() || slice[offset] != b'\'' {
+                return Err(Error::ExpectedSingleQuote(offset));
+            }
+            Ok(codepoint)
+        }
+        0 => Err(Error::InvalidCharacter(1)),
+        leader => {
+            let inner = &slice[1..slice.len() - 1];
+            let n = match leader {
+                0x00..=0x7f => 1,
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                0xf0..=0xf7 => 4,
+                _ => return Err(Error::InvalidUnicodeCodepoint(1)),
+            };
+            if inner.len() > n {
+                return Err(Error::ExpectedSingleQuote(1 + n));
+            }
+            if slice.last() != Some(&b'\'') {
+                return Err(Error::ExpectedSingleQuote(slice.len() - 1));
+            }
+            match std::str::from_utf8(inner) {
+                Ok(s) if s.len() == n => Ok(s.chars().next().unwrap() as u32),
+                _ => Err(Error::InvalidUnicodeCodepoint(1)),
+            }
+        }
+    }
+}
+
+/// A multiline string literal assembled from its run of consecutive
+/// `Tag::MultilineStringLiteralLine` tokens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MultilineString {
+    /// The string value: every line without its `\\` prefix, joined by `\n`.
+    pub value: Vec<u8>,
+    /// Index of the first line token.
+    pub first_token: usize,
+    /// Span of each line token, from the `\\` up to but excluding the line
+    /// ending, `\r\n` included.
+    pub lines: Vec<Loc>,
+}
+
+/// Assembles the multiline string literal that `index`, any of its line
+/// tokens, belongs to. No escapes are processed, as in the compiler.
+pub fn parse_multiline(source: &[u8], tokens: &TokenList, index: usize) -> MultilineString {
+    let tags = tokens.tags();
+    assert_eq!(tags[index], Tag::MultilineStringLiteralLine);
+
+    let mut first_token = index;
+    while first_token > 0 && tags[first_token - 1] == Tag::MultilineStringLiteralLine {
+        first_token -= 1;
+    }
+
+    let mut value = Vec::new();
+    let mut lines = Vec::new();
+    let line_starts = tokens.starts()[first_token..]
+        .iter()
+        .zip(&tags[first_token..])
+        .take_while(|&(_, tag)| *tag == Tag::MultilineStringLiteralLine)
+        .map(|(&start, _)| start as usize);
+    for start in line_starts {
+        let mut end = source[start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(source.len(), |n| start + n);
+        if end > start && source[end - 1] == b'\r' {
+            end -= 1;
+        }
+        if !lines.is_empty() {
+            value.push(b'\n');
+        }
+        value.extend_from_slice(&source[start + 2..end]);
+        lines.push(Loc { start, end });
+    }
+    MultilineString {
+        value,
+        first_token,
+        lines,
+    }
+}
+
+fn hex_digit(c: u8) -> Option<u32> {
+    (c as char).to_digit(16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zig::tokenizer::{tokenize, TokenStream};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse(br#""""#), Ok(b"".to_vec()));
+        assert_eq!(parse(br#""foo""#), Ok(b"foo".to_vec()));
+        assert_eq!(
+            parse(br#""a\x41\u{1F600}\n""#),
+            Ok("aA\u{1F600}\n".as_bytes().to_vec())
+        );
+        assert_eq!(parse(br#""\t\r\\\'\"\xfF""#), Ok(b"\t\r\\'\"\xff".to_vec()));
+        assert_eq!(
+            parse(br#""\u{0}\u{10ffff}""#),
+            Ok("\0\u{10ffff}".as_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn test_identifier_name() {
+        let source = br#"foo @"type" @"a\x41\n" @"\xff" @"\q" @"\xfe" @"\u{e9}\xff" @"\xc3\xa9""#;
+        let tokens: Vec<Token> = TokenStream::new(source).collect();
+        let names: Vec<_> = tokens
+            .iter()
+            .map(|token| identifier_name(source, token))
+            .collect();
+        assert_eq!(names[0], Ok(Cow::Borrowed("foo")));
+        assert!(matches!(names[0], Ok(Cow::Borrowed(_))));
+        assert_eq!(names[1].as_deref(), Ok("type"));
+        assert_eq!(names[2].as_deref(), Ok("aA\n"));
+        assert_eq!(names[3], Err(Error::InvalidUtf8(1)));
+        assert_eq!(names[4], Err(Error::InvalidEscapeCharacter(2)));
+        assert_eq!(names[5], Err(Error::InvalidUtf8(1)));
+        assert_eq!(names[6], Err(Error::InvalidUtf8(7)));
+        assert_eq!(names[7].as_deref(), Ok("\u{e9}"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(parse(br#""\q""#), Err(Error::InvalidEscapeCharacter(2)));
+        assert_eq!(parse(br#""\x4g""#), Err(Error::ExpectedHexDigit(4)));
+        assert_eq!(parse(br#""\x4""#), Err(Error::ExpectedHexDigit(4)));
+        assert_eq!(parse(br#""\u41""#), Err(Error::ExpectedLbrace(3)));
+        assert_eq!(
+            parse(br#""\u{}""#),
+            Err(Error::EmptyUnicodeEscapeSequence(4))
+        );
+        assert_eq!(
+            parse(br#""\u{4x}""#),
+            Err(Error::ExpectedHexDigitOrRbrace(5))
+        );
+        assert_eq!(
+            parse(br#""\u{110000}""#),
+            Err(Error::InvalidUnicodeCodepoint(9))
+        );
+        assert_eq!(parse(br#""\u{d800}""#), Err(Error::SurrogateCodepoint(3)));
+        assert_eq!(
+            parse(b"\"\\u{41\""),
+            Err(Error::ExpectedHexDigitOrRbrace(6))
+        );
+        assert_eq!(parse(b"\"a\nb\""), Err(Error::InvalidCharacter(2)));
+        assert_eq!(parse(b"\"\\\""), Err(Error::UnterminatedString(3)));
+        assert_eq!(parse(b"\"a\\\""), Err(Error::UnterminatedString(4)));
+
+        let bytes = br#""\q""#;
+        assert_eq!(
+            parse(bytes).unwrap_err().message(bytes),
+            "invalid escape character: 'q'"
+        );
+    }
+
+    #[test]
+    fn test_parse_char_literal() {
+        assert_eq!(parse_char_literal(b"'a'"), Ok('a' as u32));
+        assert_eq!(parse_char_literal(br"'\n'"), Ok('\n' as u32));
+        assert_eq!(parse_char_literal(br"'\''"), Ok('\'' as u32));
+        assert_eq!(parse_char_literal(br"'\xff'"), Ok(0xff));
+        assert_eq!(parse_char_literal(br"'\u{1F600}'"), Ok(0x1f600));
+        assert_eq!(parse_char_literal("'ä'".as_bytes()), Ok('ä' as u32));
+        assert_eq!(parse_char_literal("'😀'".as_bytes()), Ok(0x1f600));
+    }
+
+    #[test]
+    fn test_parse_char_literal_errors() {
+        assert_eq!(parse_char_literal(b"''"), Err(Error::EmptyCharLiteral));
+        assert_eq!(
+            parse_char_literal(b"'ab'"),
+            Err(Error::ExpectedSingleQuote(2))
+        );
+        assert_eq!(
+            parse_char_literal("'äb'".as_bytes()),
+            Err(Error::ExpectedSingleQuote(3))
+        );
+        assert_eq!(
+            parse_char_literal(b"'ab"),
+            Err(Error::ExpectedSingleQuote(2))
+        );
+        assert_eq!(
+            parse_char_literal("'ä".as_bytes()),
+            Err(Error::ExpectedSingleQuote(2))
+        );
+        assert_eq!(
+            parse_char_literal(br"'\nb'"),
+            Err(Error::ExpectedSingleQuote(3))
+        );
+        assert_eq!(
+            parse_char_literal(br"'\q'"),
+            Err(Error::InvalidEscapeCharacter(2))
+        );
+        assert_eq!(
+            parse_char_literal(b"'\xff'"),
+            Err(Error::InvalidUnicodeCodepoint(1))
+        );
+        // Overlong encoding and an encoded surrogate.
+        assert_eq!(
+            parse_char_literal(b"'\xc0\x80'"),
+            Err(Error::InvalidUnicodeCodepoint(1))
+        );
+        assert_eq!(
+            parse_char_literal(b"'\xed\xa0\x80'"),
+            Err(Error::InvalidUnicodeCodepoint(1))
+        );
+        assert_eq!(parse_char_literal(b"'\0'"), Err(Error::InvalidCharacter(1)));
+    }
+
+    #[test]
+    fn test_char_literal_tokens() {
+        let source = br"'\'' '\\' 'x'";
+        let values: Vec<_> = TokenStream::new(source)
+            .inspect(|token| assert_eq!(token.tag, Tag::CharLiteral))
+            .map(|token| parse_char_literal(&source[token.loc.start..token.loc.end]))
+            .collect();
+        assert_eq!(values, [Ok('\'' as u32), Ok('\\' as u32), Ok('x' as u32)]);
+    }
+
+    #[test]
+    fn test_parse_multiline() {
+        let source =
+            b"const s =\n    \\\\first\r\n    \\\\ second \\x41\n    \\\\\n    \\\\last\n;";
+        let tokens = tokenize(source).unwrap();
+        let string = parse_multiline(source, &tokens, 5);
+        assert_eq!(string.value, b"first\n second \\x41\n\nlast");
+        assert_eq!(string.first_token, 3);
+        assert_eq!(string.lines.len(), 4);
+        assert_eq!(
+            &source[string.lines[0].start..string.lines[0].end],
+            b"\\\\first"
+        );
+        assert_eq!(*tokens.tag(7), Tag::Semicolon);
+        assert_eq!(parse_multiline(source, &tokens, 3), string);
+
+        let source = b"\\\\eof";
+        assert_eq!(
+            parse_multiline(source, &tokenize(source).unwrap(), 0).value,
+            b"eof"
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use std::io::{self, Write};
+
+use crate::zig::line_index::LineIndex;
+use crate::zig::tokenizer::Token;
+
+/// Layout of a token dump. Both write one object per token,
+/// `{"tag":"keyword_fn","start":0,"end":2}`, with the tag named as in
+/// upstream's `std.zig.Token.Tag` and byte offsets into the source. Keys
+/// always appear in that order and objects contain no whitespace, so dumps
+/// can be compared with a plain `diff`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A single array, one token per line.
+    Json,
+    /// Newline-delimited JSON: one object per line, no enclosing array.
+    Ndjson,
+}
+
+/// Writes `tokens` to `out` in the given format. The iterator of a
+/// [`TokenStream`](crate::zig::tokenizer::TokenStream) stops before `eof`;
+/// chain the final token on when comparing against a dump that includes it.
+pub fn write_tokens<W: Write>(
+    out: W,
+    tokens: impl IntoIterator<Item = Token>,
+    format: Format,
+) -> io::Result<()> {
+    write_dump(out, tokens, format, None)
+}
+
+/// Like [`write_tokens`], but each object also gets one-based `"line"` and
+/// `"column"` keys for the token's start, with byte columns as in
+/// [`Location`](crate::zig::line_index::Location)'s `Display`.
+pub fn write_tokens_with_lines<W: Write>(
+    out: W,
+    tokens: impl IntoIterator<Item = Token>,
+    format: Format,
+    lines: &LineIndex,
+) -> io::Result<()> {
+    write_dump(out, tokens, format, Some(lines))
+}
+
+fn write_dump<W: Write>(
+    mut out: W,
+    tokens: impl IntoIterator<Item = Token>,
+    format: Format,
+    lines: Option<&LineIndex>,
+) -> io::Result<()> {
+    let mut first = true;
+    if format == Format::Json {
+        out.write_all(b"[")?;
+    }
+    for token in tokens {
+        match format {
+            Format::Json if first => out.write_all(b"\n")?,
+            Format::Json => out.write_all(b",\n")?,
+            Format::Ndjson => {}
+        }
+        match lines {
+            Some(lines) => {
+                let location = lines.location(token.loc.start);
+                write!(
+                    out,
+                    r#"{{"tag":"{}","start":{},"end":{},"line":{},"column":{}}}"#,
+                    token.tag.name(),
+                    token.loc.start,
+                    token.loc.end,
+                    location.line + 1,
+                    location.column + 1
+                )?;
+            }
+            None => write_token(&mut out, &token)?,
+        }
+        if format == Format::Ndjson {
+            out.write_all(b"\n")?;
+        }
+        first = false;
+    }
+    match format {
+        Format::Json if first => out.write_all(b"]\n"),
+        Format::Json => out.write_all(b"\n]\n"),
+        Format::Ndjson => Ok(()),
+    }
+}
+
+/// Writes a single token object without a trailing newline. Tag names are
+/// plain ASCII identifiers, so nothing needs escaping.
+pub fn write_token<W: Write>(mut out: W, token: &Token) -> io::Result<()> {
+    write!(
+        out,
+        r#"{{"tag":"{}","start":{},"end":{}}}"#,
+        token.tag.name(),
+        token.loc.start,
+        token.loc.end
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zig::tokenizer::{Tag, TokenStream};
+
+    fn dump(source: &[u8], format: Format) -> String {
+        let mut out = Vec::new();
+        write_tokens(&mut out, TokenStream::new(source), format).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_formats() {
+        let source = b"fn f() u8";
+        assert_eq!(
+            dump(source, Format::Ndjson),
+            concat!(
+                "{\"tag\":\"keyword_fn\",\"start\":0,\"end\":2}\n",
+                "{\"tag\":\"identifier\",\"start\":3,\"end\":4}\n",
+                "{\"tag\":\"l_paren\",\"start\":4,\"end\":5}\n",
+                "{\"tag\":\"r_paren\",\"start\":5,\"end\":6}\n",
+                "{\"tag\":\"identifier\",\"start\":7,\"end\":9}\n",
+            )
+        );
+        assert_eq!(
+            dump(b"a[", Format::Json),
+            concat!(
+                "[\n",
+                "{\"tag\":\"identifier\",\"start\":0,\"end\":1},\n",
+                "{\"tag\":\"l_bracket\",\"start\":1,\"end\":2}\n",
+                "]\n",
+            )
+        );
+        assert_eq!(dump(b"", Format::Json), "[]\n");
+        assert_eq!(dump(b"", Format::Ndjson), "");
+    }
+
+    #[test]
+    fn test_with_lines() {
+        let source = b"a\n  b";
+        let mut out = Vec::new();
+        let lines = LineIndex::new(source);
+        write_tokens_with_lines(&mut out, TokenStream::new(source), Format::Ndjson, &lines)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "{\"tag\":\"identifier\",\"start\":0,\"end\":1,\"line\":1,\"column\":1}\n",
+                "{\"tag\":\"identifier\",\"start\":4,\"end\":5,\"line\":2,\"column\":3}\n",
+            )
+        );
+    }
+
+    #[test]
+    fn test_tag_names() {
+        for tag in Tag::ALL {
+            let name = tag.name();
+            assert!(name
+                .bytes()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == b'_'));
+            assert_eq!(Tag::from_name(name).as_ref(), Some(tag));
+        }
+        assert_eq!(
+            Tag::AngleBrackAngleBrackLeftPipeEqual.name(),
+            "angle_bracket_angle_bracket_left_pipe_equal"
+        );
+        assert_eq!(Tag::KWUsingnamespace.name(), "keyword_usingnamespace");
+        assert_eq!(Tag::from_name("KWFn"), None);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_tag() {
+        use serde::de::{value::StrDeserializer, Deserialize, IntoDeserializer};
+
+        let de: StrDeserializer<'_, serde::de::value::Error> = "keyword_fn".into_deserializer();
+        assert_eq!(Tag::deserialize(de).unwrap(), Tag::KWFn);
+        let de: StrDeserializer<'_, serde::de::value::Error> = "fn".into_deserializer();
+        assert!(Tag::deserialize(de).is_err());
+    }
+}
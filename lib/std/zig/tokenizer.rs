@@ -1,347 +1,366 @@
 pub struct Tokenizer;
+use std::fmt;
+use std::io::{self, Read};
+use std::ops::Range;
+
 use phf::phf_map;
 
+use crate::zig::language_version::LanguageVersion;
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Token {
     pub tag: Tag,
+    /// Flattened, so a token serializes as `{"tag":..,"start":..,"end":..}`
+    /// like the [`token_dump`](crate::zig::token_dump) format.
+    #[cfg_attr(feature = "serde", serde(flatten))]
     pub loc: Loc,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Loc {
     pub start: usize,
     pub end: usize,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum Tag {
+impl Loc {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Declares [`Tag`] from a single table, along with the per-tag metadata,
+/// so none of them can drift apart. Each row is the
+/// variant, its upstream name, its [`Category`], then either the fixed
+/// `lexeme` or the `symbol` used in diagnostics, and an optional binary
+/// operator `precedence`.
+macro_rules! define_tags {
+    (@option) => {
+        None
+    };
+    (@option $value:literal) => {
+        Some($value)
+    };
+    ($(
+        $(#[$attr:meta])*
+        $variant:ident $name:literal $category:ident
+        $(lexeme $lexeme:literal)?
+        $(symbol $symbol:literal)?
+        $(precedence $precedence:literal)?,
+    )*) => {
+        /// Token kinds, in the same order as upstream's `std.zig.Token.Tag`
+        /// so `tag as u8` matches upstream. The trivia tags, which upstream
+        /// lacks, come last.
+        #[repr(u8)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Tag {
+            $($(#[$attr])* $variant,)*
+        }
+
+        impl Tag {
+            /// Every tag, in declaration order.
+            pub const ALL: &'static [Tag] = &[$(Tag::$variant,)*];
+
+            pub fn lexeme(&self) -> Option<&'static str> {
+                match self {
+                    $(Tag::$variant => define_tags!(@option $($lexeme)?),)*
+                }
+            }
+
+            /// The lexeme, or a description for tags without one, as used
+            /// in diagnostics.
+            pub fn symbol(&self) -> &'static str {
+                match self {
+                    $(Tag::$variant => $($lexeme)? $($symbol)?,)*
+                }
+            }
+
+            /// The tag's name in upstream `std.zig.Token.Tag`, such as
+            /// `keyword_fn` or `l_paren`. Trivia tags have no upstream
+            /// counterpart and use `whitespace`, `newline` and
+            /// `line_comment`.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(Tag::$variant => $name,)*
+                }
+            }
+
+            pub fn category(&self) -> Category {
+                match self {
+                    $(Tag::$variant => Category::$category,)*
+                }
+            }
+
+            /// Binding power of the tag as an infix operator, from upstream
+            /// `Parse.zig`'s `operTable`; higher binds tighter. Comparisons
+            /// (precedence 30) do not chain. `None` for tags that are not
+            /// binary operators.
+            pub fn binary_precedence(&self) -> Option<u8> {
+                match self {
+                    $(Tag::$variant => define_tags!(@option $($precedence)?),)*
+                }
+            }
+        }
+    };
+}
+
+define_tags! {
+    Invalid "invalid" Invalid symbol "invalid token",
+    InvalidPeriodAsterisks "invalid_periodasterisks" Invalid lexeme ".**",
+    Identifier "identifier" Identifier symbol "an identifier",
+    StringLiteral "string_literal" Literal symbol "a string literal",
+    MultilineStringLiteralLine "multiline_string_literal_line" Literal symbol "a string literal",
+    CharLiteral "char_literal" Literal symbol "a character literal",
+    Eof "eof" Eof symbol "EOF",
+    Builtin "builtin" Builtin symbol "a builtin function",
+    Bang "bang" Operator lexeme "!",
+    Pipe "pipe" Operator lexeme "|" precedence 40,
+    PipePipe "pipe_pipe" Operator lexeme "||" precedence 70,
+    PipeEqual "pipe_equal" Assignment lexeme "|=",
+    Equal "equal" Assignment lexeme "=",
+    EqualEqual "equal_equal" Operator lexeme "==" precedence 30,
+    EqualAngleBrackRight "equal_angle_bracket_right" Punctuation lexeme "=>",
+    BangEqual "bang_equal" Operator lexeme "!=" precedence 30,
+    LParen "l_paren" Punctuation lexeme "(",
+    RParen "r_paren" Punctuation lexeme ")",
+    Semicolon "semicolon" Punctuation lexeme ";",
+    Percent "percent" Operator lexeme "%" precedence 70,
+    PercentEqual "percent_equal" Assignment lexeme "%=",
+    LBrace "l_brace" Punctuation lexeme "{",
+    RBrace "r_brace" Punctuation lexeme "}",
+    LBrack "l_bracket" Punctuation lexeme "[",
+    RBrack "r_bracket" Punctuation lexeme "]",
+    Period "period" Punctuation lexeme ".",
+    PeriodAsterisk "period_asterisk" Operator lexeme ".*",
+    Ellipsis2 "ellipsis2" Punctuation lexeme "..",
+    Ellipsis3 "ellipsis3" Punctuation lexeme "...",
+    Caret "caret" Operator lexeme "^" precedence 40,
+    CaretEqual "caret_equal" Assignment lexeme "^=",
+    Plus "plus" Operator lexeme "+" precedence 60,
+    PlusPlus "plus_plus" Operator lexeme "++" precedence 60,
+    PlusEqual "plus_equal" Assignment lexeme "+=",
+    PlusPercent "plus_percent" Operator lexeme "+%" precedence 60,
+    PlusPercentEqual "plus_percent_equal" Assignment lexeme "+%=",
+    PlusPipe "plus_pipe" Operator lexeme "+|" precedence 60,
+    PlusPipeEqual "plus_pipe_equal" Assignment lexeme "+|=",
+    Minus "minus" Operator lexeme "-" precedence 60,
+    MinusEqual "minus_equal" Assignment lexeme "-=",
+    MinusPercent "minus_percent" Operator lexeme "-%" precedence 60,
+    MinusPercentEqual "minus_percent_equal" Assignment lexeme "-%=",
+    MinusPipe "minus_pipe" Operator lexeme "-|" precedence 60,
+    MinusPipeEqual "minus_pipe_equal" Assignment lexeme "-|=",
+    Asterisk "asterisk" Operator lexeme "*" precedence 70,
+    AsteriskEqual "asterisk_equal" Assignment lexeme "*=",
+    AsteriskAsterisk "asterisk_asterisk" Operator lexeme "**" precedence 70,
+    AsteriskPercent "asterisk_percent" Operator lexeme "*%" precedence 70,
+    AsteriskPercentEqual "asterisk_percent_equal" Assignment lexeme "*%=",
+    AsteriskPipe "asterisk_pipe" Operator lexeme "*|" precedence 70,
+    AsteriskPipeEqual "asterisk_pipe_equal" Assignment lexeme "*|=",
+    Arrow "arrow" Punctuation lexeme "->",
+    Colon "colon" Punctuation lexeme ":",
+    Slash "slash" Operator lexeme "/" precedence 70,
+    SlashEqual "slash_equal" Assignment lexeme "/=",
+    Comma "comma" Punctuation lexeme ",",
+    Ampersand "ampersand" Operator lexeme "&" precedence 40,
+    AmpersandEqual "ampersand_equal" Assignment lexeme "&=",
+    QuestionMark "question_mark" Operator lexeme "?",
+    AngleBrackLeft "angle_bracket_left" Operator lexeme "<" precedence 30,
+    AngleBrackLeftEqual "angle_bracket_left_equal" Operator lexeme "<=" precedence 30,
+    AngleBrackAngleBrackLeft "angle_bracket_angle_bracket_left" Operator lexeme "<<" precedence 50,
+    AngleBrackAngleBrackLeftEqual "angle_bracket_angle_bracket_left_equal" Assignment lexeme "<<=",
+    AngleBrackAngleBrackLeftPipe "angle_bracket_angle_bracket_left_pipe" Operator lexeme "<<|" precedence 50,
+    AngleBrackAngleBrackLeftPipeEqual "angle_bracket_angle_bracket_left_pipe_equal" Assignment lexeme "<<|=",
+    AngleBrackRight "angle_bracket_right" Operator lexeme ">" precedence 30,
+    AngleBrackRightEqual "angle_bracket_right_equal" Operator lexeme ">=" precedence 30,
+    AngleBrackAngleBrackRight "angle_bracket_angle_bracket_right" Operator lexeme ">>" precedence 50,
+    AngleBrackAngleBrackRightEqual "angle_bracket_angle_bracket_right_equal" Assignment lexeme ">>=",
+    Tilde "tilde" Operator lexeme "~",
+    NumberLiteral "number_literal" Literal symbol "a number literal",
+    DocComment "doc_comment" DocComment symbol "a document comment",
+    ContainerDocComment "container_doc_comment" DocComment symbol "a document comment",
+    KWAddrspace "keyword_addrspace" Keyword lexeme "addrspace",
+    KWAlign "keyword_align" Keyword lexeme "align",
+    KWAllowzero "keyword_allowzero" Keyword lexeme "allowzero",
+    KWAnd "keyword_and" Keyword lexeme "and" precedence 20,
+    KWAnyframe "keyword_anyframe" Keyword lexeme "anyframe",
+    KWAnytype "keyword_anytype" Keyword lexeme "anytype",
+    KWAsm "keyword_asm" Keyword lexeme "asm",
+    KWAsync "keyword_async" Keyword lexeme "async",
+    KWAwait "keyword_await" Keyword lexeme "await",
+    KWBreak "keyword_break" Keyword lexeme "break",
+    KWCallconv "keyword_callconv" Keyword lexeme "callconv",
+    KWCatch "keyword_catch" Keyword lexeme "catch" precedence 40,
+    KWComptime "keyword_comptime" Keyword lexeme "comptime",
+    KWConst "keyword_const" Keyword lexeme "const",
+    KWContinue "keyword_continue" Keyword lexeme "continue",
+    KWDefer "keyword_defer" Keyword lexeme "defer",
+    KWElse "keyword_else" Keyword lexeme "else",
+    KWEnum "keyword_enum" Keyword lexeme "enum",
+    KWErrdefer "keyword_errdefer" Keyword lexeme "errdefer",
+    KWError "keyword_error" Keyword lexeme "error",
+    KWExport "keyword_export" Keyword lexeme "export",
+    KWExtern "keyword_extern" Keyword lexeme "extern",
+    KWFn "keyword_fn" Keyword lexeme "fn",
+    KWFor "keyword_for" Keyword lexeme "for",
+    KWIf "keyword_if" Keyword lexeme "if",
+    KWInline "keyword_inline" Keyword lexeme "inline",
+    KWNoalias "keyword_noalias" Keyword lexeme "noalias",
+    KWNoinline "keyword_noinline" Keyword lexeme "noinline",
+    KWNosuspend "keyword_nosuspend" Keyword lexeme "nosuspend",
+    KWOpaque "keyword_opaque" Keyword lexeme "opaque",
+    KWOr "keyword_or" Keyword lexeme "or" precedence 10,
+    KWOrelse "keyword_orelse" Keyword lexeme "orelse" precedence 40,
+    KWPacked "keyword_packed" Keyword lexeme "packed",
+    KWPub "keyword_pub" Keyword lexeme "pub",
+    KWResume "keyword_resume" Keyword lexeme "resume",
+    KWReturn "keyword_return" Keyword lexeme "return",
+    KWLinksection "keyword_linksection" Keyword lexeme "linksection",
+    KWStruct "keyword_struct" Keyword lexeme "struct",
+    KWSuspend "keyword_suspend" Keyword lexeme "suspend",
+    KWSwitch "keyword_switch" Keyword lexeme "switch",
+    KWTest "keyword_test" Keyword lexeme "test",
+    KWThreadlocal "keyword_threadlocal" Keyword lexeme "threadlocal",
+    KWTry "keyword_try" Keyword lexeme "try",
+    KWUnion "keyword_union" Keyword lexeme "union",
+    KWUnreachable "keyword_unreachable" Keyword lexeme "unreachable",
+    KWUsingnamespace "keyword_usingnamespace" Keyword lexeme "usingnamespace",
+    KWVar "keyword_var" Keyword lexeme "var",
+    KWVolatile "keyword_volatile" Keyword lexeme "volatile",
+    KWWhile "keyword_while" Keyword lexeme "while",
+    /// Trivia, only produced by [`TokenStream::with_trivia`]: a run of
+    /// spaces, tabs and carriage returns not followed by `\n`.
+    Whitespace "whitespace" Trivia symbol "whitespace",
+    /// Trivia: a single `\n` or `\r\n`.
+    Newline "newline" Trivia symbol "a newline",
+    /// Trivia: a plain `//` comment, excluding the line ending.
+    LineComment "line_comment" Trivia symbol "a comment",
+}
+
+/// Coarse classification of a [`Tag`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category {
     Invalid,
-    InvalidPeriodAsterisks,
     Identifier,
-    StringLiteral,
-    MultilineStringLiteralLine,
-    CharLiteral,
-    Eof,
+    /// String, character, multiline string and number literals.
+    Literal,
     Builtin,
-    Bang,
-    Pipe,
-    PipePipe,
-    PipeEqual,
-    Equal,
-    EqualEqual,
-    EqualAngleBrackRight,
-    BangEqual,
-    LParen,
-    RParen,
-    Semicolon,
-    Percent,
-    PercentEqual,
-    LBrace,
-    RBrace,
-    LBrack,
-    RBrack,
-    Period,
-    PeriodAsterisk,
-    Ellipsis2,
-    Ellipsis3,
-    Caret,
-    CaretEqual,
-    Plus,
-    PlusPlus,
-    PlusEqual,
-    PlusPercent,
-    PlusPercentEqual,
-    PlusPipe,
-    PlusPipeEqual,
-    Minus,
-    MinusEqual,
-    MinusPercent,
-    MinusPercentEqual,
-    MinusPipe,
-    MinusPipeEqual,
-    Asterisk,
-    AsteriskEqual,
-    AsteriskAsterisk,
-    AsteriskPercent,
-    AsteriskPercentEqual,
-    AsteriskPipe,
-    AsteriskPipeEqual,
-    Arrow,
-    Colon,
-    Slash,
-    SlashEqual,
-    Comma,
-    Ampersand,
-    AmpersandEqual,
-    QuestionMark,
-    AngleBrackLeft,
-    AngleBrackLeftEqual,
-    AngleBrackAngleBrackLeft,
-    AngleBrackAngleBrackLeftEqual,
-    AngleBrackAngleBrackLeftPipe,
-    AngleBrackAngleBrackLeftPipeEqual,
-    AngleBrackRight,
-    AngleBrackRightEqual,
-    AngleBrackAngleBrackRight,
-    AngleBrackAngleBrackRightEqual,
-    Tilde,
-    NumberLiteral,
+    Keyword,
+    /// Operators other than assignments, including prefix and postfix ones
+    /// such as `!`, `~`, `?` and `.*`.
+    Operator,
+    /// `=` and the compound assignments such as `+%=`.
+    Assignment,
+    /// Delimiters and separators: brackets, `;`, `,`, `:`, `.`, `..`,
+    /// `...`, `->` and `=>`.
+    Punctuation,
     DocComment,
-    ContainerDocComment,
-    KWAddrspace,
-    KWAlign,
-    KWAllowzero,
-    KWAnd,
-    KWAnyframe,
-    KWAnytype,
-    KWAsm,
-    KWAsync,
-    KWAwait,
-    KWBreak,
-    KWCallconv,
-    KWCatch,
-    KWComptime,
-    KWConst,
-    KWContinue,
-    KWDefer,
-    KWElse,
-    KWEnum,
-    KWErrdefer,
-    KWError,
-    KWExport,
-    KWExtern,
-    KWFn,
-    KWFor,
-    KWIf,
-    KWInline,
-    KWNoalias,
-    KWNoinline,
-    KWNosuspend,
-    KWOpaque,
-    KWOr,
-    KWOrelse,
-    KWPacked,
-    KWPub,
-    KWResume,
-    KWReturn,
-    KWLinksection,
-    KWStruct,
-    KWSuspend,
-    KWSwitch,
-    KWTest,
-    KWThreadlocal,
-    KWTry,
-    KWUnion,
-    KWUnreachable,
-    KWUsingnamespace,
-    KWVar,
-    KWVolatile,
-    KWWhile,
+    Trivia,
+    Eof,
 }
 
-static TAGS: phf::Map<&'static str, Tag> = phf_map! {
-    "addrspace" => Tag::KWAddrspace,
-    "align" => Tag::KWAlign,
-    "allowzero" => Tag::KWAllowzero,
-    "and" => Tag::KWAnd,
-    "anyframe" => Tag::KWAnyframe,
-    "anytype" => Tag::KWAnytype,
-    "asm" => Tag::KWAsm,
-    "async" => Tag::KWAsync,
-    "await" => Tag::KWAwait,
-    "break" => Tag::KWBreak,
-    "callconv" => Tag::KWCallconv,
-    "catch" => Tag::KWCatch,
-    "comptime" => Tag::KWComptime,
-    "const" => Tag::KWConst,
-    "continue" => Tag::KWContinue,
-    "defer" => Tag::KWDefer,
-    "else" => Tag::KWElse,
-    "enum" => Tag::KWEnum,
-    "errdefer" => Tag::KWErrdefer,
-    "error" => Tag::KWError,
-    "export" => Tag::KWExport,
-    "extern" => Tag::KWExtern,
-    "fn" => Tag::KWFn,
-    "for" => Tag::KWFor,
-    "if" => Tag::KWIf,
-    "inline" => Tag::KWInline,
-    "noalias" => Tag::KWNoalias,
-    "noinline" => Tag::KWNoinline,
-    "nosuspend" => Tag::KWNosuspend,
-    "opaque" => Tag::KWOpaque,
-    "or" => Tag::KWOr,
-    "orelse" => Tag::KWOrelse,
-    "packed" => Tag::KWPacked,
-    "pub" => Tag::KWPub,
-    "resume" => Tag::KWResume,
-    "return" => Tag::KWReturn,
-    "linksection" => Tag::KWLinksection,
-    "struct" => Tag::KWStruct,
-    "suspend" => Tag::KWSuspend,
-    "switch" => Tag::KWSwitch,
-    "test" => Tag::KWTest,
-    "threadlocal" => Tag::KWThreadlocal,
-    "try" => Tag::KWTry,
-    "union" => Tag::KWUnion,
-    "unreachable" => Tag::KWUnreachable,
-    "usingnamespace" => Tag::KWUsingnamespace,
-    "var" => Tag::KWVar,
-    "volatile" => Tag::KWVolatile,
-    "while" => Tag::KWWhile,
-
-
+/// Every keyword, keyed by its bytes as in upstream's `Token.keywords`, so
+/// identifiers are looked up without UTF-8 validation.
+static KEYWORDS: phf::Map<&'static [u8], Tag> = phf_map! {
+    b"addrspace" => Tag::KWAddrspace,
+    b"align" => Tag::KWAlign,
+    b"allowzero" => Tag::KWAllowzero,
+    b"and" => Tag::KWAnd,
+    b"anyframe" => Tag::KWAnyframe,
+    b"anytype" => Tag::KWAnytype,
+    b"asm" => Tag::KWAsm,
+    b"async" => Tag::KWAsync,
+    b"await" => Tag::KWAwait,
+    b"break" => Tag::KWBreak,
+    b"callconv" => Tag::KWCallconv,
+    b"catch" => Tag::KWCatch,
+    b"comptime" => Tag::KWComptime,
+    b"const" => Tag::KWConst,
+    b"continue" => Tag::KWContinue,
+    b"defer" => Tag::KWDefer,
+    b"else" => Tag::KWElse,
+    b"enum" => Tag::KWEnum,
+    b"errdefer" => Tag::KWErrdefer,
+    b"error" => Tag::KWError,
+    b"export" => Tag::KWExport,
+    b"extern" => Tag::KWExtern,
+    b"fn" => Tag::KWFn,
+    b"for" => Tag::KWFor,
+    b"if" => Tag::KWIf,
+    b"inline" => Tag::KWInline,
+    b"noalias" => Tag::KWNoalias,
+    b"noinline" => Tag::KWNoinline,
+    b"nosuspend" => Tag::KWNosuspend,
+    b"opaque" => Tag::KWOpaque,
+    b"or" => Tag::KWOr,
+    b"orelse" => Tag::KWOrelse,
+    b"packed" => Tag::KWPacked,
+    b"pub" => Tag::KWPub,
+    b"resume" => Tag::KWResume,
+    b"return" => Tag::KWReturn,
+    b"linksection" => Tag::KWLinksection,
+    b"struct" => Tag::KWStruct,
+    b"suspend" => Tag::KWSuspend,
+    b"switch" => Tag::KWSwitch,
+    b"test" => Tag::KWTest,
+    b"threadlocal" => Tag::KWThreadlocal,
+    b"try" => Tag::KWTry,
+    b"union" => Tag::KWUnion,
+    b"unreachable" => Tag::KWUnreachable,
+    b"usingnamespace" => Tag::KWUsingnamespace,
+    b"var" => Tag::KWVar,
+    b"volatile" => Tag::KWVolatile,
+    b"while" => Tag::KWWhile,
 };
 
 pub fn parse_keyword(tag: &[u8]) -> Option<Tag> {
-    if let Ok(as_str) = std::str::from_utf8(tag) {
-        TAGS.get(as_str).cloned()
-    } else {
-        None
+    // Every keyword is 2 to 14 lowercase letters; most identifiers can be
+    // rejected without hashing.
+    if !(2..=14).contains(&tag.len()) || !tag[0].is_ascii_lowercase() {
+        return None;
     }
+    KEYWORDS.get(tag).copied()
 }
 
 impl Tag {
-    pub fn lexeme(&self) -> Option<&'static str> {
-        match self {
-            Tag::Invalid
-            | Tag::Identifier
-            | Tag::StringLiteral
-            | Tag::MultilineStringLiteralLine
-            | Tag::CharLiteral
-            | Tag::Eof
-            | Tag::Builtin
-            | Tag::NumberLiteral
-            | Tag::DocComment
-            | Tag::ContainerDocComment => None,
-
-            Tag::InvalidPeriodAsterisks => Some(".**"),
-            Tag::Bang => Some("!"),
-            Tag::Pipe => Some("|"),
-            Tag::PipePipe => Some("||"),
-            Tag::PipeEqual => Some("|="),
-            Tag::Equal => Some("="),
-            Tag::EqualEqual => Some("=="),
-            Tag::EqualAngleBrackRight => Some("=>"),
-            Tag::BangEqual => Some("!="),
-            Tag::LParen => Some("("),
-            Tag::RParen => Some(")"),
-            Tag::Semicolon => Some(";"),
-            Tag::Percent => Some("%"),
-            Tag::PercentEqual => Some("%="),
-            Tag::LBrace => Some("{"),
-            Tag::RBrace => Some("}"),
-            Tag::LBrack => Some("["),
-            Tag::RBrack => Some("]"),
-            Tag::Period => Some("."),
-            Tag::PeriodAsterisk => Some(".*"),
-            Tag::Ellipsis2 => Some(".."),
-            Tag::Ellipsis3 => Some("..."),
-            Tag::Caret => Some("^"),
-            Tag::CaretEqual => Some("^="),
-            Tag::Plus => Some("+"),
-            Tag::PlusPlus => Some("++"),
-            Tag::PlusEqual => Some("+="),
-            Tag::PlusPercent => Some("+%"),
-            Tag::PlusPercentEqual => Some("+%="),
-            Tag::PlusPipe => Some("+|"),
-            Tag::PlusPipeEqual => Some("+|="),
-            Tag::Minus => Some("-"),
-            Tag::MinusEqual => Some("-="),
-            Tag::MinusPercent => Some("-%"),
-            Tag::MinusPercentEqual => Some("-%="),
-            Tag::MinusPipe => Some("-|"),
-            Tag::MinusPipeEqual => Some("-|="),
-            Tag::Asterisk => Some("*"),
-            Tag::AsteriskEqual => Some("*="),
-            Tag::AsteriskAsterisk => Some("**"),
-            Tag::AsteriskPercent => Some("*%"),
-            Tag::AsteriskPercentEqual => Some("*%="),
-            Tag::AsteriskPipe => Some("*|"),
-            Tag::AsteriskPipeEqual => Some("*|="),
-            Tag::Arrow => Some("->"),
-            Tag::Colon => Some(":"),
-            Tag::Slash => Some("/"),
-            Tag::SlashEqual => Some("/="),
-            Tag::Comma => Some(","),
-            Tag::Ampersand => Some("&"),
-            Tag::AmpersandEqual => Some("&="),
-            Tag::QuestionMark => Some("?"),
-            Tag::AngleBrackLeft => Some("<"),
-            Tag::AngleBrackLeftEqual => Some("<="),
-            Tag::AngleBrackAngleBrackLeft => Some("<<"),
-            Tag::AngleBrackAngleBrackLeftEqual => Some("<<="),
-            Tag::AngleBrackAngleBrackLeftPipe => Some("<<|"),
-            Tag::AngleBrackAngleBrackLeftPipeEqual => Some("<<|="),
-            Tag::AngleBrackRight => Some(">"),
-            Tag::AngleBrackRightEqual => Some(">="),
-            Tag::AngleBrackAngleBrackRight => Some(">>"),
-            Tag::AngleBrackAngleBrackRightEqual => Some(">>="),
-            Tag::Tilde => Some("~"),
-            Tag::KWAddrspace => Some("addrspace"),
-            Tag::KWAlign => Some("align"),
-            Tag::KWAllowzero => Some("allowzero"),
-            Tag::KWAnd => Some("and"),
-            Tag::KWAnyframe => Some("anyframe"),
-            Tag::KWAnytype => Some("anytype"),
-            Tag::KWAsm => Some("asm"),
-            Tag::KWAsync => Some("async"),
-            Tag::KWAwait => Some("await"),
-            Tag::KWBreak => Some("break"),
-            Tag::KWCallconv => Some("callconv"),
-            Tag::KWCatch => Some("catch"),
-            Tag::KWComptime => Some("comptime"),
-            Tag::KWConst => Some("const"),
-            Tag::KWContinue => Some("continue"),
-            Tag::KWDefer => Some("defer"),
-            Tag::KWElse => Some("else"),
-            Tag::KWEnum => Some("enum"),
-            Tag::KWErrdefer => Some("errdefer"),
-            Tag::KWError => Some("error"),
-            Tag::KWExport => Some("export"),
-            Tag::KWExtern => Some("extern"),
-            Tag::KWFn => Some("fn"),
-            Tag::KWFor => Some("for"),
-            Tag::KWIf => Some("if"),
-            Tag::KWInline => Some("inline"),
-            Tag::KWNoalias => Some("noalias"),
-            Tag::KWNoinline => Some("noinline"),
-            Tag::KWNosuspend => Some("nosuspend"),
-            Tag::KWOpaque => Some("opaque"),
-            Tag::KWOr => Some("or"),
-            Tag::KWOrelse => Some("orelse"),
-            Tag::KWPacked => Some("packed"),
-            Tag::KWPub => Some("pub"),
-            Tag::KWResume => Some("resume"),
-            Tag::KWReturn => Some("return"),
-            Tag::KWLinksection => Some("linksection"),
-            Tag::KWStruct => Some("struct"),
-            Tag::KWSuspend => Some("suspend"),
-            Tag::KWSwitch => Some("switch"),
-            Tag::KWTest => Some("test"),
-            Tag::KWThreadlocal => Some("threadlocal"),
-            Tag::KWTry => Some("try"),
-            Tag::KWUnion => Some("union"),
-            Tag::KWUnreachable => Some("unreachable"),
-            Tag::KWUsingnamespace => Some("usingnamespace"),
-            Tag::KWVar => Some("var"),
-            Tag::KWVolatile => Some("volatile"),
-            Tag::KWWhile => Some("while"),
-        }
+    /// Whether this tag is a keyword in any language version.
+    pub fn is_keyword(&self) -> bool {
+        self.category() == Category::Keyword
     }
 
-    pub fn symbol(&self) -> &'static str {
-        self.lexeme().unwrap_or_else(|| match self {
-            Tag::Invalid => "invalid token",
-            Tag::Identifier => "an identifier",
-            Tag::StringLiteral | Tag::MultilineStringLiteralLine => "a string literal",
-            Tag::CharLiteral => "a character literal",
-            Tag::Eof => "EOF",
-            Tag::Builtin => "a builtin function",
-            Tag::NumberLiteral => "a number literal",
-            Tag::DocComment | Tag::ContainerDocComment => "a document comment",
-            _ => unreachable!(),
-        })
+    /// Whether this tag is `=` or a compound assignment.
+    pub fn is_assignment_op(&self) -> bool {
+        self.category() == Category::Assignment
+    }
+
+    /// Whether this tag is only produced in trivia mode.
+    pub fn is_trivia(&self) -> bool {
+        self.category() == Category::Trivia
+    }
+
+    /// The inverse of [`Tag::name`].
+    pub fn from_name(name: &str) -> Option<Tag> {
+        Tag::ALL.iter().find(|tag| tag.name() == name).copied()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Tag {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Tag {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Tag::from_name(&name)
+            .ok_or_else(|| serde::de::Error::custom(format_args!("unknown token tag `{name}`")))
     }
 }
 
@@ -394,9 +413,76 @@ enum State {
     Invalid,
 }
 
+/// A problem found while tokenizing, reported alongside the `Tag::Invalid`
+/// token it produces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenizeError {
+    pub kind: TokenizeErrorKind,
+    /// Byte offset of the offending character in the buffer.
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenizeErrorKind {
+    /// Malformed UTF-8 in a comment, string or character literal, including
+    /// overlong encodings, encoded surrogates and truncated sequences.
+    InvalidUtf8,
+    /// A byte that cannot start any token.
+    InvalidCharacter,
+    /// A newline or the end of the buffer before the closing `"`. The
+    /// offset is that of the newline or end.
+    UnterminatedStringLiteral,
+    /// A newline or the end of the buffer before the closing `'`.
+    UnterminatedCharLiteral,
+    /// An ASCII control character other than tab inside a comment or literal.
+    ControlCharacter,
+    /// A `\r` that is not immediately followed by `\n`.
+    StrayCarriageReturn,
+    /// An `@` not followed by a builtin name or a quoted identifier.
+    StrayAtSign,
+    /// A `\` not followed by a second `\` to begin a multiline string.
+    StrayBackslash,
+}
+
+impl TokenizeErrorKind {
+    pub fn message(&self) -> &'static str {
+        match self {
+            TokenizeErrorKind::InvalidUtf8 => "invalid UTF-8 sequence",
+            TokenizeErrorKind::InvalidCharacter => "invalid character",
+            TokenizeErrorKind::UnterminatedStringLiteral => "unterminated string literal",
+            TokenizeErrorKind::UnterminatedCharLiteral => "unterminated character literal",
+            TokenizeErrorKind::ControlCharacter => "invalid control character",
+            TokenizeErrorKind::StrayCarriageReturn => "carriage return not followed by newline",
+            TokenizeErrorKind::StrayAtSign => "expected builtin name or string after '@'",
+            TokenizeErrorKind::StrayBackslash => "expected '\\' to begin multiline string",
+        }
+    }
+}
+
 pub struct TokenStream<'a> {
     buffer: &'a [u8],
     index: usize,
+    errors: Vec<TokenizeError>,
+    trivia: Option<TriviaState>,
+    /// More input may follow `buffer`; see [`TokenReader`].
+    partial: bool,
+    /// `buffer` ends with a NUL that is not part of the source.
+    sentinel: bool,
+    validate_utf8: bool,
+    version: LanguageVersion,
+    suspended: Option<(State, Token)>,
+}
+
+/// Bytes a single step of the state machine may read past the current index:
+/// the longest UTF-8 sequence.
+const LOOKAHEAD: usize = 4;
+
+/// Bookkeeping for trivia mode: everything before `emitted` has been
+/// returned, and `pending` holds the token that ends the current gap.
+#[derive(Debug, Default)]
+struct TriviaState {
+    emitted: usize,
+    pending: Option<Token>,
 }
 
 impl<'a> TokenStream<'a> {
@@ -406,7 +492,95 @@ impl<'a> TokenStream<'a> {
         } else {
             0
         };
-        Self { buffer, index }
+        Self {
+            buffer,
+            index,
+            errors: Vec::new(),
+            trivia: None,
+            partial: false,
+            suspended: None,
+            sentinel: false,
+            validate_utf8: true,
+            version: LanguageVersion::default(),
+        }
+    }
+
+    /// Starts lexing at `index`, which must be the start of a token in a
+    /// previous run over the same bytes.
+    fn at(buffer: &'a [u8], index: usize) -> Self {
+        Self {
+            index,
+            ..Self::new(buffer)
+        }
+    }
+
+    /// Selects the keyword set; identifiers that are keywords only in other
+    /// versions are returned as `Tag::Identifier`. Defaults to the latest
+    /// release.
+    pub fn language_version(self, version: LanguageVersion) -> Self {
+        Self { version, ..self }
+    }
+
+    pub fn version(&self) -> LanguageVersion {
+        self.version
+    }
+
+    /// Tokenizes a NUL-terminated buffer exactly as upstream tokenizes a
+    /// `[:0]const u8`: the final NUL marks the end and is not part of any
+    /// token, a NUL anywhere else is invalid, and non-ASCII bytes in comments
+    /// and literals are accepted as-is, leaving UTF-8 validation to later
+    /// stages. Panics if `source` does not end with a NUL.
+    ///
+    /// The NUL stays in the buffer, so the state machine reads bytes
+    /// without bounds checks, as upstream does.
+    pub fn with_sentinel(source: &'a [u8]) -> Self {
+        assert_eq!(source.last(), Some(&0), "buffer must end with a NUL");
+        Self {
+            sentinel: true,
+            validate_utf8: false,
+            ..Self::new(source)
+        }
+    }
+
+    /// Lossless mode: whitespace, newlines and plain `//` comments are
+    /// returned as `Tag::Whitespace`, `Tag::Newline` and `Tag::LineComment`
+    /// tokens, so the spans of all tokens up to `Eof` concatenate back to the
+    /// input. A leading byte order mark is reported as whitespace.
+    pub fn with_trivia(buffer: &'a [u8]) -> Self {
+        Self {
+            trivia: Some(TriviaState::default()),
+            ..Self::new(buffer)
+        }
+    }
+
+    /// Errors reported so far, in the order they were found.
+    pub fn errors(&self) -> &[TokenizeError] {
+        &self.errors
+    }
+
+    /// Advances past the UTF-8 sequence starting at the current byte, which
+    /// must be non-ASCII. If the sequence is malformed an error is recorded,
+    /// the index is left in place and `false` is returned. Without UTF-8
+    /// validation this always steps over a single byte.
+    fn skip_utf8(&mut self) -> bool {
+        if !self.validate_utf8 {
+            self.index += 1;
+            return true;
+        }
+        match utf8_sequence_len(&self.buffer[self.index..]) {
+            Some(len) => {
+                self.index += len;
+                true
+            }
+            None => {
+                self.report(TokenizeErrorKind::InvalidUtf8, self.index);
+                false
+            }
+        }
+    }
+
+    fn report(&mut self, kind: TokenizeErrorKind, offset: usize) {
+        self.errors.push(TokenizeError { kind, offset });
     }
 
     #[cfg(debug_assertions)]
@@ -418,34 +592,99 @@ impl<'a> TokenStream<'a> {
     }
 
     pub fn next_token(&mut self) -> Token {
-        let mut token = Token {
-            tag: Tag::Invalid,
-            loc: Loc {
-                start: self.index,
-                end: self.index,
-            },
+        if self.trivia.is_none() {
+            return self.scan();
+        }
+        let token = match self.trivia.as_mut().unwrap().pending.take() {
+            Some(token) => token,
+            None => self.scan(),
+        };
+        let trivia = self.trivia.as_mut().unwrap();
+        if trivia.emitted < token.loc.start {
+            let trivia_token = scan_trivia(self.buffer, trivia.emitted, token.loc.start);
+            trivia.emitted = trivia_token.loc.end;
+            trivia.pending = Some(token);
+            return trivia_token;
+        }
+        trivia.emitted = token.loc.end;
+        token
+    }
+
+    fn scan(&mut self) -> Token {
+        let token = if self.sentinel {
+            self.resume::<true>()
+        } else {
+            self.resume::<false>()
         };
+        token.expect("only a partial buffer suspends the state machine")
+    }
+
+    /// Length of the source, not counting the sentinel.
+    fn source_len<const SENTINEL: bool>(&self) -> usize {
+        self.buffer.len() - SENTINEL as usize
+    }
+
+    /// The byte at `index`, which reads as 0 at the end of the source. A
+    /// sentinel buffer holds that 0, so the read needs no bounds check.
+    #[inline(always)]
+    fn byte<const SENTINEL: bool>(&self, index: usize) -> u8 {
+        if SENTINEL {
+            debug_assert!(index < self.buffer.len());
+            // SAFETY: `with_sentinel` checked that the buffer ends with a
+            // NUL, and the state machine never moves past a NUL at the end
+            // of the source, so `index` is at most the NUL's index. Reads
+            // of `index + 1` only follow a byte that is not that NUL.
+            unsafe { *self.buffer.get_unchecked(index) }
+        } else {
+            self.buffer.get(index).copied().unwrap_or(0)
+        }
+    }
 
-        let mut current_state = State::Start;
+    /// Runs the state machine until it produces a token. With a partial
+    /// buffer it instead saves its state and returns `None` once fewer than
+    /// `LOOKAHEAD` bytes are left, since no state then knows whether it is
+    /// looking at the real end of the input.
+    ///
+    /// Like upstream, the end of the source is found in the arms for a 0
+    /// byte rather than by a check on every step; a NUL before the end is
+    /// invalid.
+    fn resume<const SENTINEL: bool>(&mut self) -> Option<Token> {
+        let (mut current_state, mut token) = self.suspended.take().unwrap_or((
+            State::Start,
+            Token {
+                tag: Tag::Invalid,
+                loc: Loc {
+                    start: self.index,
+                    end: self.index,
+                },
+            },
+        ));
 
         loop {
-            if self.index >= self.buffer.len() {
-                if current_state == State::Start {
-                    return Token {
-                        tag: Tag::Eof,
-                        loc: Loc {
-                            start: self.index,
-                            end: self.index,
-                        },
-                    };
-                }
-                break;
+            if !SENTINEL && self.partial && self.index + LOOKAHEAD > self.buffer.len() {
+                self.suspended = Some((current_state, token));
+                return None;
             }
 
             match current_state {
-                State::Start => match self.buffer[self.index] {
+                State::Start => match self.byte::<SENTINEL>(self.index) {
+                    0 if self.index == self.source_len::<SENTINEL>() => {
+                        return Some(Token {
+                            tag: Tag::Eof,
+                            loc: Loc {
+                                start: self.index,
+                                end: self.index,
+                            },
+                        });
+                    }
                     b' ' | b'\n' | b'\t' | b'\r' => {
                         self.index += 1;
+                        while matches!(
+                            self.byte::<SENTINEL>(self.index),
+                            b' ' | b'\n' | b'\t' | b'\r'
+                        ) {
+                            self.index += 1;
+                        }
                         token.loc.start = self.index;
                         continue;
                     }
@@ -605,37 +844,40 @@ impl<'a> TokenStream<'a> {
                         continue;
                     }
                     _ => {
+                        self.report(TokenizeErrorKind::InvalidCharacter, self.index);
                         current_state = State::Invalid;
                         continue;
                     }
                 },
                 State::ExpectNewline => {
                     self.index += 1;
-                    match self.buffer.get(self.index) {
-                        Some(b'\n') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'\n' => {
                             self.index += 1;
                             token.loc.start = self.index;
                             current_state = State::Start;
                             continue;
                         }
-                        Some(_) => {
-                            current_state = State::Invalid;
-                            continue;
-                        }
-                        None => {
+                        0 if self.index == self.source_len::<SENTINEL>() => {
+                            self.report(TokenizeErrorKind::StrayCarriageReturn, self.index - 1);
                             token.tag = Tag::Invalid;
                             break;
                         }
+                        _ => {
+                            self.report(TokenizeErrorKind::StrayCarriageReturn, self.index - 1);
+                            current_state = State::Invalid;
+                            continue;
+                        }
                     }
                 }
 
                 State::Invalid => {
                     self.index += 1;
-                    if self.index >= self.buffer.len() {
-                        token.tag = Tag::Invalid;
-                        break;
-                    }
-                    match self.buffer[self.index] {
+                    match self.byte::<SENTINEL>(self.index) {
+                        0 if self.index == self.source_len::<SENTINEL>() => {
+                            token.tag = Tag::Invalid;
+                            break;
+                        }
                         b'\n' => {
                             token.tag = Tag::Invalid;
                             break;
@@ -644,29 +886,37 @@ impl<'a> TokenStream<'a> {
                     }
                 }
 
-                State::SawAtSign => match self.buffer.get(self.index) {
-                    Some(b'\n') | None => {
+                State::SawAtSign => match self.byte::<SENTINEL>(self.index) {
+                    0 if self.index == self.source_len::<SENTINEL>() => {
+                        self.report(TokenizeErrorKind::StrayAtSign, token.loc.start);
+                        token.tag = Tag::Invalid;
+                        break;
+                    }
+                    b'\n' => {
+                        self.report(TokenizeErrorKind::StrayAtSign, token.loc.start);
                         token.tag = Tag::Invalid;
                         break;
                     }
-                    Some(b'"') => {
+                    b'"' => {
+                        self.index += 1;
                         token.tag = Tag::Identifier;
                         current_state = State::StringLiteral;
                         continue;
                     }
-                    Some(b'a'..=b'z') | Some(b'A'..=b'Z') | Some(b'_') => {
+                    b'a'..=b'z' | b'A'..=b'Z' | b'_' => {
                         token.tag = Tag::Builtin;
                         current_state = State::Builtin;
                         continue;
                     }
-                    Some(_) => {
+                    _ => {
+                        self.report(TokenizeErrorKind::StrayAtSign, token.loc.start);
                         current_state = State::Invalid;
                         continue;
                     }
                 },
 
-                State::Ampersand => match self.buffer.get(self.index) {
-                    Some(b'=') => {
+                State::Ampersand => match self.byte::<SENTINEL>(self.index) {
+                    b'=' => {
                         token.tag = Tag::AmpersandEqual;
                         self.index += 1;
                         break;
@@ -677,23 +927,23 @@ impl<'a> TokenStream<'a> {
                     }
                 },
 
-                State::Asterisk => match self.buffer.get(self.index) {
-                    Some(b'=') => {
+                State::Asterisk => match self.byte::<SENTINEL>(self.index) {
+                    b'=' => {
                         token.tag = Tag::AsteriskEqual;
                         self.index += 1;
                         break;
                     }
-                    Some(b'*') => {
+                    b'*' => {
                         token.tag = Tag::AsteriskAsterisk;
                         self.index += 1;
                         break;
                     }
-                    Some(b'%') => {
+                    b'%' => {
                         self.index += 1;
                         current_state = State::AsteriskPercent;
                         continue;
                     }
-                    Some(b'|') => {
+                    b'|' => {
                         self.index += 1;
                         current_state = State::AsteriskPipe;
                         continue;
@@ -704,8 +954,8 @@ impl<'a> TokenStream<'a> {
                     }
                 },
 
-                State::AsteriskPercent => match self.buffer.get(self.index) {
-                    Some(b'=') => {
+                State::AsteriskPercent => match self.byte::<SENTINEL>(self.index) {
+                    b'=' => {
                         token.tag = Tag::AsteriskPercentEqual;
                         self.index += 1;
                         break;
@@ -716,8 +966,8 @@ impl<'a> TokenStream<'a> {
                     }
                 },
 
-                State::AsteriskPipe => match self.buffer.get(self.index) {
-                    Some(b'=') => {
+                State::AsteriskPipe => match self.byte::<SENTINEL>(self.index) {
+                    b'=' => {
                         token.tag = Tag::AsteriskPipeEqual;
                         self.index += 1;
                         break;
@@ -728,185 +978,214 @@ impl<'a> TokenStream<'a> {
                     }
                 },
 
-                State::Identifier => match self.buffer.get(self.index) {
-                    Some(b'a'..=b'z') | Some(b'A'..=b'Z') | Some(b'_') | Some(b'0'..=b'9') => {
-                        self.index += 1;
+                State::Identifier => {
+                    self.index += identifier_run(&self.buffer[self.index..]);
+                    if self.index == self.buffer.len() && self.partial {
                         continue;
                     }
-                    _ => {
-                        if let Some(keyword_tag) =
-                            parse_keyword(&self.buffer[token.loc.start..self.index])
-                        {
+                    if let Some(keyword_tag) =
+                        parse_keyword(&self.buffer[token.loc.start..self.index])
+                    {
+                        if self.version.is_keyword(&keyword_tag) {
                             token.tag = keyword_tag;
                         }
-                        break;
                     }
-                },
+                    break;
+                }
 
-                State::Builtin => match self.buffer.get(self.index) {
-                    Some(b'a'..=b'z') | Some(b'A'..=b'Z') | Some(b'_') | Some(b'0'..=b'9') => {
-                        self.index += 1;
+                State::Builtin => {
+                    self.index += identifier_run(&self.buffer[self.index..]);
+                    if self.index == self.buffer.len() && self.partial {
                         continue;
                     }
-                    _ => break,
-                },
+                    break;
+                }
 
-                State::Backslash => match self.buffer.get(self.index) {
-                    None => {
+                State::Backslash => match self.byte::<SENTINEL>(self.index) {
+                    0 if self.index == self.source_len::<SENTINEL>() => {
+                        self.report(TokenizeErrorKind::StrayBackslash, token.loc.start);
                         token.tag = Tag::Invalid;
                         break;
                     }
-                    Some(b'\\') => {
+                    b'\\' => {
                         current_state = State::MultilineStringLiteralLine;
                         continue;
                     }
-                    Some(b'\n') => {
+                    b'\n' => {
+                        self.report(TokenizeErrorKind::StrayBackslash, token.loc.start);
                         token.tag = Tag::Invalid;
                         break;
                     }
-                    Some(_) => {
+                    _ => {
+                        self.report(TokenizeErrorKind::StrayBackslash, token.loc.start);
                         current_state = State::Invalid;
                         continue;
                     }
                 },
 
-                State::StringLiteral => {
-                    if self.index >= self.buffer.len() {
-                        if self.index != self.buffer.len() {
-                            current_state = State::Invalid;
-                            continue;
-                        } else {
-                            token.tag = Tag::Invalid;
-                            break;
-                        }
+                State::StringLiteral => match self.byte::<SENTINEL>(self.index) {
+                    0 if self.index == self.source_len::<SENTINEL>() => {
+                        self.report(TokenizeErrorKind::UnterminatedStringLiteral, self.index);
+                        token.tag = Tag::Invalid;
+                        break;
                     }
-                    match self.buffer[self.index] {
-                        b'\n' => {
-                            token.tag = Tag::Invalid;
-                            break;
-                        }
-                        b'\\' => {
-                            self.index += 1;
-                            current_state = State::StringLiteralBackslash;
-                            continue;
-                        }
-                        b'"' => {
-                            self.index += 1;
-                            break;
-                        }
-                        0x01..=0x09 | 0x0b..=0x1f | 0x7f => {
+                    b'\n' => {
+                        self.report(TokenizeErrorKind::UnterminatedStringLiteral, self.index);
+                        token.tag = Tag::Invalid;
+                        break;
+                    }
+                    b'\\' => {
+                        self.index += 1;
+                        current_state = State::StringLiteralBackslash;
+                        continue;
+                    }
+                    b'"' => {
+                        self.index += 1;
+                        break;
+                    }
+                    0x00..=0x09 | 0x0b..=0x1f | 0x7f => {
+                        self.report(TokenizeErrorKind::ControlCharacter, self.index);
+                        current_state = State::Invalid;
+                        continue;
+                    }
+                    0x80..=0xff => {
+                        if !self.skip_utf8() {
                             current_state = State::Invalid;
-                            continue;
-                        }
-                        _ => {
-                            self.index += 1;
-                            continue;
                         }
+                        continue;
                     }
-                }
+                    _ => {
+                        self.index += 1;
+                        self.index += printable_run(&self.buffer[self.index..], Some(b'"'));
+                        continue;
+                    }
+                },
 
-                State::StringLiteralBackslash => match self.buffer.get(self.index) {
-                    Some(b'\n') | None => {
+                State::StringLiteralBackslash => match self.byte::<SENTINEL>(self.index) {
+                    0 if self.index == self.source_len::<SENTINEL>() => {
+                        self.report(TokenizeErrorKind::UnterminatedStringLiteral, self.index);
+                        token.tag = Tag::Invalid;
+                        break;
+                    }
+                    b'\n' => {
+                        self.report(TokenizeErrorKind::UnterminatedStringLiteral, self.index);
+                        token.tag = Tag::Invalid;
+                        break;
+                    }
+                    0 => {
+                        self.report(TokenizeErrorKind::ControlCharacter, self.index);
                         token.tag = Tag::Invalid;
                         break;
                     }
-                    Some(_) => {
+                    0x80..=0xff => {
+                        current_state = State::StringLiteral;
+                        continue;
+                    }
+                    _ => {
+                        self.index += 1;
                         current_state = State::StringLiteral;
                         continue;
                     }
                 },
 
-                State::CharLiteral => {
-                    if self.index >= self.buffer.len() {
-                        if self.index != self.buffer.len() {
+                State::CharLiteral => match self.byte::<SENTINEL>(self.index) {
+                    0 if self.index == self.source_len::<SENTINEL>() => {
+                        self.report(TokenizeErrorKind::UnterminatedCharLiteral, self.index);
+                        token.tag = Tag::Invalid;
+                        break;
+                    }
+                    b'\n' => {
+                        self.report(TokenizeErrorKind::UnterminatedCharLiteral, self.index);
+                        token.tag = Tag::Invalid;
+                        break;
+                    }
+                    b'\\' => {
+                        self.index += 1;
+                        current_state = State::CharLiteralBackslash;
+                        continue;
+                    }
+                    b'\'' => {
+                        self.index += 1;
+                        break;
+                    }
+                    0x00..=0x09 | 0x0b..=0x1f | 0x7f => {
+                        self.report(TokenizeErrorKind::ControlCharacter, self.index);
+                        current_state = State::Invalid;
+                        continue;
+                    }
+                    0x80..=0xff => {
+                        if !self.skip_utf8() {
                             current_state = State::Invalid;
-                            continue;
-                        } else {
-                            token.tag = Tag::Invalid;
-                            break;
                         }
+                        continue;
                     }
-                    match self.buffer[self.index] {
-                        b'\n' => {
-                            token.tag = Tag::Invalid;
-                            break;
-                        }
-                        b'\\' => {
-                            self.index += 1;
-                            current_state = State::CharLiteralBackslash;
-                            continue;
-                        }
-                        b'\'' => {
-                            self.index += 1;
-                            break;
-                        }
-                        0x01..=0x09 | 0x0b..=0x1f | 0x7f => {
-                            current_state = State::Invalid;
-                            continue;
-                        }
-                        _ => {
-                            self.index += 1;
-                            continue;
-                        }
+                    _ => {
+                        self.index += 1;
+                        self.index += printable_run(&self.buffer[self.index..], Some(b'\''));
+                        continue;
                     }
-                }
+                },
 
-                State::CharLiteralBackslash => {
-                    if self.index >= self.buffer.len() {
-                        if self.index != self.buffer.len() {
-                            current_state = State::Invalid;
-                            continue;
-                        } else {
-                            token.tag = Tag::Invalid;
-                            break;
-                        }
+                State::CharLiteralBackslash => match self.byte::<SENTINEL>(self.index) {
+                    0 if self.index == self.source_len::<SENTINEL>() => {
+                        self.report(TokenizeErrorKind::UnterminatedCharLiteral, self.index);
+                        token.tag = Tag::Invalid;
+                        break;
                     }
-                    match self.buffer[self.index] {
-                        b'\n' => {
-                            token.tag = Tag::Invalid;
-                            break;
-                        }
-                        0x01..=0x09 | 0x0b..=0x1f | 0x7f => {
-                            current_state = State::Invalid;
-                            continue;
-                        }
-                        _ => {
-                            current_state = State::CharLiteral;
-                            continue;
-                        }
+                    b'\n' => {
+                        self.report(TokenizeErrorKind::UnterminatedCharLiteral, self.index);
+                        token.tag = Tag::Invalid;
+                        break;
                     }
-                }
+                    0x00..=0x09 | 0x0b..=0x1f | 0x7f => {
+                        self.report(TokenizeErrorKind::ControlCharacter, self.index);
+                        current_state = State::Invalid;
+                        continue;
+                    }
+                    0x80..=0xff => {
+                        current_state = State::CharLiteral;
+                        continue;
+                    }
+                    _ => {
+                        self.index += 1;
+                        current_state = State::CharLiteral;
+                        continue;
+                    }
+                },
 
-                State::MultilineStringLiteralLine => {
-                    if self.index >= self.buffer.len() {
-                        if self.index != self.buffer.len() {
+                State::MultilineStringLiteralLine => match self.byte::<SENTINEL>(self.index) {
+                    0 if self.index == self.source_len::<SENTINEL>() => {
+                        break;
+                    }
+                    b'\n' => break,
+                    b'\r' => {
+                        if self.byte::<SENTINEL>(self.index + 1) != b'\n' {
+                            self.report(TokenizeErrorKind::StrayCarriageReturn, self.index);
                             current_state = State::Invalid;
                             continue;
                         }
                         break;
                     }
-                    match self.buffer[self.index] {
-                        b'\n' => break,
-                        b'\r' => {
-                            if self.buffer.get(self.index + 1) != Some(&b'\n') {
-                                current_state = State::Invalid;
-                                continue;
-                            }
-                            break;
-                        }
-                        0x01..=0x09 | 0x0b..=0x0c | 0x0e..=0x1f | 0x7f => {
+                    0x00..=0x09 | 0x0b..=0x0c | 0x0e..=0x1f | 0x7f => {
+                        self.report(TokenizeErrorKind::ControlCharacter, self.index);
+                        current_state = State::Invalid;
+                        continue;
+                    }
+                    0x80..=0xff => {
+                        if !self.skip_utf8() {
                             current_state = State::Invalid;
-                            continue;
-                        }
-                        _ => {
-                            self.index += 1;
-                            continue;
                         }
+                        continue;
                     }
-                }
+                    _ => {
+                        self.index += 1;
+                        self.index += printable_run(&self.buffer[self.index..], None);
+                        continue;
+                    }
+                },
                 State::Bang => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::BangEqual;
                             self.index += 1;
                         }
@@ -916,12 +1195,12 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::Pipe => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::PipeEqual;
                             self.index += 1;
                         }
-                        Some(b'|') => {
+                        b'|' => {
                             token.tag = Tag::PipePipe;
                             self.index += 1;
                         }
@@ -931,12 +1210,12 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::Equal => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::EqualEqual;
                             self.index += 1;
                         }
-                        Some(b'>') => {
+                        b'>' => {
                             token.tag = Tag::EqualAngleBrackRight;
                             self.index += 1;
                         }
@@ -946,21 +1225,21 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::Minus => {
-                    match self.buffer.get(self.index) {
-                        Some(b'>') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'>' => {
                             token.tag = Tag::Arrow;
                             self.index += 1;
                         }
-                        Some(b'=') => {
+                        b'=' => {
                             token.tag = Tag::MinusEqual;
                             self.index += 1;
                         }
-                        Some(b'%') => {
+                        b'%' => {
                             self.index += 1;
                             current_state = State::MinusPercent;
                             continue;
                         }
-                        Some(b'|') => {
+                        b'|' => {
                             self.index += 1;
                             current_state = State::MinusPipe;
                             continue;
@@ -971,8 +1250,8 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::MinusPercent => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::MinusPercentEqual;
                             self.index += 1;
                         }
@@ -982,8 +1261,8 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::MinusPipe => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::MinusPipeEqual;
                             self.index += 1;
                         }
@@ -993,13 +1272,13 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::AngleBrackLeft => {
-                    match self.buffer.get(self.index) {
-                        Some(b'<') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'<' => {
                             self.index += 1;
                             current_state = State::AngleBrackAngleBrackLeft;
                             continue;
                         }
-                        Some(b'=') => {
+                        b'=' => {
                             token.tag = Tag::AngleBrackLeftEqual;
                             self.index += 1;
                         }
@@ -1009,12 +1288,12 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::AngleBrackAngleBrackLeft => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::AngleBrackAngleBrackLeftEqual;
                             self.index += 1;
                         }
-                        Some(b'|') => {
+                        b'|' => {
                             self.index += 1;
                             current_state = State::AngleBrackAngleBrackLeftPipe;
                             continue;
@@ -1025,8 +1304,8 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::AngleBrackAngleBrackLeftPipe => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::AngleBrackAngleBrackLeftPipeEqual;
                             self.index += 1;
                         }
@@ -1036,13 +1315,13 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::AngleBrackRight => {
-                    match self.buffer.get(self.index) {
-                        Some(b'>') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'>' => {
                             self.index += 1;
                             current_state = State::AngleBrackAngleBrackRight;
                             continue;
                         }
-                        Some(b'=') => {
+                        b'=' => {
                             token.tag = Tag::AngleBrackRightEqual;
                             self.index += 1;
                         }
@@ -1052,8 +1331,8 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::AngleBrackAngleBrackRight => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::AngleBrackAngleBrackRightEqual;
                             self.index += 1;
                         }
@@ -1062,13 +1341,13 @@ impl<'a> TokenStream<'a> {
                     break;
                 }
                 State::Period => {
-                    match self.buffer.get(self.index) {
-                        Some(b'.') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'.' => {
                             self.index += 1;
                             current_state = State::Period2;
                             continue;
                         }
-                        Some(b'*') => {
+                        b'*' => {
                             self.index += 1;
                             current_state = State::PeriodAsterisk;
                             continue;
@@ -1079,8 +1358,8 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::Period2 => {
-                    match self.buffer.get(self.index) {
-                        Some(b'.') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'.' => {
                             token.tag = Tag::Ellipsis3;
                             self.index += 1;
                         }
@@ -1090,8 +1369,8 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::PeriodAsterisk => {
-                    match self.buffer.get(self.index) {
-                        Some(b'*') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'*' => {
                             token.tag = Tag::InvalidPeriodAsterisks;
                         }
                         _ => token.tag = Tag::PeriodAsterisk,
@@ -1100,13 +1379,13 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::Slash => {
-                    match self.buffer.get(self.index) {
-                        Some(b'/') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'/' => {
                             self.index += 1;
                             current_state = State::LineCommentStart;
                             continue;
                         }
-                        Some(b'=') => {
+                        b'=' => {
                             token.tag = Tag::SlashEqual;
                             self.index += 1;
                         }
@@ -1115,180 +1394,207 @@ impl<'a> TokenStream<'a> {
                     break;
                 }
 
-                State::LineCommentStart => {
-                    if self.index >= self.buffer.len() {
-                        if self.index != self.buffer.len() {
-                            current_state = State::Invalid;
-                            continue;
+                State::LineCommentStart => match self.byte::<SENTINEL>(self.index) {
+                    0 if self.index == self.source_len::<SENTINEL>() => {
+                        return Some(Token {
+                            tag: Tag::Eof,
+                            loc: Loc {
+                                start: self.index,
+                                end: self.index,
+                            },
+                        });
+                    }
+                    b'!' => {
+                        token.tag = Tag::ContainerDocComment;
+                        current_state = State::DocComment;
+                        continue;
+                    }
+                    b'\n' => {
+                        self.index += 1;
+                        token.loc.start = self.index;
+                        current_state = State::Start;
+                        continue;
+                    }
+                    b'/' => {
+                        self.index += 1;
+                        current_state = State::DocCommentStart;
+                        continue;
+                    }
+                    b'\r' => {
+                        current_state = State::ExpectNewline;
+                        continue;
+                    }
+                    0x00..=0x08 | 0x0b..=0x0c | 0x0e..=0x1f | 0x7f => {
+                        self.report(TokenizeErrorKind::ControlCharacter, self.index);
+                        current_state = State::Invalid;
+                        continue;
+                    }
+                    0x80..=0xff => {
+                        current_state = if self.skip_utf8() {
+                            State::LineComment
                         } else {
-                            return Token {
-                                tag: Tag::Eof,
-                                loc: Loc {
-                                    start: self.index,
-                                    end: self.index,
-                                },
-                            };
-                        }
+                            State::Invalid
+                        };
+                        continue;
                     }
-                    match self.buffer[self.index] {
-                        b'!' => {
-                            token.tag = Tag::ContainerDocComment;
-                            current_state = State::DocComment;
-                            continue;
-                        }
-                        b'\n' => {
-                            self.index += 1;
-                            token.loc.start = self.index;
-                            current_state = State::Start;
-                            continue;
-                        }
-                        b'/' => {
-                            self.index += 1;
-                            current_state = State::DocCommentStart;
-                            continue;
-                        }
-                        b'\r' => {
-                            current_state = State::ExpectNewline;
-                            continue;
-                        }
-                        0x01..=0x09 | 0x0b..=0x0c | 0x0e..=0x1f | 0x7f => {
-                            current_state = State::Invalid;
-                            continue;
-                        }
-                        _ => {
-                            self.index += 1;
-                            current_state = State::LineComment;
-                            continue;
-                        }
+                    _ => {
+                        self.index += 1;
+                        current_state = State::LineComment;
+                        continue;
                     }
-                }
+                },
 
-                State::DocCommentStart => match self.buffer.get(self.index) {
-                    None | Some(b'\n') => {
+                State::DocCommentStart => match self.byte::<SENTINEL>(self.index) {
+                    b'\n' | 0 => {
                         token.tag = Tag::DocComment;
                         break;
                     }
-                    Some(b'\r') => {
-                        if self.buffer.get(self.index + 1) == Some(&b'\n') {
+                    b'\r' => {
+                        if self.byte::<SENTINEL>(self.index + 1) == b'\n' {
                             token.tag = Tag::DocComment;
                             break;
                         } else {
+                            self.report(TokenizeErrorKind::StrayCarriageReturn, self.index);
                             current_state = State::Invalid;
                             continue;
                         }
                     }
-                    Some(b'/') => {
+                    b'/' => {
                         self.index += 1;
                         current_state = State::LineComment;
                         continue;
                     }
-                    Some(0x01..=0x09) | Some(0x0b..=0x0c) | Some(0x0e..=0x1f) | Some(0x7f) => {
+                    0x01..=0x08 | 0x0b..=0x0c | 0x0e..=0x1f | 0x7f => {
+                        self.report(TokenizeErrorKind::ControlCharacter, self.index);
                         current_state = State::Invalid;
                         continue;
                     }
-                    Some(_) => {
+                    _ => {
                         token.tag = Tag::DocComment;
                         current_state = State::DocComment;
                         continue;
                     }
                 },
 
-                State::LineComment => {
-                    if self.index >= self.buffer.len() {
-                        if self.index != self.buffer.len() {
-                            current_state = State::Invalid;
-                            continue;
-                        } else {
-                            return Token {
-                                tag: Tag::Eof,
-                                loc: Loc {
-                                    start: self.index,
-                                    end: self.index,
-                                },
-                            };
-                        }
+                State::LineComment => match self.byte::<SENTINEL>(self.index) {
+                    0 if self.index == self.source_len::<SENTINEL>() => {
+                        return Some(Token {
+                            tag: Tag::Eof,
+                            loc: Loc {
+                                start: self.index,
+                                end: self.index,
+                            },
+                        });
+                    }
+                    b'\n' => {
+                        self.index += 1;
+                        token.loc.start = self.index;
+                        current_state = State::Start;
+                        continue;
                     }
-                    match self.buffer[self.index] {
-                        b'\n' => {
-                            self.index += 1;
-                            token.loc.start = self.index;
-                            current_state = State::Start;
-                            continue;
-                        }
-                        b'\r' => {
-                            current_state = State::ExpectNewline;
-                            continue;
-                        }
-                        0x01..=0x09 | 0x0b..=0x0c | 0x0e..=0x1f | 0x7f => {
+                    b'\r' => {
+                        current_state = State::ExpectNewline;
+                        continue;
+                    }
+                    0x00..=0x08 | 0x0b..=0x0c | 0x0e..=0x1f | 0x7f => {
+                        self.report(TokenizeErrorKind::ControlCharacter, self.index);
+                        current_state = State::Invalid;
+                        continue;
+                    }
+                    0x80..=0xff => {
+                        if !self.skip_utf8() {
                             current_state = State::Invalid;
-                            continue;
-                        }
-                        _ => {
-                            self.index += 1;
-                            continue;
                         }
+                        continue;
                     }
-                }
+                    _ => {
+                        self.index += 1;
+                        self.index += printable_run(&self.buffer[self.index..], None);
+                        continue;
+                    }
+                },
 
-                State::DocComment => match self.buffer.get(self.index) {
-                    None | Some(b'\n') => break,
-                    Some(b'\r') => {
-                        if self.buffer.get(self.index + 1) != Some(&b'\n') {
+                State::DocComment => match self.byte::<SENTINEL>(self.index) {
+                    b'\n' | 0 => break,
+                    b'\r' => {
+                        if self.byte::<SENTINEL>(self.index + 1) != b'\n' {
+                            self.report(TokenizeErrorKind::StrayCarriageReturn, self.index);
                             current_state = State::Invalid;
                             continue;
                         }
                         break;
                     }
-                    Some(0x01..=0x09) | Some(0x0b..=0x0c) | Some(0x0e..=0x1f) | Some(0x7f) => {
+                    0x01..=0x08 | 0x0b..=0x0c | 0x0e..=0x1f | 0x7f => {
+                        self.report(TokenizeErrorKind::ControlCharacter, self.index);
                         current_state = State::Invalid;
                         continue;
                     }
-                    Some(_) => {
+                    0x80..=0xff => {
+                        if !self.skip_utf8() {
+                            current_state = State::Invalid;
+                        }
+                        continue;
+                    }
+                    _ => {
                         self.index += 1;
+                        self.index += printable_run(&self.buffer[self.index..], None);
                         continue;
                     }
                 },
-                State::Int => match self.buffer.get(self.index) {
-                    Some(b'.') => {
+                State::Int => match self.byte::<SENTINEL>(self.index) {
+                    b'.' => {
                         current_state = State::IntPeriod;
                         continue;
                     }
-                    Some(b'_') | Some(b'a'..=b'd') | Some(b'f'..=b'o') | Some(b'q'..=b'z')
-                    | Some(b'A'..=b'D') | Some(b'F'..=b'O') | Some(b'Q'..=b'Z')
-                    | Some(b'0'..=b'9') => {
+                    b'_'
+                    | b'a'..=b'd'
+                    | b'f'..=b'o'
+                    | b'q'..=b'z'
+                    | b'A'..=b'D'
+                    | b'F'..=b'O'
+                    | b'Q'..=b'Z'
+                    | b'0'..=b'9' => {
                         self.index += 1;
                         continue;
                     }
-                    Some(b'e') | Some(b'E') | Some(b'p') | Some(b'P') => {
+                    b'e' | b'E' | b'p' | b'P' => {
                         current_state = State::IntExponent;
                         continue;
                     }
                     _ => break,
                 },
 
-                State::IntExponent => match self.buffer.get(self.index) {
-                    Some(b'-') | Some(b'+') => {
-                        self.index += 1;
-                        current_state = State::Float;
-                        continue;
-                    }
-                    _ => {
-                        current_state = State::Int;
-                        continue;
+                State::IntExponent => {
+                    self.index += 1;
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'-' | b'+' => {
+                            self.index += 1;
+                            current_state = State::Float;
+                            continue;
+                        }
+                        _ => {
+                            current_state = State::Int;
+                            continue;
+                        }
                     }
-                },
+                }
 
                 State::IntPeriod => {
                     self.index += 1;
-                    match self.buffer.get(self.index) {
-                        Some(b'_') | Some(b'a'..=b'd') | Some(b'f'..=b'o') | Some(b'q'..=b'z')
-                        | Some(b'A'..=b'D') | Some(b'F'..=b'O') | Some(b'Q'..=b'Z')
-                        | Some(b'0'..=b'9') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'_'
+                        | b'a'..=b'd'
+                        | b'f'..=b'o'
+                        | b'q'..=b'z'
+                        | b'A'..=b'D'
+                        | b'F'..=b'O'
+                        | b'Q'..=b'Z'
+                        | b'0'..=b'9' => {
                             self.index += 1;
                             current_state = State::Float;
                             continue;
                         }
-                        Some(b'e') | Some(b'E') | Some(b'p') | Some(b'P') => {
+                        b'e' | b'E' | b'p' | b'P' => {
                             current_state = State::FloatExponent;
                             continue;
                         }
@@ -1299,34 +1605,42 @@ impl<'a> TokenStream<'a> {
                     }
                 }
 
-                State::Float => match self.buffer.get(self.index) {
-                    Some(b'_') | Some(b'a'..=b'd') | Some(b'f'..=b'o') | Some(b'q'..=b'z')
-                    | Some(b'A'..=b'D') | Some(b'F'..=b'O') | Some(b'Q'..=b'Z')
-                    | Some(b'0'..=b'9') => {
+                State::Float => match self.byte::<SENTINEL>(self.index) {
+                    b'_'
+                    | b'a'..=b'd'
+                    | b'f'..=b'o'
+                    | b'q'..=b'z'
+                    | b'A'..=b'D'
+                    | b'F'..=b'O'
+                    | b'Q'..=b'Z'
+                    | b'0'..=b'9' => {
                         self.index += 1;
                         continue;
                     }
-                    Some(b'e') | Some(b'E') | Some(b'p') | Some(b'P') => {
+                    b'e' | b'E' | b'p' | b'P' => {
                         current_state = State::FloatExponent;
                         continue;
                     }
                     _ => break,
                 },
 
-                State::FloatExponent => match self.buffer.get(self.index) {
-                    Some(b'-') | Some(b'+') => {
-                        self.index += 1;
-                        current_state = State::Float;
-                        continue;
-                    }
-                    _ => {
-                        current_state = State::Float;
-                        continue;
+                State::FloatExponent => {
+                    self.index += 1;
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'-' | b'+' => {
+                            self.index += 1;
+                            current_state = State::Float;
+                            continue;
+                        }
+                        _ => {
+                            current_state = State::Float;
+                            continue;
+                        }
                     }
-                },
+                }
                 State::Caret => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::CaretEqual;
                             self.index += 1;
                         }
@@ -1336,8 +1650,8 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::Percent => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::PercentEqual;
                             self.index += 1;
                         }
@@ -1347,21 +1661,21 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::Plus => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::PlusEqual;
                             self.index += 1;
                         }
-                        Some(b'+') => {
+                        b'+' => {
                             token.tag = Tag::PlusPlus;
                             self.index += 1;
                         }
-                        Some(b'%') => {
+                        b'%' => {
                             self.index += 1;
                             current_state = State::PlusPercent;
                             continue;
                         }
-                        Some(b'|') => {
+                        b'|' => {
                             self.index += 1;
                             current_state = State::PlusPipe;
                             continue;
@@ -1372,8 +1686,8 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::PlusPercent => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::PlusPercentEqual;
                             self.index += 1;
                         }
@@ -1383,8 +1697,8 @@ impl<'a> TokenStream<'a> {
                 }
 
                 State::PlusPipe => {
-                    match self.buffer.get(self.index) {
-                        Some(b'=') => {
+                    match self.byte::<SENTINEL>(self.index) {
+                        b'=' => {
                             token.tag = Tag::PlusPipeEqual;
                             self.index += 1;
                         }
@@ -1396,31 +1710,1535 @@ impl<'a> TokenStream<'a> {
         }
 
         token.loc.end = self.index;
-        token
+        Some(token)
     }
 }
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    #[test]
-    fn test_basic_tokens() {
-        let input = b"fn main() {}";
-        let mut tokenizer = TokenStream::new(input);
+/// Length of the well-formed UTF-8 sequence at the start of `bytes`, which
+/// must begin with a non-ASCII byte.
+fn utf8_sequence_len(bytes: &[u8]) -> Option<usize> {
+    let len = match bytes[0] {
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => return None,
+    };
+    let sequence = bytes.get(..len)?;
+    std::str::from_utf8(sequence).ok().map(|_| len)
+}
 
-        let tokens = vec![
-            Tag::KWFn,
-            Tag::Identifier,
-            Tag::LParen,
-            Tag::RParen,
-            Tag::LBrace,
-            Tag::RBrace,
-            Tag::Eof,
-        ];
+/// Returns the first piece of trivia in `buffer[start..end]`, a gap between
+/// two tokens that the state machine skipped.
+fn scan_trivia(buffer: &[u8], start: usize, end: usize) -> Token {
+    let gap = &buffer[start..end];
+    let is_crlf = |i: usize| gap[i] == b'\r' && gap.get(i + 1) == Some(&b'\n');
+    let (tag, len) = if gap[0] == b'\n' {
+        (Tag::Newline, 1)
+    } else if is_crlf(0) {
+        (Tag::Newline, 2)
+    } else if gap.starts_with(b"//") {
+        let len = (0..gap.len())
+            .find(|&i| gap[i] == b'\n' || is_crlf(i))
+            .unwrap_or(gap.len());
+        (Tag::LineComment, len)
+    } else {
+        let len = (1..gap.len())
+            .find(|&i| gap[i] == b'\n' || gap[i] == b'/' || is_crlf(i))
+            .unwrap_or(gap.len());
+        (Tag::Whitespace, len)
+    };
+    Token {
+        tag,
+        loc: Loc {
+            start,
+            end: start + len,
+        },
+    }
+}
 
-        for expected_tag in tokens {
-            let token = tokenizer.next_token();
-            assert_eq!(token.tag, expected_tag);
+fn identifier_run(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .position(|&byte| !(byte.is_ascii_alphanumeric() || byte == b'_'))
+        .unwrap_or(bytes.len())
+}
+
+/// Length of the leading run of printable ASCII (`0x20..=0x7e`) in `bytes`,
+/// also stopping at `quote` and `\` when a quote is given. Checks eight
+/// bytes at a time; the byte that ends the run is left to the caller's state
+/// to handle.
+fn printable_run(bytes: &[u8], quote: Option<u8>) -> usize {
+    const ONES: u64 = u64::from_ne_bytes([0x01; 8]);
+    const HIGHS: u64 = u64::from_ne_bytes([0x80; 8]);
+    // Nonzero if any byte of `word` is zero.
+    fn any_zero(word: u64) -> u64 {
+        word.wrapping_sub(ONES) & !word & HIGHS
+    }
+    fn is_stop(byte: u8, quote: Option<u8>) -> bool {
+        !(0x20..=0x7e).contains(&byte) || quote.is_some_and(|q| byte == q || byte == b'\\')
+    }
+
+    let mut run = 0;
+    for chunk in bytes.chunks_exact(8) {
+        let word = u64::from_ne_bytes(chunk.try_into().unwrap());
+        let below_space = word.wrapping_sub(ONES * 0x20) & !word & HIGHS;
+        let above_tilde = (word.wrapping_add(ONES) | word) & HIGHS;
+        let mut stops = below_space | above_tilde;
+        if let Some(quote) = quote {
+            stops |= any_zero(word ^ (ONES * quote as u64)) | any_zero(word ^ (ONES * 0x5c));
+        }
+        if stops != 0 {
+            break;
+        }
+        run += 8;
+    }
+    run + bytes[run..]
+        .iter()
+        .position(|&byte| is_stop(byte, quote))
+        .unwrap_or(bytes.len() - run)
+}
+
+impl Iterator for TokenStream<'_> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.next_token();
+        if token.tag == Tag::Eof {
+            None
+        } else {
+            Some(token)
+        }
+    }
+}
+
+/// Tokenizes input read in chunks from `reader`, for sources too large to
+/// hold in memory or arriving over a pipe. The state machine is suspended at
+/// the end of each chunk and resumed once the next one is read, so only the
+/// token being built (or the comment line being skipped) is kept in memory.
+/// Token and error offsets are absolute positions in the whole input.
+pub struct TokenReader<R> {
+    reader: R,
+    /// Unconsumed input; `window[0]` is at offset `base` of the input.
+    window: Vec<u8>,
+    base: usize,
+    index: usize,
+    suspended: Option<(State, Token)>,
+    errors: Vec<TokenizeError>,
+    started: bool,
+    at_eof: bool,
+    chunk_size: usize,
+    version: LanguageVersion,
+}
+
+impl<R: Read> TokenReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_chunk_size(reader, 64 * 1024)
+    }
+
+    /// Reads `chunk_size` bytes at a time instead of the default 64 KiB.
+    pub fn with_chunk_size(reader: R, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0);
+        Self {
+            reader,
+            chunk_size,
+            version: LanguageVersion::default(),
+            window: Vec::new(),
+            base: 0,
+            index: 0,
+            suspended: None,
+            errors: Vec::new(),
+            started: false,
+            at_eof: false,
+        }
+    }
+
+    /// See [`TokenStream::language_version`].
+    pub fn language_version(self, version: LanguageVersion) -> Self {
+        Self { version, ..self }
+    }
+
+    /// Errors reported so far, in the order they were found.
+    pub fn errors(&self) -> &[TokenizeError] {
+        &self.errors
+    }
+
+    pub fn next_token(&mut self) -> io::Result<Token> {
+        if !self.started {
+            while self.window.len() < 3 && !self.at_eof {
+                self.fill()?;
+            }
+            if self.window.starts_with(&[0xEF, 0xBB, 0xBF]) {
+                self.index = 3;
+            }
+            self.started = true;
+        }
+        loop {
+            let mut stream = TokenStream {
+                index: self.index,
+                partial: !self.at_eof,
+                suspended: self.suspended.take(),
+                version: self.version,
+                ..TokenStream::new(&self.window)
+            };
+            let token = stream.resume::<false>();
+            self.index = stream.index;
+            self.suspended = stream.suspended;
+            let base = self.base;
+            self.errors
+                .extend(stream.errors.into_iter().map(|error| TokenizeError {
+                    offset: error.offset + base,
+                    ..error
+                }));
+            if let Some(mut token) = token {
+                token.loc.start += base;
+                token.loc.end += base;
+                return Ok(token);
+            }
+            self.fill()?;
         }
     }
+
+    /// Drops the consumed part of the window and reads the next chunk.
+    fn fill(&mut self) -> io::Result<()> {
+        let keep = match &self.suspended {
+            Some((_, token)) => token.loc.start,
+            None => self.index,
+        };
+        self.window.drain(..keep);
+        self.base += keep;
+        self.index -= keep;
+        if let Some((_, token)) = &mut self.suspended {
+            token.loc.start -= keep;
+        }
+
+        // `read_to_end` only stops short of the limit at the end of input.
+        let read = (&mut self.reader)
+            .take(self.chunk_size as u64)
+            .read_to_end(&mut self.window)?;
+        self.at_eof = read < self.chunk_size;
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for TokenReader<R> {
+    type Item = io::Result<Token>;
+
+    fn next(&mut self) -> Option<io::Result<Token>> {
+        match self.next_token() {
+            Ok(token) if token.tag == Tag::Eof => None,
+            result => Some(result),
+        }
+    }
+}
+
+/// Tokens stored as parallel arrays of tags and start offsets, the same
+/// layout as upstream `Ast.TokenList`: five bytes per token. Ends are not
+/// stored; [`TokenList::token_loc`] recomputes them. Offsets are 32-bit, so
+/// sources are limited to 4 GiB. The final entry is always `Tag::Eof`.
+#[derive(Debug, Clone, Default)]
+pub struct TokenList {
+    tags: Vec<Tag>,
+    starts: Vec<u32>,
+}
+
+impl TokenList {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            tags: Vec::with_capacity(capacity),
+            starts: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn push(&mut self, tag: Tag, start: u32) {
+        self.tags.push(tag);
+        self.starts.push(start);
+    }
+
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    pub fn tags(&self) -> &[Tag] {
+        &self.tags
+    }
+
+    pub fn starts(&self) -> &[u32] {
+        &self.starts
+    }
+
+    pub fn tag(&self, index: usize) -> &Tag {
+        &self.tags[index]
+    }
+
+    pub fn start(&self, index: usize) -> usize {
+        self.starts[index] as usize
+    }
+
+    /// Location of token `index` in `source`, the buffer the list was built
+    /// from. Tags with a fixed lexeme are measured directly; others are
+    /// re-lexed from their start, as upstream's `Ast.tokenSlice` does.
+    pub fn token_loc(&self, source: &[u8], index: usize) -> Loc {
+        let tag = self.tags[index];
+        let start = self.start(index);
+        // `.**` is spelled that way in diagnostics, but the token only
+        // covers the `.*`, so it is re-lexed like the tags without a lexeme.
+        if let Some(lexeme) = tag.lexeme().filter(|_| tag != Tag::InvalidPeriodAsterisks) {
+            return Loc {
+                start,
+                end: start + lexeme.len(),
+            };
+        }
+        if tag == Tag::Eof {
+            return Loc { start, end: start };
+        }
+        let token = TokenStream::at(source, start).next_token();
+        debug_assert_eq!(token.tag, tag);
+        token.loc
+    }
+
+    pub fn token_slice<'a>(&self, source: &'a [u8], index: usize) -> &'a [u8] {
+        let loc = self.token_loc(source, index);
+        &source[loc.start..loc.end]
+    }
+
+    /// Updates the list for `edit` by re-lexing only the tokens it can have
+    /// affected. `source` is the buffer after the edit has been applied.
+    ///
+    /// Lexing restarts at the first token whose scan may have looked at the
+    /// edited bytes (a token reads at most two bytes past its end) and stops
+    /// as soon as a new token starts where an old one did, shifted past the
+    /// edit. From there on the tokens are unchanged apart from their offsets.
+    pub fn apply_edit(&mut self, source: &[u8], edit: &Edit) -> Result<Relexed, SourceTooLarge> {
+        SourceTooLarge::check(source)?;
+        let old_len = self.start(self.len().checked_sub(1).expect("token list ends with Eof"));
+        assert!(edit.loc.start <= edit.loc.end && edit.loc.end <= old_len);
+        assert_eq!(source.len(), old_len - edit.loc.len() + edit.text.len());
+        debug_assert_eq!(
+            &source[edit.loc.start..edit.loc.start + edit.text.len()],
+            edit.text
+        );
+
+        let new_end = edit.loc.start + edit.text.len();
+        let shift = |old_start: u32| (old_start as usize - edit.loc.end + new_end) as u32;
+
+        // The byte order mark is only skipped when lexing from offset 0.
+        let first = if edit.loc.start < 3 {
+            0
+        } else {
+            self.starts[1..].partition_point(|&next| next as usize + 2 <= edit.loc.start)
+        };
+        let mut stream = if first == 0 {
+            TokenStream::new(source)
+        } else {
+            TokenStream::at(source, self.start(first))
+        };
+
+        let mut tags = Vec::new();
+        let mut starts = Vec::new();
+        let resync = loop {
+            let token = stream.next_token();
+            if token.loc.start >= new_end {
+                let old_start = (token.loc.start - new_end + edit.loc.end) as u32;
+                if let Ok(old) = self.starts[first..].binary_search(&old_start) {
+                    break first + old;
+                }
+            }
+            tags.push(token.tag);
+            starts.push(token.loc.start as u32);
+        };
+
+        let inserted = tags.len();
+        self.tags.splice(first..resync, tags);
+        self.starts.splice(first..resync, starts);
+        for start in &mut self.starts[first + inserted..] {
+            *start = shift(*start);
+        }
+        Ok(Relexed {
+            old: first..resync,
+            new: first..first + inserted,
+        })
+    }
+}
+
+/// Replacement of the bytes at `loc` with `text`, in offsets of the buffer
+/// before the edit.
+#[derive(Debug, Clone, Copy)]
+pub struct Edit<'a> {
+    pub loc: Loc,
+    pub text: &'a [u8],
+}
+
+/// Token indices touched by [`TokenList::apply_edit`]: the tokens at `old`
+/// were replaced by the ones now at `new`. Both ranges start at the same
+/// index; tokens after them only moved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relexed {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+/// A source too large for [`TokenList`]'s 32-bit offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceTooLarge {
+    pub len: usize,
+}
+
+impl SourceTooLarge {
+    const MAX_LEN: usize = u32::MAX as usize;
+
+    fn check(source: &[u8]) -> Result<(), SourceTooLarge> {
+        Self::check_len(source.len())
+    }
+
+    fn check_len(len: usize) -> Result<(), SourceTooLarge> {
+        if len > Self::MAX_LEN {
+            return Err(SourceTooLarge { len });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SourceTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "source is {} bytes, more than the {} bytes a token list can address",
+            self.len,
+            Self::MAX_LEN
+        )
+    }
+}
+
+impl std::error::Error for SourceTooLarge {}
+
+pub fn tokenize(buffer: &[u8]) -> Result<TokenList, SourceTooLarge> {
+    SourceTooLarge::check(buffer)?;
+    // Same estimate upstream uses: roughly one token per eight bytes of source.
+    let mut tokens = TokenList::with_capacity(buffer.len() / 8);
+    let mut stream = TokenStream::new(buffer);
+    loop {
+        let token = stream.next_token();
+        tokens.push(token.tag, token.loc.start as u32);
+        if token.tag == Tag::Eof {
+            return Ok(tokens);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic_tokens() {
+        let input = b"fn main() {}";
+        let mut tokenizer = TokenStream::new(input);
+
+        let tokens = vec![
+            Tag::KWFn,
+            Tag::Identifier,
+            Tag::LParen,
+            Tag::RParen,
+            Tag::LBrace,
+            Tag::RBrace,
+            Tag::Eof,
+        ];
+
+        for expected_tag in tokens {
+            let token = tokenizer.next_token();
+            assert_eq!(token.tag, expected_tag);
+        }
+    }
+
+    #[test]
+    fn test_utf8_validation() {
+        fn invalid_utf8_offsets(source: &[u8]) -> Vec<usize> {
+            let mut stream = TokenStream::new(source);
+            while stream.next_token().tag != Tag::Eof {}
+            stream
+                .errors()
+                .iter()
+                .inspect(|error| assert_eq!(error.kind, TokenizeErrorKind::InvalidUtf8))
+                .map(|error| error.offset)
+                .collect()
+        }
+
+        let valid = "// ä€😀\n/// ä€😀\n//! ä€😀\n\"ä€😀\\ä\" 'ä' '😀'\n\\\\ä€😀\n";
+        assert_eq!(invalid_utf8_offsets(valid.as_bytes()), []);
+
+        // Latin-1, stray continuation byte, overlong, surrogate, beyond
+        // U+10FFFF and a sequence cut short by the end of the line.
+        assert_eq!(invalid_utf8_offsets(b"// caf\xe9\n"), [6]);
+        assert_eq!(invalid_utf8_offsets(b"/// \x80\n"), [4]);
+        assert_eq!(invalid_utf8_offsets(b"//! \xc0\x80\n"), [4]);
+        assert_eq!(invalid_utf8_offsets(b"\"\xed\xa0\x80\""), [1]);
+        assert_eq!(invalid_utf8_offsets(b"'\xf4\x90\x80\x80'"), [1]);
+        assert_eq!(invalid_utf8_offsets(b"\\\\ab\xe2\x82\n"), [4]);
+        assert_eq!(invalid_utf8_offsets(b"\"\\\xff\""), [2]);
+
+        let mut stream = TokenStream::new(b"x // \xff\ny");
+        assert_eq!(stream.next_token().tag, Tag::Identifier);
+        assert_eq!(stream.next_token().tag, Tag::Invalid);
+        assert_eq!(stream.next_token().tag, Tag::Identifier);
+    }
+
+    #[test]
+    fn test_errors() {
+        fn errors(source: &[u8]) -> Vec<(TokenizeErrorKind, usize)> {
+            let mut stream = TokenStream::new(source);
+            while stream.next_token().tag != Tag::Eof {}
+            stream.errors().iter().map(|e| (e.kind, e.offset)).collect()
+        }
+
+        use TokenizeErrorKind::*;
+        assert_eq!(errors(b"a # b"), [(InvalidCharacter, 2)]);
+        assert_eq!(errors(b"x = \"abc\ny"), [(UnterminatedStringLiteral, 8)]);
+        assert_eq!(errors(b"x = \"abc"), [(UnterminatedStringLiteral, 8)]);
+        assert_eq!(errors(b"\"abc\\"), [(UnterminatedStringLiteral, 5)]);
+        assert_eq!(errors(b"'a\nb"), [(UnterminatedCharLiteral, 2)]);
+        assert_eq!(errors(b"'"), [(UnterminatedCharLiteral, 1)]);
+        assert_eq!(errors(b"// a\x07b\n"), [(ControlCharacter, 4)]);
+        assert_eq!(errors(b"\"a\tb\""), [(ControlCharacter, 2)]);
+        assert_eq!(errors(b"// a\rb\n"), [(StrayCarriageReturn, 4)]);
+        assert_eq!(errors(b"/// a\rb\n"), [(StrayCarriageReturn, 5)]);
+        assert_eq!(errors(b"\\\\a\rb\n"), [(StrayCarriageReturn, 3)]);
+        assert_eq!(errors(b"// a\r\nb\r\n"), []);
+        assert_eq!(errors(b"x @ y"), [(StrayAtSign, 2)]);
+        assert_eq!(errors(b"x @"), [(StrayAtSign, 2)]);
+        assert_eq!(errors(b"x \\ y"), [(StrayBackslash, 2)]);
+
+        let source = b"const s = \"oops\n;";
+        let mut stream = TokenStream::new(source);
+        let tags: Vec<Tag> = stream.by_ref().map(|t| t.tag).collect();
+        assert_eq!(
+            tags,
+            [
+                Tag::KWConst,
+                Tag::Identifier,
+                Tag::Equal,
+                Tag::Invalid,
+                Tag::Semicolon
+            ]
+        );
+        assert_eq!(
+            stream.errors(),
+            [TokenizeError {
+                kind: UnterminatedStringLiteral,
+                offset: 15,
+            }]
+        );
+        assert_eq!(
+            stream.errors()[0].kind.message(),
+            "unterminated string literal"
+        );
+    }
+
+    #[test]
+    fn test_eof_after_token() {
+        let tags: Vec<Tag> = TokenStream::new(b"x const").map(|t| t.tag).collect();
+        assert_eq!(tags, [Tag::Identifier, Tag::KWConst]);
+        let tags: Vec<Tag> = TokenStream::new(b"!x // comment").map(|t| t.tag).collect();
+        assert_eq!(tags, [Tag::Bang, Tag::Identifier]);
+    }
+
+    #[test]
+    fn test_iterator_stops_before_eof() {
+        let tags: Vec<Tag> = TokenStream::new(b"const x = y;").map(|t| t.tag).collect();
+        assert_eq!(
+            tags,
+            [
+                Tag::KWConst,
+                Tag::Identifier,
+                Tag::Equal,
+                Tag::Identifier,
+                Tag::Semicolon,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_token_list() {
+        let tokens = tokenize(b"a + bc;").unwrap();
+        assert_eq!(
+            tokens.tags(),
+            [
+                Tag::Identifier,
+                Tag::Plus,
+                Tag::Identifier,
+                Tag::Semicolon,
+                Tag::Eof,
+            ]
+        );
+        assert_eq!(tokens.starts(), [0, 2, 4, 6, 7]);
+        assert_eq!(*tokens.tag(2), Tag::Identifier);
+        assert_eq!(tokens.start(2), 4);
+    }
+
+    #[test]
+    fn test_token_loc() {
+        assert_eq!(std::mem::size_of::<Tag>(), 1);
+        let source = include_bytes!("../../../benches/corpus.zig");
+        let tokens = tokenize(source).unwrap();
+        let expected: Vec<Loc> = TokenStream::new(source).map(|token| token.loc).collect();
+        assert_eq!(tokens.len(), expected.len() + 1);
+        for (index, loc) in expected.into_iter().enumerate() {
+            assert_eq!(tokens.token_loc(source, index), loc);
+        }
+        let source = b"\xEF\xBB\xBFx = \"a\" // c\n$";
+        let tokens = tokenize(source).unwrap();
+        let slices: Vec<&[u8]> = (0..tokens.len())
+            .map(|index| tokens.token_slice(source, index))
+            .collect();
+        assert_eq!(slices, [&b"x"[..], b"=", b"\"a\"", b"$", b""]);
+
+        let source = b"a.**b";
+        let tokens = tokenize(source).unwrap();
+        assert_eq!(tokens.tag(1), &Tag::InvalidPeriodAsterisks);
+        assert_eq!(tokens.token_loc(source, 1), Loc { start: 1, end: 3 });
+        assert_eq!(tokens.token_slice(source, 2), b"*");
+
+        let too_large = SourceTooLarge::MAX_LEN + 1;
+        assert_eq!(
+            SourceTooLarge::check_len(too_large),
+            Err(SourceTooLarge { len: too_large })
+        );
+        assert_eq!(SourceTooLarge::check_len(SourceTooLarge::MAX_LEN), Ok(()));
+    }
+
+    /// Port of upstream's `testTokenize`: the tags must match exactly and be
+    /// followed by a zero-length `Eof` at the end of the source.
+    fn test_tokenize(source: impl AsRef<[u8]>, expected: &[Tag]) {
+        let source = source.as_ref();
+        let mut stream = TokenStream::new(source);
+        for expected_tag in expected {
+            let token = stream.next_token();
+            assert_eq!(
+                token.tag,
+                *expected_tag,
+                "in {:?}",
+                String::from_utf8_lossy(source)
+            );
+        }
+        let last = stream.next_token();
+        assert_eq!(
+            last.tag,
+            Tag::Eof,
+            "in {:?}",
+            String::from_utf8_lossy(source)
+        );
+        assert_eq!(last.loc.start, source.len());
+        assert_eq!(last.loc.end, source.len());
+    }
+
+    fn test_number_literals(sources: &[&str]) {
+        for source in sources {
+            test_tokenize(source, &[Tag::NumberLiteral]);
+        }
+    }
+
+    #[test]
+    fn test_keywords() {
+        test_tokenize("test const else", &[Tag::KWTest, Tag::KWConst, Tag::KWElse]);
+    }
+
+    #[test]
+    fn test_line_comment_followed_by_top_level_comptime() {
+        test_tokenize(
+            "// line comment\ncomptime {}\n",
+            &[Tag::KWComptime, Tag::LBrace, Tag::RBrace],
+        );
+    }
+
+    #[test]
+    fn test_unknown_length_pointer_and_then_c_pointer() {
+        test_tokenize(
+            "[*]u8\n[*c]u8",
+            &[
+                Tag::LBrack,
+                Tag::Asterisk,
+                Tag::RBrack,
+                Tag::Identifier,
+                Tag::LBrack,
+                Tag::Asterisk,
+                Tag::Identifier,
+                Tag::RBrack,
+                Tag::Identifier,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_code_point_literal_with_hex_escape() {
+        test_tokenize(r"'\x1b'", &[Tag::CharLiteral]);
+        test_tokenize(r"'\x1'", &[Tag::CharLiteral]);
+    }
+
+    #[test]
+    fn test_newline_in_literals() {
+        test_tokenize("'\n'", &[Tag::Invalid, Tag::Invalid]);
+        test_tokenize("\"\n\"", &[Tag::Invalid, Tag::Invalid]);
+    }
+
+    #[test]
+    fn test_code_point_literal_with_unicode_escapes() {
+        // The tokenizer only finds the end of the literal; whether the escape
+        // is well formed is up to `string_literal::parse_char_literal`.
+        for source in [
+            r"'\u{3}'",
+            r"'\u{01}'",
+            r"'\u{2a}'",
+            r"'\u{3f9}'",
+            r"'\u{6E09aBc1523}'",
+            r"'\u'",
+            r"'\u{{'",
+            r"'\u{}'",
+            r"'\u{s}'",
+            r"'\u{2z}'",
+            r"'\u{4a'",
+            r"'\u0333'",
+            r"'\U0333'",
+        ] {
+            test_tokenize(source, &[Tag::CharLiteral]);
+        }
+        test_tokenize(r#""\u{440}""#, &[Tag::StringLiteral]);
+    }
+
+    #[test]
+    fn test_code_point_literal_with_unicode_code_point() {
+        test_tokenize("'💩'", &[Tag::CharLiteral]);
+    }
+
+    #[test]
+    fn test_float_literal_exponents() {
+        let expected = [
+            Tag::Identifier,
+            Tag::Equal,
+            Tag::NumberLiteral,
+            Tag::Semicolon,
+        ];
+        test_tokenize("a = 4.94065645841246544e-324;\n", &expected);
+        test_tokenize("a = 0x1.a827999fcef32p+1;\n", &expected);
+    }
+
+    #[test]
+    fn test_chars() {
+        test_tokenize("'c'", &[Tag::CharLiteral]);
+    }
+
+    #[test]
+    fn test_invalid_token_characters() {
+        test_tokenize("#", &[Tag::Invalid]);
+        test_tokenize("`", &[Tag::Invalid]);
+        test_tokenize("'c", &[Tag::Invalid]);
+        test_tokenize("'", &[Tag::Invalid]);
+        test_tokenize("''", &[Tag::CharLiteral]);
+        test_tokenize("'\n'", &[Tag::Invalid, Tag::Invalid]);
+    }
+
+    #[test]
+    fn test_invalid_literal_and_comment_characters() {
+        test_tokenize(b"\"\x00\"", &[Tag::Invalid]);
+        test_tokenize(b"`\x00`", &[Tag::Invalid]);
+        test_tokenize(b"//\x00", &[Tag::Invalid]);
+        test_tokenize(b"//\x1f", &[Tag::Invalid]);
+        test_tokenize(b"//\x7f", &[Tag::Invalid]);
+    }
+
+    #[test]
+    fn test_utf8() {
+        test_tokenize(b"//\xc2\x80", &[]);
+        test_tokenize(b"//\xf4\x8f\xbf\xbf", &[]);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        // Upstream leaves these to a later pass and yields no tokens; this
+        // tokenizer validates comments itself, so each becomes `Invalid`.
+        for source in [
+            &b"//\x80"[..],
+            b"//\xbf",
+            b"//\xf8",
+            b"//\xff",
+            b"//\xc2\xc0",
+            b"//\xe0",
+            b"//\xf0",
+            b"//\xf0\x90\x80\xc0",
+        ] {
+            test_tokenize(source, &[Tag::Invalid]);
+        }
+    }
+
+    #[test]
+    fn test_illegal_unicode_codepoints() {
+        // U+0085, U+2028 and U+2029 (and their neighbours) are valid UTF-8.
+        test_tokenize(b"//\xc2\x84", &[]);
+        test_tokenize(b"//\xc2\x85", &[]);
+        test_tokenize(b"//\xc2\x86", &[]);
+        test_tokenize(b"//\xe2\x80\xa7", &[]);
+        test_tokenize(b"//\xe2\x80\xa8", &[]);
+        test_tokenize(b"//\xe2\x80\xa9", &[]);
+        test_tokenize(b"//\xe2\x80\xaa", &[]);
+    }
+
+    #[test]
+    fn test_string_identifier_and_builtin_fns() {
+        test_tokenize(
+            r#"const @"if" = @import("std");"#,
+            &[
+                Tag::KWConst,
+                Tag::Identifier,
+                Tag::Equal,
+                Tag::Builtin,
+                Tag::LParen,
+                Tag::StringLiteral,
+                Tag::RParen,
+                Tag::Semicolon,
+            ],
+        );
+        test_tokenize(r#"@"a b\"c""#, &[Tag::Identifier]);
+        test_tokenize(r#"@"a"#, &[Tag::Invalid]);
+    }
+
+    #[test]
+    fn test_pipe_and_then_invalid() {
+        test_tokenize("||=", &[Tag::PipePipe, Tag::Equal]);
+    }
+
+    #[test]
+    fn test_line_comment_and_doc_comment() {
+        test_tokenize("//", &[]);
+        test_tokenize("// a / b", &[]);
+        test_tokenize("// /", &[]);
+        test_tokenize("/// a", &[Tag::DocComment]);
+        test_tokenize("///", &[Tag::DocComment]);
+        test_tokenize("////", &[]);
+        test_tokenize("//!", &[Tag::ContainerDocComment]);
+        test_tokenize("//!!", &[Tag::ContainerDocComment]);
+    }
+
+    #[test]
+    fn test_line_comment_followed_by_identifier() {
+        test_tokenize(
+            "    Unexpected,\n    // another\n    Another,",
+            &[Tag::Identifier, Tag::Comma, Tag::Identifier, Tag::Comma],
+        );
+    }
+
+    #[test]
+    fn test_utf8_bom_is_recognized_and_skipped() {
+        test_tokenize(b"\xEF\xBB\xBFa;\n", &[Tag::Identifier, Tag::Semicolon]);
+    }
+
+    #[test]
+    fn test_correctly_parse_pointer_assignment() {
+        test_tokenize(
+            "b.*=3;\n",
+            &[
+                Tag::Identifier,
+                Tag::PeriodAsterisk,
+                Tag::Equal,
+                Tag::NumberLiteral,
+                Tag::Semicolon,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_correctly_parse_pointer_dereference_followed_by_asterisk() {
+        test_tokenize(
+            "\"b\".* ** 10",
+            &[
+                Tag::StringLiteral,
+                Tag::PeriodAsterisk,
+                Tag::AsteriskAsterisk,
+                Tag::NumberLiteral,
+            ],
+        );
+        test_tokenize(
+            "(\"b\".*)** 10",
+            &[
+                Tag::LParen,
+                Tag::StringLiteral,
+                Tag::PeriodAsterisk,
+                Tag::RParen,
+                Tag::AsteriskAsterisk,
+                Tag::NumberLiteral,
+            ],
+        );
+        test_tokenize(
+            "\"b\".*** 10",
+            &[
+                Tag::StringLiteral,
+                Tag::InvalidPeriodAsterisks,
+                Tag::AsteriskAsterisk,
+                Tag::NumberLiteral,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_range_literals() {
+        for (source, operand) in [
+            ("0...9", Tag::NumberLiteral),
+            ("'0'...'9'", Tag::CharLiteral),
+            ("0x00...0x09", Tag::NumberLiteral),
+            ("0b00...0b11", Tag::NumberLiteral),
+            ("0o00...0o11", Tag::NumberLiteral),
+        ] {
+            test_tokenize(source, &[operand, Tag::Ellipsis3, operand]);
+        }
+        test_tokenize(
+            "1..2",
+            &[Tag::NumberLiteral, Tag::Ellipsis2, Tag::NumberLiteral],
+        );
+    }
+
+    #[test]
+    fn test_number_literals_decimal() {
+        test_number_literals(&["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"]);
+        test_tokenize("1..", &[Tag::NumberLiteral, Tag::Ellipsis2]);
+        test_number_literals(&["0a", "9b", "1z", "1z_1", "9z3"]);
+
+        test_number_literals(&[
+            "0_0",
+            "0001",
+            "01234567890",
+            "012_345_6789_0",
+            "0_1_2_3_4_5_6_7_8_9_0",
+        ]);
+
+        test_number_literals(&["00_", "0_0_", "0__0", "0_0f", "0_0_f", "0_0_f_00"]);
+        test_tokenize("1_,", &[Tag::NumberLiteral, Tag::Comma]);
+
+        test_number_literals(&[
+            "0.0",
+            "1.0",
+            "10.0",
+            "0e0",
+            "1e0",
+            "1e100",
+            "1.0e100",
+            "1.0e+100",
+            "1.0e-100",
+            "1_0_0_0.0_0_0_0_0_1e1_0_0_0",
+        ]);
+
+        test_tokenize("1.", &[Tag::NumberLiteral, Tag::Period]);
+        test_number_literals(&["1e", "1.e100", "1.0e1f0", "1.0p100", "1.0p-100", "1.0p1f0"]);
+        test_tokenize("1.0_,", &[Tag::NumberLiteral, Tag::Comma]);
+        test_number_literals(&["1_.0", "1._", "1.a", "1.z", "1._0"]);
+        test_tokenize("1.+", &[Tag::NumberLiteral, Tag::Period, Tag::Plus]);
+        test_tokenize("1._+", &[Tag::NumberLiteral, Tag::Plus]);
+        test_number_literals(&["1._e", "1.0e"]);
+        test_tokenize("1.0e,", &[Tag::NumberLiteral, Tag::Comma]);
+        test_number_literals(&["1.0e_", "1.0e+_", "1.0e-_"]);
+        test_tokenize("1.0e0_+", &[Tag::NumberLiteral, Tag::Plus]);
+    }
+
+    #[test]
+    fn test_number_literals_binary() {
+        test_number_literals(&[
+            "0b0", "0b1", "0b2", "0b3", "0b4", "0b5", "0b6", "0b7", "0b8", "0b9", "0ba", "0bb",
+            "0bc", "0bd", "0be", "0bf", "0bz",
+        ]);
+
+        test_number_literals(&[
+            "0b0000_0000",
+            "0b1111_1111",
+            "0b10_10_10_10",
+            "0b0_1_0_1_0_1_0_1",
+        ]);
+        test_tokenize("0b1.", &[Tag::NumberLiteral, Tag::Period]);
+        test_number_literals(&["0b1.0"]);
+
+        test_number_literals(&[
+            "0B0", "0b_", "0b_0", "0b1_", "0b0__1", "0b0_1_", "0b1e", "0b1p", "0b1e0", "0b1p0",
+        ]);
+        test_tokenize("0b1_,", &[Tag::NumberLiteral, Tag::Comma]);
+    }
+
+    #[test]
+    fn test_number_literals_octal() {
+        test_number_literals(&[
+            "0o0", "0o1", "0o2", "0o3", "0o4", "0o5", "0o6", "0o7", "0o8", "0o9", "0oa", "0ob",
+            "0oc", "0od", "0oe", "0of", "0oz",
+        ]);
+
+        test_number_literals(&[
+            "0o01234567",
+            "0o0123_4567",
+            "0o01_23_45_67",
+            "0o0_1_2_3_4_5_6_7",
+        ]);
+        test_tokenize("0o7.", &[Tag::NumberLiteral, Tag::Period]);
+        test_number_literals(&["0o7.0"]);
+
+        test_number_literals(&[
+            "0O0", "0o_", "0o_0", "0o1_", "0o0__1", "0o0_1_", "0o1e", "0o1p", "0o1e0", "0o1p0",
+        ]);
+        test_tokenize("0o_,", &[Tag::NumberLiteral, Tag::Comma]);
+    }
+
+    #[test]
+    fn test_number_literals_hexadecimal() {
+        test_number_literals(&[
+            "0x0", "0x1", "0x2", "0x3", "0x4", "0x5", "0x6", "0x7", "0x8", "0x9", "0xa", "0xb",
+            "0xc", "0xd", "0xe", "0xf", "0xA", "0xB", "0xC", "0xD", "0xE", "0xF", "0x0z", "0xz",
+        ]);
+
+        test_number_literals(&[
+            "0x0123456789ABCDEF",
+            "0x0123_4567_89AB_CDEF",
+            "0x01_23_45_67_89AB_CDE_F",
+            "0x0_1_2_3_4_5_6_7_8_9_A_B_C_D_E_F",
+        ]);
+
+        test_number_literals(&["0X0", "0x_", "0x_1", "0x1_", "0x0__1", "0x0_1_"]);
+        test_tokenize("0x_,", &[Tag::NumberLiteral, Tag::Comma]);
+
+        test_number_literals(&[
+            "0x1.0", "0xF.0", "0xF.F", "0xF.Fp0", "0xF.FP0", "0x1p0", "0xfp0",
+        ]);
+        test_tokenize(
+            "0x1.0+0xF.0",
+            &[Tag::NumberLiteral, Tag::Plus, Tag::NumberLiteral],
+        );
+
+        test_tokenize("0x1.", &[Tag::NumberLiteral, Tag::Period]);
+        test_tokenize("0xF.", &[Tag::NumberLiteral, Tag::Period]);
+        test_tokenize(
+            "0x1.+0xF.",
+            &[
+                Tag::NumberLiteral,
+                Tag::Period,
+                Tag::Plus,
+                Tag::NumberLiteral,
+                Tag::Period,
+            ],
+        );
+        test_number_literals(&["0xff.p10"]);
+
+        test_number_literals(&[
+            "0x0123456.789ABCDEF",
+            "0x0_123_456.789_ABC_DEF",
+            "0x0_1_2_3_4_5_6.7_8_9_A_B_C_D_E_F",
+            "0x0p0",
+            "0x0.0p0",
+            "0xff.ffp10",
+            "0xff.ffP10",
+            "0xffp10",
+            "0xff_ff.ff_ffp1_0_0_0",
+            "0xf_f_f_f.f_f_f_fp+1_000",
+            "0xf_f_f_f.f_f_f_fp-1_00_0",
+        ]);
+
+        test_number_literals(&[
+            "0x1e",
+            "0x1e0",
+            "0x1p",
+            "0xfp0z1",
+            "0xff.ffpff",
+            "0x0.p",
+            "0x0.z",
+            "0x0._",
+            "0x0_.0",
+        ]);
+        test_tokenize(
+            "0x0_.0.0",
+            &[Tag::NumberLiteral, Tag::Period, Tag::NumberLiteral],
+        );
+        test_number_literals(&[
+            "0x0._0",
+            "0x0.0_",
+            "0x0_p0",
+            "0x0_.p0",
+            "0x0._p0",
+            "0x0.0_p0",
+            "0x0._0p0",
+            "0x0.0p_0",
+            "0x0.0p+_0",
+            "0x0.0p-_0",
+            "0x0.0p0_",
+        ]);
+    }
+
+    #[test]
+    fn test_multi_line_string_literal_with_only_1_backslash() {
+        test_tokenize("x \\\n;", &[Tag::Identifier, Tag::Invalid, Tag::Semicolon]);
+    }
+
+    #[test]
+    fn test_invalid_builtin_identifiers() {
+        test_tokenize("@()", &[Tag::Invalid]);
+        test_tokenize("@0()", &[Tag::Invalid]);
+    }
+
+    #[test]
+    fn test_invalid_token_with_unfinished_escape_right_before_eof() {
+        test_tokenize("\"\\", &[Tag::Invalid]);
+        test_tokenize("'\\", &[Tag::Invalid]);
+        test_tokenize("'\\u", &[Tag::Invalid]);
+    }
+
+    #[test]
+    fn test_saturating_operators() {
+        test_tokenize("<<", &[Tag::AngleBrackAngleBrackLeft]);
+        test_tokenize("<<|", &[Tag::AngleBrackAngleBrackLeftPipe]);
+        test_tokenize("<<|=", &[Tag::AngleBrackAngleBrackLeftPipeEqual]);
+
+        test_tokenize("*", &[Tag::Asterisk]);
+        test_tokenize("*|", &[Tag::AsteriskPipe]);
+        test_tokenize("*|=", &[Tag::AsteriskPipeEqual]);
+
+        test_tokenize("+", &[Tag::Plus]);
+        test_tokenize("+|", &[Tag::PlusPipe]);
+        test_tokenize("+|=", &[Tag::PlusPipeEqual]);
+
+        test_tokenize("-", &[Tag::Minus]);
+        test_tokenize("-|", &[Tag::MinusPipe]);
+        test_tokenize("-|=", &[Tag::MinusPipeEqual]);
+    }
+
+    #[test]
+    fn test_null_byte_before_eof() {
+        test_tokenize(b"123 \x00 456", &[Tag::NumberLiteral, Tag::Invalid]);
+        test_tokenize(b"//\x00", &[Tag::Invalid]);
+        test_tokenize(b"\\\\\x00", &[Tag::Invalid]);
+        test_tokenize(b"\x00", &[Tag::Invalid]);
+        test_tokenize(b"// NUL\x00\n", &[Tag::Invalid]);
+        test_tokenize(b"///\x00\n", &[Tag::DocComment, Tag::Invalid]);
+        test_tokenize(b"/// NUL\x00\n", &[Tag::DocComment, Tag::Invalid]);
+    }
+
+    #[test]
+    fn test_invalid_tabs_and_carriage_returns() {
+        // Tabs are allowed in comments but not in multiline string literals;
+        // CR is only allowed directly before a newline.
+        test_tokenize("//\t", &[]);
+        test_tokenize("// \t", &[]);
+        test_tokenize("///\t", &[Tag::DocComment]);
+        test_tokenize("/// \t", &[Tag::DocComment]);
+        test_tokenize("//!\t", &[Tag::ContainerDocComment]);
+        test_tokenize("//! \t", &[Tag::ContainerDocComment]);
+
+        for source in [
+            "//\r", "// \r", "///\r", "/// \r", "//\r ", "// \r ", "///\r ", "/// \r ", "//!\r",
+            "//! \r", "//!\r ", "//! \r ",
+        ] {
+            test_tokenize(source, &[Tag::Invalid]);
+        }
+        test_tokenize("//\r\n", &[]);
+        test_tokenize("// \r\n", &[]);
+        test_tokenize("///\r\n", &[Tag::DocComment]);
+        test_tokenize("/// \r\n", &[Tag::DocComment]);
+        test_tokenize("//!\r\n", &[Tag::ContainerDocComment]);
+        test_tokenize("//! \r\n", &[Tag::ContainerDocComment]);
+
+        for source in [
+            "\\\\\r", "\\\\\r ", "\\\\ \r", "\\\\\t", "\\\\\t ", "\\\\ \t",
+        ] {
+            test_tokenize(source, &[Tag::Invalid]);
+        }
+        test_tokenize("\\\\\r\n", &[Tag::MultilineStringLiteralLine]);
+
+        test_tokenize(" \tpub\tswitch\t", &[Tag::KWPub, Tag::KWSwitch]);
+        test_tokenize(" \rpub\rswitch\r", &[Tag::KWPub, Tag::KWSwitch]);
+    }
+
+    #[test]
+    fn test_lexemes_round_trip() {
+        for &tag in Tag::ALL {
+            let Some(lexeme) = tag.lexeme() else {
+                continue;
+            };
+            // `.**` is how the tag is spelled in diagnostics, but the
+            // tokenizer reports it as `.*` followed by `*`.
+            if tag == Tag::InvalidPeriodAsterisks {
+                test_tokenize(lexeme, &[tag, Tag::Asterisk]);
+                continue;
+            }
+            test_tokenize(lexeme, &[tag]);
+        }
+    }
+
+    #[test]
+    fn test_tag_metadata() {
+        for (i, &tag) in Tag::ALL.iter().enumerate() {
+            assert_eq!(tag as usize, i);
+            assert_eq!(
+                tag.is_keyword(),
+                parse_keyword(tag.symbol().as_bytes()) == Some(tag)
+            );
+            if tag.is_assignment_op() {
+                assert!(tag.lexeme().unwrap().ends_with('='));
+                assert_eq!(tag.binary_precedence(), None);
+            }
+        }
+        assert_eq!(Tag::KWAddrspace as u8, 73);
+        assert_eq!(Tag::ALL.iter().filter(|tag| tag.is_keyword()).count(), 49);
+        assert_eq!(KEYWORDS.len(), 49);
+        assert_eq!(
+            Tag::ALL.iter().filter(|tag| tag.is_assignment_op()).count(),
+            18
+        );
+        assert!(!Tag::EqualEqual.is_assignment_op());
+        assert!(Tag::AngleBrackAngleBrackLeftPipeEqual.is_assignment_op());
+        assert_eq!(Tag::KWOr.binary_precedence(), Some(10));
+        assert_eq!(Tag::KWOrelse.binary_precedence(), Some(40));
+        assert_eq!(Tag::PipePipe.binary_precedence(), Some(70));
+        assert_eq!(Tag::Bang.binary_precedence(), None);
+        assert_eq!(Tag::LParen.category(), Category::Punctuation);
+        assert_eq!(Tag::DocComment.category(), Category::DocComment);
+        assert_eq!(parse_keyword(b".**"), None);
+    }
+
+    /// Properties from upstream's fuzz test, checked over a fixed set of
+    /// pseudo-random inputs drawn from bytes the tokenizer cares about.
+    #[test]
+    fn test_properties_upheld() {
+        const ALPHABET: &[u8] = b" \t\r\n\x00\x7f\xc3\xa4\xff\"'\\/!@.*+-|<=>_0xe9aZ";
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..2000 {
+            let len = (next() % 24) as usize;
+            let source: Vec<u8> = (0..len)
+                .map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize])
+                .collect();
+            let mut stream = TokenStream::new(&source);
+            let mut previous_end = 0;
+            loop {
+                let token = stream.next_token();
+                assert!(token.loc.start >= previous_end, "{source:?}");
+                assert!(token.loc.end >= token.loc.start, "{source:?}");
+                previous_end = token.loc.end;
+                match token.tag {
+                    Tag::Invalid => assert!(
+                        matches!(source.get(token.loc.end), None | Some(b'\n') | Some(0)),
+                        "{source:?}"
+                    ),
+                    Tag::Eof => {
+                        assert_eq!(token.loc.start, source.len(), "{source:?}");
+                        assert_eq!(token.loc.end, source.len(), "{source:?}");
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+
+            let rebuilt: Vec<u8> = TokenStream::with_trivia(&source)
+                .flat_map(|token| &source[token.loc.start..token.loc.end])
+                .copied()
+                .collect();
+            assert_eq!(rebuilt, source);
+        }
+    }
+
+    #[test]
+    fn test_trivia_tokens() {
+        let source = b"const a = 1; // one\r\n\n\t/// doc\n  // two";
+        let tokens: Vec<Token> = TokenStream::with_trivia(source).collect();
+        let tags: Vec<Tag> = tokens.iter().map(|t| t.tag).collect();
+        assert_eq!(
+            tags,
+            [
+                Tag::KWConst,
+                Tag::Whitespace,
+                Tag::Identifier,
+                Tag::Whitespace,
+                Tag::Equal,
+                Tag::Whitespace,
+                Tag::NumberLiteral,
+                Tag::Semicolon,
+                Tag::Whitespace,
+                Tag::LineComment,
+                Tag::Newline,
+                Tag::Newline,
+                Tag::Whitespace,
+                Tag::DocComment,
+                Tag::Newline,
+                Tag::Whitespace,
+                Tag::LineComment,
+            ]
+        );
+        assert_eq!(tokens[9].loc, Loc { start: 13, end: 19 });
+        assert_eq!(tokens[10].loc, Loc { start: 19, end: 21 });
+    }
+
+    #[test]
+    fn test_trivia_round_trip() {
+        let sources: [&[u8]; 6] = [
+            b"\xEF\xBB\xBFpub fn main() void {}\n",
+            b"//! top\n\n// a\r\n//\n////\nconst x = \"y\"; // z",
+            b"  \r \t\n\n",
+            b"x // caf\xe9\ny",
+            b"a /* b */ c",
+            b"\\\\ line\r\n\\\\ two\n;",
+        ];
+        for source in sources {
+            let mut rebuilt = Vec::new();
+            let mut stream = TokenStream::with_trivia(source);
+            loop {
+                let token = stream.next_token();
+                assert_eq!(token.loc.start, rebuilt.len());
+                rebuilt.extend_from_slice(&source[token.loc.start..token.loc.end]);
+                if token.tag == Tag::Eof {
+                    break;
+                }
+            }
+            assert_eq!(rebuilt, source);
+        }
+    }
+
+    #[test]
+    fn test_apply_edit() {
+        let source = b"const x = 1;\nconst y = x + 2;\n";
+        let mut tokens = tokenize(source).unwrap();
+        let edit = Edit {
+            loc: Loc { start: 19, end: 20 },
+            text: b"yy",
+        };
+        let edited = b"const x = 1;\nconst yy = x + 2;\n";
+        let relexed = tokens.apply_edit(edited, &edit).unwrap();
+        assert_eq!(
+            relexed,
+            Relexed {
+                old: 5..7,
+                new: 5..7
+            }
+        );
+        assert_eq!(tokens.tags(), tokenize(edited).unwrap().tags());
+        assert_eq!(tokens.starts(), tokenize(edited).unwrap().starts());
+
+        // Opening a string swallows the rest of the line.
+        let edit = Edit {
+            loc: Loc { start: 10, end: 10 },
+            text: b"\"",
+        };
+        let edited = b"const x = \"1;\nconst yy = x + 2;\n";
+        let relexed = tokens.apply_edit(edited, &edit).unwrap();
+        assert_eq!(
+            relexed,
+            Relexed {
+                old: 2..5,
+                new: 2..4
+            }
+        );
+        assert_eq!(*tokens.tag(3), Tag::Invalid);
+        assert_eq!(tokens.starts(), tokenize(edited).unwrap().starts());
+    }
+
+    #[test]
+    fn test_apply_edit_matches_full_tokenize() {
+        const SNIPPETS: &[&[u8]] = &[
+            b"",
+            b" ",
+            b"\n",
+            b"\r\n",
+            b"\"",
+            b"'",
+            b"//",
+            b"/",
+            b"\\\\",
+            b".",
+            b"*",
+            b"1",
+            b"e",
+            b"x",
+            b"@",
+            b"\xef\xbb\xbf",
+            b"\xc3\xa4",
+            b"=",
+            b"|",
+        ];
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut next = |bound: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % bound as u64) as usize
+        };
+        let mut source =
+            b"const s = \"a\\\"b\"; // c\n/// d\nx.* = 1.e2..0x1p-3;\n\\\\ m\n".to_vec();
+        let mut tokens = tokenize(&source).unwrap();
+        for _ in 0..3000 {
+            let start = next(source.len() + 1);
+            let end = start + next(source.len() - start + 1).min(4);
+            let text = SNIPPETS[next(SNIPPETS.len())];
+            source.splice(start..end, text.iter().copied());
+            let relexed = tokens
+                .apply_edit(
+                    &source,
+                    &Edit {
+                        loc: Loc { start, end },
+                        text,
+                    },
+                )
+                .unwrap();
+            let expected = tokenize(&source).unwrap();
+            assert_eq!(tokens.tags(), expected.tags(), "{source:?}");
+            assert_eq!(tokens.starts(), expected.starts(), "{source:?}");
+            assert!(relexed.new.end < tokens.len());
+        }
+    }
+
+    #[test]
+    fn test_printable_run() {
+        fn naive(bytes: &[u8], quote: Option<u8>) -> usize {
+            bytes
+                .iter()
+                .position(|&b| {
+                    !(0x20..=0x7e).contains(&b)
+                        || Some(b) == quote
+                        || (quote.is_some() && b == b'\\')
+                })
+                .unwrap_or(bytes.len())
+        }
+        let mut state: u64 = 0x1234_5678_9abc_def1;
+        for _ in 0..5000 {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let len = (state % 40) as usize;
+            let stop =
+                [b'\n', b'"', b'\\', 0x7f, 0x80, 0x1f, b'~', b' '][(state >> 8) as usize % 8];
+            let mut bytes = vec![b'a'; len];
+            if len > 0 {
+                bytes[(state >> 16) as usize % len] = stop;
+            }
+            for quote in [None, Some(b'"'), Some(b'\'')] {
+                assert_eq!(
+                    printable_run(&bytes, quote),
+                    naive(&bytes, quote),
+                    "{bytes:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_token_reader_matches_token_stream() {
+        let sources: [&[u8]; 6] = [
+            b"\xEF\xBB\xBFconst std = @import(\"std\");\n",
+            b"//! doc\n/// d\xc3\xa4\r\nfn f() void { return 0x1.8p3 ... 1e10; } // \xf0\x9f\x98\x80\n",
+            b"x = \"caf\xe9\"; y = '\\''; \\\\ line\r\n @\"a b\" .** 1..2",
+            b"a\rb // \x07\n unreachable usingnamespace",
+            b"\xEF\xBB",
+            include_bytes!("../../../benches/corpus.zig"),
+        ];
+        for source in sources {
+            let mut expected = TokenStream::new(source);
+            let expected_tokens: Vec<(Tag, Loc)> = expected
+                .by_ref()
+                .map(|token| (token.tag, token.loc))
+                .collect();
+            for step in [1, 2, 3, 5, 4096] {
+                let mut reader = TokenReader::with_chunk_size(source, step);
+                let tokens: Vec<(Tag, Loc)> = reader
+                    .by_ref()
+                    .map(|token| token.map(|token| (token.tag, token.loc)))
+                    .collect::<io::Result<_>>()
+                    .unwrap();
+                assert_eq!(tokens, expected_tokens, "step {step}");
+                assert_eq!(reader.errors(), expected.errors(), "step {step}");
+                let eof = reader.next_token().unwrap();
+                assert_eq!(
+                    eof.loc,
+                    Loc {
+                        start: source.len(),
+                        end: source.len()
+                    }
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_sentinel_matches_upstream() {
+        fn tags(source: &[u8]) -> Vec<Tag> {
+            let mut stream = TokenStream::with_sentinel(source);
+            let tags = stream.by_ref().map(|token| token.tag).collect();
+            assert_eq!(stream.next_token().loc.start, source.len() - 1);
+            tags
+        }
+
+        // Upstream's "invalid utf8" cases, which produce no tokens there.
+        for source in [
+            &b"//\x80\x00"[..],
+            b"//\xbf\x00",
+            b"//\xf8\x00",
+            b"//\xff\x00",
+            b"//\xc2\xc0\x00",
+            b"//\xe0\x00",
+            b"//\xf0\x00",
+            b"//\xf0\x90\x80\xc0\x00",
+        ] {
+            assert_eq!(tags(source), []);
+        }
+        assert_eq!(
+            tags(b"\"\xff\" '\xc0'\x00"),
+            [Tag::StringLiteral, Tag::CharLiteral]
+        );
+        assert_eq!(
+            tags(b"\xEF\xBB\xBFa;\x00"),
+            [Tag::Identifier, Tag::Semicolon]
+        );
+
+        // Embedded NULs are invalid, the sentinel is not.
+        assert_eq!(
+            tags(b"123 \x00 456\x00"),
+            [Tag::NumberLiteral, Tag::Invalid]
+        );
+        assert_eq!(tags(b"///\x00\n\x00"), [Tag::DocComment, Tag::Invalid]);
+        assert_eq!(tags(b"\x00"), []);
+    }
+
+    #[test]
+    fn test_sentinel_matches_token_stream() {
+        // Cutting ASCII sources at every byte ends the input in every state,
+        // which sentinel mode detects in its NUL arms.
+        let sources: [&[u8]; 3] = [
+            b"//! doc\n/// d\r\nfn f() void { return 0x1.8p-3 ... 1e+10; } // c\n",
+            b"x = \"a\\\"\"; y = '\\''; \\\\ line\r\n @\"a b\" @b .** 1..2 a.* \r\x07",
+            b"a <<|= b >>= c *%= d +| e -% f |= g != h => i -> j ^ k ~ l & m /= n",
+        ];
+        for source in sources {
+            for end in 0..=source.len() {
+                let prefix = &source[..end];
+                let mut terminated = prefix.to_vec();
+                terminated.push(0);
+
+                let mut expected = TokenStream::new(prefix);
+                let mut stream = TokenStream::with_sentinel(&terminated);
+                loop {
+                    let token = stream.next_token();
+                    let expected_token = expected.next_token();
+                    assert_eq!(
+                        (token.tag, token.loc),
+                        (expected_token.tag, expected_token.loc),
+                        "{:?}",
+                        prefix.escape_ascii()
+                    );
+                    if token.tag == Tag::Eof {
+                        break;
+                    }
+                }
+                assert_eq!(stream.errors(), expected.errors());
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "buffer must end with a NUL")]
+    fn test_sentinel_required() {
+        TokenStream::with_sentinel(b"const");
+    }
 }
@@ -0,0 +1,480 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use zig_in_rust::zig::language_version::LanguageVersion;
+use zig_in_rust::zig::line_index::LineIndex;
+use zig_in_rust::zig::token_dump::{self, Format};
+use zig_in_rust::zig::tokenizer::{Tag, Token, TokenStream};
+
+const USAGE: &str = "\
+Usage: zig-in-rust tokenize [options] [path ...]
+
+Tokenizes each file, or every .zig file under each directory, and prints the
+tokens with one-based line:column positions. Reads stdin when no path or `-`
+is given.
+
+Options:
+  -f, --format <fmt>   human (default), json or csv
+      --stats          print a tag histogram instead of the tokens
+      --trivia         include whitespace, newline and comment tokens
+      --zig <version>  language version: 0.11 to 0.14 or master (default 0.14)
+  -h, --help           print this help
+";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Human,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    format: OutputFormat,
+    stats: bool,
+    trivia: bool,
+    version: LanguageVersion,
+    paths: Vec<PathBuf>,
+}
+
+enum Command {
+    Tokenize(Options),
+    Help,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Command, String> {
+    match args.next().as_deref() {
+        Some("tokenize") => {}
+        Some("-h" | "--help") => return Ok(Command::Help),
+        Some(other) => return Err(format!("unknown command `{other}`")),
+        None => return Err("missing command".into()),
+    }
+    let mut options = Options {
+        format: OutputFormat::Human,
+        stats: false,
+        trivia: false,
+        version: LanguageVersion::default(),
+        paths: Vec::new(),
+    };
+    let mut only_paths = false;
+    while let Some(arg) = args.next() {
+        if only_paths || arg == "-" || !arg.starts_with('-') {
+            options.paths.push(PathBuf::from(arg));
+            continue;
+        }
+        let (name, inline_value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_owned())),
+            None => (arg.as_str(), None),
+        };
+        let mut value = |name: &str| {
+            inline_value
+                .clone()
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("`{name}` needs a value"))
+        };
+        match name {
+            "--" => only_paths = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-f" | "--format" => {
+                options.format = match value(name)?.as_str() {
+                    "human" => OutputFormat::Human,
+                    "json" => OutputFormat::Json,
+                    "csv" => OutputFormat::Csv,
+                    other => return Err(format!("unknown format `{other}`")),
+                }
+            }
+            "--zig" => {
+                let version = value(name)?;
+                options.version = LanguageVersion::from_name(&version)
+                    .ok_or_else(|| format!("unknown Zig version `{version}`"))?;
+            }
+            "--stats" => options.stats = true,
+            "--trivia" => options.trivia = true,
+            _ => return Err(format!("unknown option `{arg}`")),
+        }
+    }
+    if options.paths.is_empty() {
+        options.paths.push(PathBuf::from("-"));
+    }
+    Ok(Command::Tokenize(options))
+}
+
+/// Expands directories into the `.zig` files below them, sorted so output
+/// is stable. Hidden entries, such as `.zig-cache`, are skipped. Symlinks
+/// are followed, but each directory is walked at most once, by canonical
+/// path, so a link back up the tree cannot loop.
+fn collect_inputs(
+    path: &Path,
+    inputs: &mut Vec<PathBuf>,
+    visited: &mut HashSet<PathBuf>,
+) -> io::Result<()> {
+    if path.as_os_str() == "-" || !path.is_dir() {
+        inputs.push(path.to_owned());
+        return Ok(());
+    }
+    if !visited.insert(fs::canonicalize(path)?) {
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        let hidden = entry
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if hidden {
+            continue;
+        }
+        if entry.is_dir() {
+            collect_inputs(&entry, inputs, visited)?;
+        } else if entry.extension().is_some_and(|ext| ext == "zig") {
+            inputs.push(entry);
+        }
+    }
+    Ok(())
+}
+
+fn read_input(path: &Path) -> io::Result<Vec<u8>> {
+    if path.as_os_str() == "-" {
+        let mut source = Vec::new();
+        io::stdin().lock().read_to_end(&mut source)?;
+        Ok(source)
+    } else {
+        fs::read(path)
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    files: usize,
+    bytes: usize,
+    errors: usize,
+    counts: Vec<(Tag, usize)>,
+}
+
+impl Stats {
+    fn add(&mut self, tag: &Tag) {
+        match self.counts.iter_mut().find(|(probe, _)| probe == tag) {
+            Some((_, count)) => *count += 1,
+            None => self.counts.push((*tag, 1)),
+        }
+    }
+
+    fn write(mut self, out: &mut impl Write, format: OutputFormat) -> io::Result<()> {
+        self.counts
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.name().cmp(b.0.name())));
+        let tokens: usize = self.counts.iter().map(|(_, count)| count).sum();
+        match format {
+            OutputFormat::Human => {
+                writeln!(
+                    out,
+                    "files: {}, bytes: {}, tokens: {}, errors: {}",
+                    self.files, self.bytes, tokens, self.errors
+                )?;
+                let max = self.counts.first().map_or(1, |(_, count)| *count);
+                let width = self
+                    .counts
+                    .iter()
+                    .map(|(tag, _)| tag.name().len())
+                    .max()
+                    .unwrap_or(0);
+                for (tag, count) in &self.counts {
+                    writeln!(
+                        out,
+                        "{:width$}  {:>8}  {:>5.1}%  {}",
+                        tag.name(),
+                        count,
+                        *count as f64 * 100.0 / tokens as f64,
+                        "#".repeat((count * 40).div_ceil(max)),
+                    )?;
+                }
+                Ok(())
+            }
+            OutputFormat::Json => {
+                write!(
+                    out,
+                    r#"{{"files":{},"bytes":{},"tokens":{},"errors":{},"tags":{{"#,
+                    self.files, self.bytes, tokens, self.errors
+                )?;
+                for (i, (tag, count)) in self.counts.iter().enumerate() {
+                    let comma = if i == 0 { "" } else { "," };
+                    write!(out, r#"{comma}"{}":{count}"#, tag.name())?;
+                }
+                writeln!(out, "}}}}")
+            }
+            OutputFormat::Csv => {
+                writeln!(out, "tag,count")?;
+                for (tag, count) in &self.counts {
+                    writeln!(out, "{},{count}", tag.name())?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn display_path(path: &Path) -> String {
+    if path.as_os_str() == "-" {
+        "<stdin>".into()
+    } else {
+        path.display().to_string()
+    }
+}
+
+/// Quotes a CSV field when it contains a delimiter, quote or line break.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn write_tokens(
+    out: &mut impl Write,
+    path: &str,
+    source: &[u8],
+    tokens: Vec<Token>,
+    format: OutputFormat,
+) -> io::Result<()> {
+    let lines = LineIndex::new(source);
+    match format {
+        OutputFormat::Human => {
+            for token in tokens {
+                let text = &source[token.loc.start..token.loc.end];
+                writeln!(
+                    out,
+                    "{:<10}{:<32}\"{}\"",
+                    lines.location(token.loc.start).to_string(),
+                    token.tag.name(),
+                    text.escape_ascii()
+                )?;
+            }
+            Ok(())
+        }
+        OutputFormat::Json => {
+            token_dump::write_tokens_with_lines(out, tokens, Format::Json, &lines)
+        }
+        OutputFormat::Csv => {
+            let path = csv_field(path);
+            for token in tokens {
+                let location = lines.location(token.loc.start);
+                let text = String::from_utf8_lossy(&source[token.loc.start..token.loc.end]);
+                writeln!(
+                    out,
+                    "{path},{},{},{},{},{},{}",
+                    location.line + 1,
+                    location.column + 1,
+                    token.loc.start,
+                    token.loc.end,
+                    token.tag.name(),
+                    csv_field(&text)
+                )?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn tokenize(options: Options) -> io::Result<bool> {
+    let mut inputs = Vec::new();
+    let mut visited = HashSet::new();
+    for path in &options.paths {
+        collect_inputs(path, &mut inputs, &mut visited)
+            .map_err(|err| io::Error::new(err.kind(), format!("{}: {err}", path.display())))?;
+    }
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    let mut stats = Stats::default();
+    let mut ok = true;
+    // JSON is always an object keyed by path, even for a single file or
+    // stdin, so scripts see one shape whatever the arguments.
+    let json_object = options.format == OutputFormat::Json;
+    if json_object && !options.stats {
+        writeln!(out, "{{")?;
+    } else if options.format == OutputFormat::Csv && !options.stats {
+        writeln!(out, "path,line,column,start,end,tag,text")?;
+    }
+    let mut first = true;
+    for path in &inputs {
+        let name = display_path(path);
+        let source = match read_input(path) {
+            Ok(source) => source,
+            Err(err) => {
+                out.flush()?;
+                eprintln!("{name}: error: {err}");
+                ok = false;
+                continue;
+            }
+        };
+        let mut stream = if options.trivia {
+            TokenStream::with_trivia(&source)
+        } else {
+            TokenStream::new(&source)
+        }
+        .language_version(options.version);
+        let tokens: Vec<Token> = stream.by_ref().collect();
+        if !stream.errors().is_empty() {
+            out.flush()?;
+            let lines = LineIndex::new(&source);
+            for error in stream.errors() {
+                eprintln!(
+                    "{name}:{}: error: {}",
+                    lines.location(error.offset),
+                    error.kind.message()
+                );
+            }
+            ok = false;
+        }
+
+        if options.stats {
+            stats.files += 1;
+            stats.bytes += source.len();
+            stats.errors += stream.errors().len();
+            tokens.iter().for_each(|token| stats.add(&token.tag));
+        } else if json_object {
+            let mut array = Vec::new();
+            write_tokens(&mut array, &name, &source, tokens, options.format)?;
+            let comma = if first { "" } else { ",\n" };
+            write!(out, "{comma}{}: ", json_string(&name))?;
+            out.write_all(array.trim_ascii_end())?;
+        } else {
+            if options.format == OutputFormat::Human && inputs.len() > 1 {
+                let gap = if first { "" } else { "\n" };
+                writeln!(out, "{gap}==> {name} <==")?;
+            }
+            write_tokens(&mut out, &name, &source, tokens, options.format)?;
+        }
+        first = false;
+    }
+    if options.stats {
+        stats.write(&mut out, options.format)?;
+    } else if json_object {
+        writeln!(out, "\n}}")?;
+    }
+    out.flush()?;
+    Ok(ok)
+}
+
+fn main() -> ExitCode {
+    let command = match parse_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match command {
+        Command::Help => {
+            print!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Command::Tokenize(options) => match tokenize(options) {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::FAILURE,
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {err}");
+                ExitCode::FAILURE
+            }
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        match parse_args(args.iter().map(|arg| arg.to_string()))? {
+            Command::Tokenize(options) => Ok(options),
+            Command::Help => Err("help".into()),
+        }
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = parse(&[
+            "tokenize",
+            "--format=csv",
+            "--zig",
+            "0.11",
+            "a.zig",
+            "--",
+            "-b",
+        ])
+        .unwrap();
+        assert_eq!(options.format, OutputFormat::Csv);
+        assert_eq!(options.version, LanguageVersion::V0_11);
+        assert_eq!(options.paths, [PathBuf::from("a.zig"), PathBuf::from("-b")]);
+
+        let options = parse(&["tokenize", "-f", "json", "--stats"]).unwrap();
+        assert_eq!(options.format, OutputFormat::Json);
+        assert!(options.stats);
+        assert_eq!(options.paths, [PathBuf::from("-")]);
+
+        assert!(parse(&["tokenize", "--format"]).is_err());
+        assert!(parse(&["tokenize", "--format", "xml"]).is_err());
+        assert!(parse(&["tokenize", "--zig=0.10"]).is_err());
+        assert!(parse(&["tokenize", "--bogus"]).is_err());
+        assert!(parse(&["parse"]).is_err());
+        assert!(parse(&[]).is_err());
+    }
+
+    #[test]
+    fn test_output() {
+        let source = b"const s = \"a,\\\"b\";";
+        let tokens: Vec<Token> = TokenStream::new(source).collect();
+        let render = |format| {
+            let mut out = Vec::new();
+            write_tokens(&mut out, "x.zig", source, tokens.clone(), format).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        let human = render(OutputFormat::Human);
+        assert!(human.starts_with(&format!("1:1       {:<32}\"const\"\n", "keyword_const")));
+        assert!(human.contains("string_literal"));
+        let csv = render(OutputFormat::Csv);
+        assert!(csv.contains("x.zig,1,11,10,17,string_literal,\"\"\"a,\\\"\"b\"\"\"\n"));
+        assert!(render(OutputFormat::Json).contains(r#""line":1,"column":11}"#));
+        assert_eq!(json_string("a\"\\\n\t"), r#""a\"\\\n\u0009""#);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_inputs_symlink_loop() {
+        let root = std::env::temp_dir().join(format!("zig-in-rust-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(root.join("a.zig"), "").unwrap();
+        fs::write(root.join("sub/b.zig"), "").unwrap();
+        std::os::unix::fs::symlink("..", root.join("sub/up")).unwrap();
+        std::os::unix::fs::symlink("sub", root.join("link")).unwrap();
+
+        let mut inputs = Vec::new();
+        let result = collect_inputs(&root, &mut inputs, &mut HashSet::new());
+        fs::remove_dir_all(&root).unwrap();
+        result.unwrap();
+        assert_eq!(inputs, [root.join("a.zig"), root.join("link/b.zig")]);
+    }
+}